@@ -0,0 +1,83 @@
+//! Running a script with its output kept, for tests
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::{error::InterpreterError, Lox};
+
+/// What a script printed and how it ended
+#[derive(Debug)]
+pub struct Captured {
+    /// What `print` wrote
+    pub stdout: String,
+    /// What `eprint` wrote, an uncaught error is left in `result`
+    pub stderr: String,
+    /// How the script ended
+    pub result: Result<(), InterpreterError>,
+}
+
+/// Runs a script with a fresh [`Lox`], keeping its output rather than
+/// printing it
+pub fn run_captured(script: &str) -> Captured {
+    let stdout = Buffer::default();
+    let stderr = Buffer::default();
+    let mut lox = Lox::new();
+    lox.set_output(Box::new(stdout.clone()));
+    lox.set_error_output(Box::new(stderr.clone()));
+    let result = lox.run(script);
+    Captured {
+        stdout: stdout.contents(),
+        stderr: stderr.contents(),
+        result,
+    }
+}
+
+/// Bytes written through one clone can be read through another
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::InterpreterError;
+
+    use super::run_captured;
+
+    #[test]
+    fn captures_both_streams() {
+        let captured = run_captured("print 1; eprint(\"warning\"); print \"two\";");
+        assert_eq!("1\ntwo\n", captured.stdout);
+        assert_eq!("warning\n", captured.stderr);
+        assert!(captured.result.is_ok());
+    }
+
+    #[test]
+    fn keeps_output_before_an_error() {
+        let captured = run_captured("print \"before\"; print nope; print \"after\";");
+        assert_eq!("before\n", captured.stdout);
+        assert_eq!("", captured.stderr);
+        assert!(matches!(
+            captured.result,
+            Err(InterpreterError::UndefinedVariable(name)) if name == "nope"
+        ));
+    }
+}
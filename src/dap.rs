@@ -0,0 +1,707 @@
+//! A Debug Adapter Protocol server over stdio, started with `lox dap`
+//!
+//! The script runs once both `launch` and `configurationDone` have been
+//! received. While it is stopped, requests are served from inside the
+//! interpreter's execute hook, so the variables shown are those of the live
+//! environment.
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    debugger::Mode,
+    environment::Environment,
+    error::InterpreterError,
+    interpreter::{ExecuteHook, Frame, Interpreter, Unwind},
+    json::{read_message, write_message, Json},
+    parser::LoxParser,
+    syntax::{Expr, Stmt, StmtKind, Target},
+    token::TokenMeta,
+};
+
+/// The only thread, scripts are single threaded
+const THREAD_ID: usize = 1;
+
+/// Serves requests from `input` until it ends or the client disconnects
+pub fn serve<R, W>(input: R, output: W) -> Result<(), InterpreterError>
+where
+    R: BufRead + 'static,
+    W: Write + 'static,
+{
+    let adapter = Rc::new(RefCell::new(Adapter::new(input, output)));
+    loop {
+        let request = match adapter.borrow_mut().read()? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        let action = adapter.borrow_mut().handle(&request, None)?;
+        match action {
+            Action::Wait | Action::Resume => {}
+            Action::Run => run(&adapter)?,
+            Action::Disconnect => return Ok(()),
+        }
+        if adapter.borrow().disconnected {
+            return Ok(());
+        }
+    }
+}
+
+fn run<R, W>(adapter: &Rc<RefCell<Adapter<R, W>>>) -> Result<(), InterpreterError>
+where
+    R: BufRead + 'static,
+    W: Write + 'static,
+{
+    let statements = match adapter.borrow_mut().program.take() {
+        Some(program) => program.statements,
+        None => return Ok(()),
+    };
+    let mut interpreter = Interpreter::new();
+    interpreter.set_file(Path::new(&adapter.borrow().path));
+    interpreter.set_input(Box::new(NoInput));
+    interpreter.set_hook(Box::new(Hook(adapter.clone())));
+    interpreter.set_output(Box::new(OutputEvents {
+        adapter: adapter.clone(),
+        category: "stdout",
+        line: Vec::new(),
+    }));
+    interpreter.set_error_output(Box::new(OutputEvents {
+        adapter: adapter.clone(),
+        category: "stderr",
+        line: Vec::new(),
+    }));
+    let result = interpreter.interpret(&statements);
+    // flushes the output of a script that doesn't end in a newline
+    drop(interpreter);
+
+    let mut adapter = adapter.borrow_mut();
+    let exit_code = match result {
+        Ok(()) => 0,
+        Err(InterpreterError::Unwind(Unwind::Interrupted)) if adapter.disconnected => return Ok(()),
+        Err(err) => {
+            let output = format!("{err}\n");
+            adapter.event(
+                "output",
+                Json::object([("category", "stderr".into()), ("output", output.into())]),
+            )?;
+            70
+        }
+    };
+    adapter.event("exited", Json::object([("exitCode", exit_code.into())]))?;
+    adapter.event("terminated", Json::object([]))?;
+    Ok(())
+}
+
+struct Program {
+    statements: Vec<Stmt>,
+    /// The lines a statement starts on, where breakpoints can be verified
+    lines: Vec<usize>,
+}
+
+/// The lines statements start on in the script at `path`, none if it
+/// can't be read or parsed
+fn file_lines(path: &Path) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let source = std::fs::read_to_string(path).unwrap_or_default();
+    if let Ok(statements) = LoxParser::new(&source).parse() {
+        statement_lines(&statements, &mut lines);
+    }
+    lines
+}
+
+/// A path as the file system resolves it, so that two paths to the same
+/// file are equal
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.into())
+}
+
+/// Adds the lines of `statements` and of every statement in their
+/// blocks and functions
+fn statement_lines(statements: &[Stmt], lines: &mut Vec<usize>) {
+    for stmt in statements {
+        lines.push(stmt.meta.row);
+        match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Print(expr) | StmtKind::Throw(expr) => {
+                expr_lines(expr, lines)
+            }
+            StmtKind::Var(_, expr) | StmtKind::Return(expr) => {
+                expr.iter().for_each(|expr| expr_lines(expr, lines))
+            }
+            StmtKind::Block(statements) => statement_lines(statements, lines),
+            StmtKind::Try(statement) => {
+                statement_lines(&statement.body, lines);
+                if let Some(catch) = &statement.catch {
+                    statement_lines(&catch.body, lines);
+                }
+                if let Some(finally) = &statement.finally {
+                    statement_lines(finally, lines);
+                }
+            }
+            StmtKind::Import(_) => {}
+        }
+    }
+}
+
+/// Adds the lines of the statements in the functions `expr` makes
+fn expr_lines(expr: &Expr, lines: &mut Vec<usize>) {
+    let mut exprs = |exprs: &[&Expr]| exprs.iter().for_each(|expr| expr_lines(expr, lines));
+    match expr {
+        Expr::Assign(assign) => exprs(&[&assign.value]),
+        Expr::Binary(binary) => exprs(&[&binary.left, &binary.right]),
+        Expr::Call(call) => {
+            exprs(&[&call.callee]);
+            call.arguments
+                .iter()
+                .for_each(|expr| expr_lines(expr, lines));
+        }
+        Expr::Comma(comma) => exprs(&[&comma.left, &comma.right]),
+        Expr::Conditional(conditional) => exprs(&[
+            &conditional.condition,
+            &conditional.then_branch,
+            &conditional.else_branch,
+        ]),
+        Expr::Function(function) => statement_lines(&function.body, lines),
+        Expr::Grouping(grouping) => exprs(&[&grouping.expression]),
+        Expr::Index(index) => exprs(&[&index.object, &index.index]),
+        Expr::Interpolation(parts) | Expr::List(parts) => {
+            parts.iter().for_each(|expr| expr_lines(expr, lines))
+        }
+        Expr::Literal(_) => {}
+        Expr::Map(entries) => entries.iter().for_each(|(key, value)| {
+            expr_lines(key, lines);
+            expr_lines(value, lines);
+        }),
+        Expr::SetIndex(set) => exprs(&[&set.object, &set.index, &set.value]),
+        Expr::Unary(unary) => exprs(&[&unary.expression]),
+        Expr::Update(update) => {
+            if let Target::Index(index) = &update.target {
+                exprs(&[&index.object, &index.index]);
+            }
+            expr_lines(&update.value, lines);
+        }
+    }
+}
+
+/// What the script should do after a request is handled
+enum Action {
+    Wait,
+    Resume,
+    Run,
+    Disconnect,
+}
+
+/// Where the script is stopped
+struct Stopped<'a> {
+    environment: &'a Environment,
+    file: &'a Path,
+    meta: &'a TokenMeta,
+    /// The calls the statement is in, innermost last
+    calls: &'a [Frame],
+}
+
+impl Stopped<'_> {
+    /// Calls `f` with the innermost scope of the stack frame `frame` steps
+    /// out from the one stopped in, none if there is no such frame
+    fn scope<T>(&self, frame: usize, f: impl FnOnce(&Environment) -> T) -> Option<T> {
+        if frame == 0 {
+            return Some(f(self.environment));
+        }
+        let call = self.calls.len().checked_sub(frame)?;
+        Some(f(&self.calls[call].environment.borrow()))
+    }
+
+    /// The variables of the scope `index` steps out from the innermost of
+    /// the stack frame `frame`, formatted for display
+    fn variables(&self, frame: usize, index: usize) -> Option<Vec<(String, String)>> {
+        self.scope(frame, |environment| {
+            let format = |scope: &Environment| {
+                scope
+                    .variables()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect()
+            };
+            if index == 0 {
+                return Some(format(environment));
+            }
+            let mut next = environment.enclosing();
+            let mut depth = 1;
+            while let Some(enclosing) = next {
+                let scope = enclosing.borrow();
+                if depth == index {
+                    return Some(format(&scope));
+                }
+                next = scope.enclosing();
+                depth += 1;
+            }
+            None
+        })?
+    }
+
+    fn scope_count(&self, frame: usize) -> usize {
+        self.scope(frame, |environment| {
+            let mut count = 1;
+            let mut next = environment.enclosing();
+            while let Some(enclosing) = next {
+                next = enclosing.borrow().enclosing();
+                count += 1;
+            }
+            count
+        })
+        .unwrap_or(0)
+    }
+}
+
+struct Adapter<R, W> {
+    input: R,
+    output: W,
+    seq: usize,
+    program: Option<Program>,
+    /// The path of the launched program, kept after it starts running
+    path: String,
+    configured: bool,
+    /// The lines to stop at, by the canonical path of their file
+    breakpoints: HashMap<PathBuf, Vec<usize>>,
+    /// The canonical paths of the files statements have run in
+    canonical: HashMap<PathBuf, PathBuf>,
+    /// The stack frame and scope index of each variables reference given
+    /// out, a reference is its position in here plus one
+    scopes: Vec<(usize, usize)>,
+    mode: Mode,
+    /// How many calls the statement the script is stopped at is in
+    depth: usize,
+    stop_on_entry: bool,
+    disconnected: bool,
+}
+
+impl<R: BufRead, W: Write> Adapter<R, W> {
+    fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            seq: 0,
+            program: None,
+            path: String::new(),
+            configured: false,
+            breakpoints: HashMap::new(),
+            canonical: HashMap::new(),
+            scopes: Vec::new(),
+            mode: Mode::Continue,
+            depth: 0,
+            stop_on_entry: false,
+            disconnected: false,
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        loop {
+            let Some(body) = read_message(&mut self.input)? else {
+                return Ok(None);
+            };
+            // there is no request to answer for a message that isn't JSON
+            if let Ok(request) = Json::parse(&body) {
+                return Ok(Some(request));
+            }
+        }
+    }
+
+    fn send(&mut self, kind: &str, mut fields: Vec<(String, Json)>) -> io::Result<()> {
+        self.seq += 1;
+        fields.insert(0, ("seq".into(), self.seq.into()));
+        fields.insert(1, ("type".into(), kind.into()));
+        write_message(&mut self.output, &Json::Object(fields))
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        let fields = vec![("event".into(), event.into()), ("body".into(), body)];
+        self.send("event", fields)
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) -> io::Result<()> {
+        let fields = vec![
+            ("request_seq".into(), request_seq(request)),
+            ("success".into(), true.into()),
+            ("command".into(), command(request).into()),
+            ("body".into(), body),
+        ];
+        self.send("response", fields)
+    }
+
+    fn fail(&mut self, request: &Json, message: String) -> io::Result<()> {
+        let fields = vec![
+            ("request_seq".into(), request_seq(request)),
+            ("success".into(), false.into()),
+            ("command".into(), command(request).into()),
+            ("message".into(), message.into()),
+        ];
+        self.send("response", fields)
+    }
+
+    fn is_breakpoint(&mut self, file: &Path, line: usize) -> bool {
+        if self.breakpoints.is_empty() {
+            return false;
+        }
+        if !self.canonical.contains_key(file) {
+            self.canonical.insert(file.into(), canonical(file));
+        }
+        self.breakpoints
+            .get(&self.canonical[file])
+            .is_some_and(|lines| lines.contains(&line))
+    }
+
+    /// The lines statements start on in the file at `path`, where
+    /// breakpoints can be verified once a program is launched
+    fn statement_lines(&self, path: &Path) -> Option<Vec<usize>> {
+        let program = self.program.as_ref()?;
+        if canonical(path) == canonical(Path::new(&self.path)) {
+            Some(program.lines.clone())
+        } else {
+            Some(file_lines(path))
+        }
+    }
+
+    fn handle(&mut self, request: &Json, stopped: Option<&Stopped>) -> io::Result<Action> {
+        let arguments = request.get("arguments").unwrap_or(&Json::Null);
+        match command(request) {
+            "initialize" => {
+                let capabilities =
+                    Json::object([("supportsConfigurationDoneRequest", true.into())]);
+                self.respond(request, capabilities)?;
+                self.event("initialized", Json::object([]))?;
+            }
+            "launch" => match self.launch(arguments) {
+                Ok(()) => {
+                    self.respond(request, Json::object([]))?;
+                    if self.configured {
+                        return Ok(Action::Run);
+                    }
+                }
+                Err(message) => self.fail(request, message)?,
+            },
+            "setBreakpoints" => {
+                let path = arguments
+                    .get("source")
+                    .and_then(|source| source.get("path"))
+                    .and_then(Json::as_str)
+                    .unwrap_or_default();
+                let lines: Vec<_> = arguments
+                    .get("breakpoints")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint.get("line")?.as_usize())
+                    .collect();
+                let statement_lines = self.statement_lines(Path::new(path));
+                let breakpoints = lines
+                    .iter()
+                    .map(|&line| {
+                        let verified = statement_lines
+                            .as_ref()
+                            .is_none_or(|statement_lines| statement_lines.contains(&line));
+                        Json::object([("verified", verified.into()), ("line", line.into())])
+                    })
+                    .collect::<Vec<_>>();
+                self.breakpoints.insert(canonical(Path::new(path)), lines);
+                self.respond(request, Json::object([("breakpoints", breakpoints.into())]))?;
+            }
+            "setExceptionBreakpoints" => self.respond(request, Json::object([]))?,
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, Json::object([]))?;
+                if self.program.is_some() {
+                    return Ok(Action::Run);
+                }
+            }
+            "threads" => {
+                let thread = Json::object([("id", THREAD_ID.into()), ("name", "main".into())]);
+                let threads = Json::Array(vec![thread]);
+                self.respond(request, Json::object([("threads", threads)]))?;
+            }
+            "stackTrace" => {
+                let frames = match stopped {
+                    Some(stopped) => (0..=stopped.calls.len())
+                        .map(|frame| self.frame(stopped, frame))
+                        .collect(),
+                    None => vec![],
+                };
+                let total = frames.len();
+                let body = Json::object([
+                    ("stackFrames", frames.into()),
+                    ("totalFrames", total.into()),
+                ]);
+                self.respond(request, body)?;
+            }
+            "scopes" => {
+                let frame = arguments
+                    .get("frameId")
+                    .and_then(Json::as_usize)
+                    .map_or(0, |id| id.saturating_sub(1));
+                let count = stopped.map_or(0, |stopped| stopped.scope_count(frame));
+                let scopes = (0..count)
+                    .map(|index| {
+                        let name = if index + 1 == count {
+                            "Globals"
+                        } else {
+                            "Block"
+                        };
+                        let reference = self.reference(frame, index);
+                        Json::object([
+                            ("name", name.into()),
+                            ("variablesReference", reference.into()),
+                            ("expensive", false.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                self.respond(request, Json::object([("scopes", scopes.into())]))?;
+            }
+            "variables" => {
+                let variables = arguments
+                    .get("variablesReference")
+                    .and_then(Json::as_usize)
+                    .and_then(|reference| self.scopes.get(reference.checked_sub(1)?))
+                    .and_then(|&(frame, index)| stopped?.variables(frame, index))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(name, value)| {
+                        Json::object([
+                            ("name", name.into()),
+                            ("value", value.into()),
+                            ("variablesReference", 0usize.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                self.respond(request, Json::object([("variables", variables.into())]))?;
+            }
+            "continue" | "next" | "stepIn" | "stepOut" => match stopped {
+                Some(_) => {
+                    self.mode = match command(request) {
+                        "continue" => Mode::Continue,
+                        "next" => Mode::Next(self.depth),
+                        "stepIn" => Mode::Step,
+                        _ => Mode::Finish(self.depth),
+                    };
+                    let body = if command(request) == "continue" {
+                        Json::object([("allThreadsContinued", true.into())])
+                    } else {
+                        Json::object([])
+                    };
+                    self.respond(request, body)?;
+                    return Ok(Action::Resume);
+                }
+                None => self.fail(request, "The script is not stopped".into())?,
+            },
+            "disconnect" | "terminate" => {
+                self.disconnected = true;
+                self.respond(request, Json::object([]))?;
+                return Ok(Action::Disconnect);
+            }
+            command => self.fail(request, format!("Unsupported request '{command}'"))?,
+        }
+        Ok(Action::Wait)
+    }
+
+    fn launch(&mut self, arguments: &Json) -> Result<(), String> {
+        let path = arguments
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("launch needs a program")?;
+        let source = std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        let statements = LoxParser::new(&source)
+            .parse()
+            .map_err(|err| InterpreterError::from(err).to_string())?;
+        let mut lines = Vec::new();
+        statement_lines(&statements, &mut lines);
+
+        self.stop_on_entry = arguments
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        if self.stop_on_entry {
+            self.mode = Mode::Step;
+        }
+        self.path = path.into();
+        self.program = Some(Program { statements, lines });
+        Ok(())
+    }
+
+    /// The variables reference of the scope `index` steps out from the
+    /// innermost of the stack frame `frame`
+    fn reference(&mut self, frame: usize, index: usize) -> usize {
+        let position = self
+            .scopes
+            .iter()
+            .position(|&scope| scope == (frame, index));
+        position.unwrap_or_else(|| {
+            self.scopes.push((frame, index));
+            self.scopes.len() - 1
+        }) + 1
+    }
+
+    /// The stack frame `frame` steps out from the one stopped in, the
+    /// frames outside it are stopped at the calls they made
+    fn frame(&self, stopped: &Stopped, frame: usize) -> Json {
+        let calls = stopped.calls.len();
+        let (file, meta) = match frame {
+            0 => (stopped.file, stopped.meta),
+            frame => {
+                let call = &stopped.calls[calls - frame];
+                let file = call.file.as_deref().unwrap_or(Path::new(&self.path));
+                (file, &call.location)
+            }
+        };
+        let function = match calls.checked_sub(frame + 1) {
+            Some(call) => stopped.calls[call].name.as_deref().unwrap_or("<anonymous>"),
+            None => "<script>",
+        };
+        let path = file.to_string_lossy();
+        let name = file
+            .file_name()
+            .map_or(path.clone(), |name| name.to_string_lossy());
+        Json::object([
+            ("id", (frame + 1).into()),
+            ("name", function.into()),
+            (
+                "source",
+                Json::object([
+                    ("name", name.as_ref().into()),
+                    ("path", path.as_ref().into()),
+                ]),
+            ),
+            ("line", meta.row.into()),
+            ("column", meta.col.into()),
+        ])
+    }
+}
+
+fn command(request: &Json) -> &str {
+    request
+        .get("command")
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+}
+
+fn request_seq(request: &Json) -> Json {
+    request.get("seq").cloned().unwrap_or(Json::Null)
+}
+
+/// Pauses the script and serves requests until one of them resumes it
+struct Hook<R, W>(Rc<RefCell<Adapter<R, W>>>);
+
+impl<R: BufRead, W: Write> ExecuteHook for Hook<R, W> {
+    fn before_execute(
+        &mut self,
+        stmt: &Stmt,
+        file: Option<&Path>,
+        environment: &Environment,
+        _depth: usize,
+        calls: &[Frame],
+    ) -> Result<(), InterpreterError> {
+        let mut adapter = self.0.borrow_mut();
+        // stepping goes by calls, `next` and `stepOut` run a whole call
+        let depth = calls.len();
+        let file = file.map_or_else(|| Cow::Owned(PathBuf::from(&adapter.path)), Cow::Borrowed);
+        let reason = if adapter.mode.pauses_at(depth) {
+            if std::mem::take(&mut adapter.stop_on_entry) {
+                "entry"
+            } else {
+                "step"
+            }
+        } else if adapter.is_breakpoint(&file, stmt.meta.row) {
+            "breakpoint"
+        } else {
+            return Ok(());
+        };
+        adapter.depth = depth;
+        adapter.event(
+            "stopped",
+            Json::object([
+                ("reason", reason.into()),
+                ("threadId", THREAD_ID.into()),
+                ("allThreadsStopped", true.into()),
+            ]),
+        )?;
+
+        let stopped = Stopped {
+            environment,
+            file: &file,
+            meta: &stmt.meta,
+            calls,
+        };
+        loop {
+            // without a client nobody can resume the script
+            let request = adapter.read()?.ok_or(Unwind::Interrupted)?;
+            match adapter.handle(&request, Some(&stopped))? {
+                Action::Wait | Action::Run => {}
+                Action::Resume => return Ok(()),
+                Action::Disconnect => return Err(Unwind::Interrupted.into()),
+            }
+        }
+    }
+}
+
+/// What scripts read under the adapter, whose stdin is the protocol
+struct NoInput;
+
+impl NoInput {
+    fn error() -> io::Error {
+        io::Error::other("Scripts have no input under lox dap")
+    }
+}
+
+impl Read for NoInput {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(Self::error())
+    }
+}
+
+impl BufRead for NoInput {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Err(Self::error())
+    }
+
+    fn consume(&mut self, _: usize) {}
+}
+
+/// Sends what the script prints as `output` events, a line at a time
+struct OutputEvents<R: BufRead, W: Write> {
+    adapter: Rc<RefCell<Adapter<R, W>>>,
+    category: &'static str,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead, W: Write> Write for OutputEvents<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        if self.line.ends_with(b"\n") {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+        let output = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        self.adapter.borrow_mut().event(
+            "output",
+            Json::object([
+                ("category", self.category.into()),
+                ("output", output.into()),
+            ]),
+        )
+    }
+}
+
+impl<R: BufRead, W: Write> Drop for OutputEvents<R, W> {
+    fn drop(&mut self) {
+        // there is nobody to report a failure to
+        let _ = self.flush();
+    }
+}
@@ -0,0 +1,256 @@
+//! An interactive debugger, started with `lox debug file.lox`
+//!
+//! The script pauses before its first statement, and afterwards whenever
+//! it reaches a breakpoint or finishes a step.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    environment::Environment,
+    error::InterpreterError,
+    interpreter::{ExecuteHook, Frame, Unwind},
+    syntax::Stmt,
+};
+
+const HELP: &str = "\
+break [file:]line  pause before statements on a line
+step               run to the next statement, entering blocks
+next               run to the next statement in this block or an outer one
+finish             run until this block is left
+continue           run until a breakpoint
+vars               show the variables of every enclosing scope
+print name         show the value of a variable
+quit               stop the script
+";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Breakpoint {
+    file: Option<String>,
+    line: usize,
+}
+
+impl Breakpoint {
+    fn parse(location: &str) -> Option<Self> {
+        match location.rsplit_once(':') {
+            Some((file, line)) => Some(Self {
+                file: Some(file.into()),
+                line: line.parse().ok()?,
+            }),
+            None => Some(Self {
+                file: None,
+                line: location.parse().ok()?,
+            }),
+        }
+    }
+
+    fn matches(&self, file: &str, line: usize) -> bool {
+        self.line == line
+            && self
+                .file
+                .as_ref()
+                .is_none_or(|name| Path::new(file).ends_with(name))
+    }
+}
+
+/// When to pause next, the depths are those of the statement paused at
+#[derive(Debug, Clone, Copy)]
+pub enum Mode {
+    Step,
+    Next(usize),
+    Finish(usize),
+    Continue,
+}
+
+impl Mode {
+    /// Whether stepping stops before a statement at `depth`
+    pub fn pauses_at(self, depth: usize) -> bool {
+        match self {
+            Mode::Step => true,
+            Mode::Next(from) => depth <= from,
+            Mode::Finish(from) => depth < from,
+            Mode::Continue => false,
+        }
+    }
+}
+
+pub struct Debugger<R, W> {
+    /// The main script, the file of statements that aren't in one
+    file: PathBuf,
+    /// The lines of every file a statement was paused in, by its path
+    sources: HashMap<PathBuf, Vec<String>>,
+    input: R,
+    output: W,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(file: String, source: &str, input: R, output: W) -> Self {
+        let lines = source.lines().map(Into::into).collect();
+        let file = PathBuf::from(file);
+        Self {
+            sources: HashMap::from([(file.clone(), lines)]),
+            file,
+            input,
+            output,
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+        }
+    }
+
+    fn should_pause(&self, stmt: &Stmt, file: &Path, depth: usize) -> bool {
+        let file = file.to_string_lossy();
+        self.mode.pauses_at(depth)
+            || self
+                .breakpoints
+                .iter()
+                .any(|breakpoint| breakpoint.matches(&file, stmt.meta.row))
+    }
+
+    /// The text of a line of `file`, read the first time it is shown
+    fn line(&mut self, file: &Path, line: usize) -> &str {
+        let lines = self.sources.entry(file.into()).or_insert_with(|| {
+            fs::read_to_string(file)
+                .map(|source| source.lines().map(Into::into).collect())
+                .unwrap_or_default()
+        });
+        lines.get(line - 1).map_or("", |text| text.trim())
+    }
+
+    fn show_variables(&mut self, environment: &Environment) -> Result<(), InterpreterError> {
+        self.show_scope(0, environment)?;
+        let mut depth = 1;
+        let mut next = environment.enclosing();
+        while let Some(enclosing) = next {
+            let scope = enclosing.borrow();
+            self.show_scope(depth, &scope)?;
+            next = scope.enclosing();
+            depth += 1;
+        }
+        Ok(())
+    }
+
+    fn show_scope(&mut self, depth: usize, scope: &Environment) -> Result<(), InterpreterError> {
+        writeln!(self.output, "scope {depth}")?;
+        for (name, value) in scope.variables() {
+            writeln!(self.output, "  {name} = {value}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads commands until one of them resumes the script
+    fn prompt(&mut self, environment: &Environment, depth: usize) -> Result<(), InterpreterError> {
+        loop {
+            write!(self.output, "(lox) ")?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                // nobody is left to resume the script, so let it run to the end
+                writeln!(self.output)?;
+                self.breakpoints.clear();
+                self.mode = Mode::Continue;
+                return Ok(());
+            }
+            let (command, argument) = line
+                .trim()
+                .split_once(' ')
+                .map_or((line.trim(), ""), |(c, a)| (c, a.trim()));
+
+            match command {
+                "" => {}
+                "b" | "break" => match Breakpoint::parse(argument) {
+                    Some(breakpoint) => {
+                        self.breakpoints.push(breakpoint);
+                        let n = self.breakpoints.len();
+                        writeln!(self.output, "Breakpoint {n} at {argument}")?;
+                    }
+                    None => writeln!(self.output, "Expected a location like file.lox:12")?,
+                },
+                "s" | "step" => {
+                    self.mode = Mode::Step;
+                    return Ok(());
+                }
+                "n" | "next" => {
+                    self.mode = Mode::Next(depth);
+                    return Ok(());
+                }
+                "f" | "finish" => {
+                    self.mode = Mode::Finish(depth);
+                    return Ok(());
+                }
+                "c" | "continue" => {
+                    self.mode = Mode::Continue;
+                    return Ok(());
+                }
+                "v" | "vars" => self.show_variables(environment)?,
+                "p" | "print" => match environment.get(argument) {
+                    Some(value) => writeln!(self.output, "{argument} = {value}")?,
+                    None => writeln!(self.output, "Undefined variable '{argument}'")?,
+                },
+                "q" | "quit" => return Err(Unwind::Interrupted.into()),
+                "h" | "help" => self.output.write_all(HELP.as_bytes())?,
+                _ => writeln!(self.output, "Unknown command '{command}', try 'help'")?,
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> ExecuteHook for Debugger<R, W> {
+    fn before_execute(
+        &mut self,
+        stmt: &Stmt,
+        file: Option<&Path>,
+        environment: &Environment,
+        depth: usize,
+        _calls: &[Frame],
+    ) -> Result<(), InterpreterError> {
+        let file = file.unwrap_or(&self.file);
+        if !self.should_pause(stmt, file, depth) {
+            return Ok(());
+        }
+        let file = file.to_path_buf();
+        let line = stmt.meta.row;
+        let text = self.line(&file, line).to_string();
+        writeln!(self.output, "{}:{line}: {text}", file.display())?;
+        self.prompt(environment, depth)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Breakpoint;
+
+    #[test]
+    fn parse_breakpoints() {
+        assert_eq!(
+            Some(Breakpoint {
+                file: Some("dir/a.lox".into()),
+                line: 12
+            }),
+            Breakpoint::parse("dir/a.lox:12")
+        );
+        assert_eq!(
+            Some(Breakpoint {
+                file: None,
+                line: 3
+            }),
+            Breakpoint::parse("3")
+        );
+        assert_eq!(None, Breakpoint::parse("a.lox:"));
+    }
+
+    #[test]
+    fn match_breakpoints_by_path_suffix() {
+        let breakpoint = Breakpoint::parse("a.lox:2").unwrap();
+
+        assert!(breakpoint.matches("examples/a.lox", 2));
+        assert!(!breakpoint.matches("examples/ba.lox", 2));
+        assert!(!breakpoint.matches("examples/a.lox", 3));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+use crate::value::Value;
+
+/// The variables of one scope, and the scope it is nested in
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+    /// The variables defined by the interpreter rather than the script
+    natives: HashSet<&'static str>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+            natives: HashSet::new(),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.natives.remove(name);
+        self.values.insert(name.into(), value);
+    }
+
+    /// Defines a variable that is part of the language, such as `len` or `PI`
+    pub fn define_native(&mut self, name: &'static str, value: Value) {
+        self.values.insert(name.into(), value);
+        self.natives.insert(name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().get(name),
+        }
+    }
+
+    /// Assigns to an existing variable, returns `false` if there is none
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        match self.values.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    /// Roughly how many bytes a scope and the ones it is nested in hold on
+    /// the heap, unless `seen` shows they were already counted
+    pub fn heap_size(environment: &Rc<RefCell<Self>>, seen: &mut HashSet<*const ()>) -> usize {
+        if !seen.insert(Rc::as_ptr(environment).cast()) {
+            return 0;
+        }
+        let environment = environment.borrow();
+        let variables: usize = environment
+            .values
+            .iter()
+            .map(|(name, value)| name.capacity() + mem::size_of::<Value>() + value.heap_size(seen))
+            .sum();
+        let enclosing = match &environment.enclosing {
+            Some(enclosing) => Self::heap_size(enclosing, seen),
+            None => 0,
+        };
+        mem::size_of::<Self>() + variables + enclosing
+    }
+
+    /// The variables declared in this scope, sorted by name, leaving out
+    /// the natives
+    pub fn variables(&self) -> Vec<(&str, &Value)> {
+        let mut variables: Vec<_> = self
+            .values
+            .iter()
+            .filter(|(name, _)| !self.natives.contains(name.as_str()))
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        variables.sort_by_key(|&(name, _)| name);
+        variables
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::value::Value;
+
+    use super::Environment;
+
+    #[test]
+    fn lookup_through_enclosing() {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        globals.borrow_mut().define("a", 1.0.into());
+        globals.borrow_mut().define("b", 2.0.into());
+
+        let mut inner = Environment::new(globals.clone());
+        inner.define("a", "shadow".into());
+
+        assert_eq!(Some(Value::from("shadow")), inner.get("a"));
+        assert_eq!(Some(Value::from(2.0)), inner.get("b"));
+        assert_eq!(None, inner.get("c"));
+    }
+
+    #[test]
+    fn assign_to_enclosing() {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        globals.borrow_mut().define("a", 1.0.into());
+
+        let mut inner = Environment::new(globals.clone());
+        assert!(inner.assign("a", 3.0.into()));
+        assert!(!inner.assign("b", 3.0.into()));
+
+        assert_eq!(Some(Value::from(3.0)), globals.borrow().get("a"));
+        assert!(inner.variables().is_empty());
+    }
+
+    #[test]
+    fn natives_are_not_listed() {
+        let mut globals = Environment::default();
+        globals.define_native("PI", 3.0.into());
+        globals.define_native("E", 2.0.into());
+        globals.define("a", 1.into());
+        globals.define("E", "redefined".into());
+
+        let names: Vec<_> = globals
+            .variables()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(vec!["E", "a"], names);
+        assert_eq!(Some(Value::from(3.0)), globals.get("PI"));
+    }
+}
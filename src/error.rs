@@ -1,59 +1,267 @@
-use std::{fmt::Display, io};
+//! The errors reading, parsing and running a script can fail with
 
+use std::{fmt::Display, io, path::Path, rc::Rc};
+
+use crate::interpreter::Unwind;
 use crate::token::{Operator, Structure};
 use crate::value::Value;
 
+/// Why a script couldn't be read, parsed or run, its `Display` is the
+/// message `lox` prints
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum InterpreterError {
-    TooManyArgs,
+    /// Reading a script or writing its output failed
     Io(io::Error),
+    /// An I/O failure in a native such as `readFile`, a runtime error
+    /// rather than a failure of `lox` itself
+    NativeIo(io::Error),
+    /// The script has a character or token that can't be scanned
     LexicalError(LexicalError),
+    /// The script's tokens aren't a program
     ParserError(LoxParserError),
+    /// A syntax tree read from JSON that isn't one
+    AstError(AstError),
+    /// An operator or function was given a value of the wrong type
     TypeError(Value),
-}
-
-impl PartialEq for InterpreterError {
-    fn eq(&self, other: &Self) -> bool {
-        use InterpreterError::*;
-        matches!((self, other), (TooManyArgs, TooManyArgs))
-    }
+    /// A variable read or assigned without being declared
+    UndefinedVariable(String),
+    /// Int arithmetic whose result doesn't fit in an int
+    IntegerOverflow,
+    /// Int division or remainder by zero
+    DivisionByZero,
+    /// Bitwise operators only work on ints
+    NotAnInt(Value),
+    /// A shift by a negative amount or by 64 bits or more
+    InvalidShift(i64),
+    /// Only ints can index a list
+    InvalidIndex(Value),
+    /// An index below zero
+    NegativeIndex(i64),
+    /// The index and the length of the list
+    IndexOutOfBounds(usize, usize),
+    /// The start and end of a substring and the length of the string, in chars
+    RangeOutOfBounds(i64, i64, usize),
+    /// How many times to repeat a string
+    InvalidCount(i64),
+    /// How many `{}` a format string has and how many values it was given
+    FormatArguments(usize, usize),
+    /// `pop` on a list with no elements
+    EmptyList,
+    /// Only strings, numbers, booleans and nil can be map keys
+    InvalidKey(Value),
+    /// A key read or removed that the map doesn't have
+    UndefinedKey(Value),
+    /// How many arguments a function takes and how many it was called with
+    WrongArity(usize, usize),
+    /// The least a variadic function takes and how many it was called with
+    TooFewArguments(usize, usize),
+    /// Recursion went deeper than the interpreter can follow
+    NestingTooDeep,
+    /// The script ran more than the limit of steps
+    StepLimit(u64),
+    /// Function calls nested deeper than the limit
+    CallDepthLimit(usize),
+    /// The script's values took more than the limit of bytes
+    HeapLimit(usize),
+    /// The script was still running at its deadline
+    DeadlineExceeded,
+    /// The script stopped other than by failing: a `throw` no `catch`
+    /// caught, a `return` outside a function or the debugger quitting,
+    /// only its message is public
+    Unwind(Unwind),
+    /// An error from running an imported module
+    InModule(Rc<Path>, Box<InterpreterError>),
+    /// The modules that import each other, starting and ending with the same one
+    ImportCycle(Vec<Rc<Path>>),
+    /// The path as written in `from` and the name it doesn't export
+    UndefinedExport(String, String),
 }
 
 impl Display for InterpreterError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            InterpreterError::TooManyArgs => f.write_str("Error: Too many arguments"),
-            InterpreterError::Io(err) => f.write_fmt(format_args!("IoError: {err}")),
-            InterpreterError::LexicalError(LexicalError::UnexpectedChar(char, row, col)) => f
-                .write_fmt(format_args!(
-                    "[{row}:{col}] LexicalError: Unexpected {char:?}"
-                )),
-            InterpreterError::LexicalError(LexicalError::UnterminatedString(row, col)) => f
-                .write_fmt(format_args!(
-                    "[{row}:{col}] starts a string that is not terminated"
-                )),
-            InterpreterError::LexicalError(LexicalError::ParseNumberError(row, col)) => {
-                f.write_fmt(format_args!("[{row}:{col}] is an invalid number"))
-            }
-            InterpreterError::ParserError(err) => f.write_fmt(format_args!("{err:?}")),
+            InterpreterError::Io(err) | InterpreterError::NativeIo(err) => {
+                f.write_fmt(format_args!("IoError: {err}"))
+            }
+            InterpreterError::LexicalError(err)
+            | InterpreterError::ParserError(LoxParserError::LexicalError(err)) => {
+                let (row, col) = err.position();
+                f.write_fmt(format_args!("[{row}:{col}] LexicalError: {err}"))
+            }
+            InterpreterError::ParserError(err) => match err.position() {
+                Some((row, col)) => f.write_fmt(format_args!("[{row}:{col}] ParserError: {err}")),
+                None => f.write_fmt(format_args!("ParserError: {err}")),
+            },
+            InterpreterError::AstError(err) => f.write_fmt(format_args!("AstError: {err}")),
             InterpreterError::TypeError(value) => {
-                f.write_fmt(format_args!("Type error: {value:?}"))
+                f.write_fmt(format_args!("Type error: {}", value.described()))
+            }
+            InterpreterError::UndefinedVariable(name) => {
+                f.write_fmt(format_args!("Undefined variable '{name}'"))
+            }
+            InterpreterError::IntegerOverflow => f.write_str("Integer overflow"),
+            InterpreterError::DivisionByZero => f.write_str("Division by zero"),
+            InterpreterError::NotAnInt(value) => {
+                f.write_fmt(format_args!("Expected an int, got {}", value.described()))
+            }
+            InterpreterError::InvalidShift(n) => f.write_fmt(format_args!("Can't shift by {n}")),
+            InterpreterError::InvalidIndex(value) => {
+                f.write_fmt(format_args!("Invalid index {}", value.described()))
+            }
+            InterpreterError::NegativeIndex(n) => f.write_fmt(format_args!("Negative index {n}")),
+            InterpreterError::IndexOutOfBounds(i, len) => f.write_fmt(format_args!(
+                "Index {i} out of bounds for list of length {len}"
+            )),
+            InterpreterError::RangeOutOfBounds(start, end, len) => f.write_fmt(format_args!(
+                "Range {start}..{end} out of bounds for string of length {len}"
+            )),
+            InterpreterError::InvalidCount(n) => {
+                f.write_fmt(format_args!("Can't repeat a string {n} times"))
+            }
+            InterpreterError::FormatArguments(holes, n) => f.write_fmt(format_args!(
+                "Format string has {holes} '{{}}' but got {n} values"
+            )),
+            InterpreterError::EmptyList => f.write_str("Can't pop from an empty list"),
+            InterpreterError::InvalidKey(value) => f.write_fmt(format_args!(
+                "Map keys must be strings, numbers, booleans or nil, not {value}"
+            )),
+            InterpreterError::UndefinedKey(Value::String(key)) => {
+                f.write_fmt(format_args!("Undefined key {key:?}"))
+            }
+            InterpreterError::UndefinedKey(key) => f.write_fmt(format_args!("Undefined key {key}")),
+            InterpreterError::WrongArity(1, n) => {
+                f.write_fmt(format_args!("Expected 1 argument but got {n}"))
+            }
+            InterpreterError::WrongArity(arity, n) => {
+                f.write_fmt(format_args!("Expected {arity} arguments but got {n}"))
+            }
+            InterpreterError::TooFewArguments(1, n) => {
+                f.write_fmt(format_args!("Expected at least 1 argument but got {n}"))
+            }
+            InterpreterError::TooFewArguments(arity, n) => f.write_fmt(format_args!(
+                "Expected at least {arity} arguments but got {n}"
+            )),
+            InterpreterError::NestingTooDeep => f.write_str("Nesting too deep"),
+            InterpreterError::StepLimit(n) => {
+                f.write_fmt(format_args!("Step limit of {n} exceeded"))
+            }
+            InterpreterError::CallDepthLimit(n) => {
+                f.write_fmt(format_args!("Call depth limit of {n} exceeded"))
+            }
+            InterpreterError::HeapLimit(n) => {
+                f.write_fmt(format_args!("Heap limit of {n} bytes exceeded"))
+            }
+            InterpreterError::DeadlineExceeded => f.write_str("Deadline exceeded"),
+            InterpreterError::Unwind(unwind) => Display::fmt(unwind, f),
+            InterpreterError::InModule(path, err) => {
+                f.write_fmt(format_args!("{}: {err}", path.display()))
+            }
+            InterpreterError::ImportCycle(cycle) => {
+                f.write_str("Import cycle: ")?;
+                for (i, path) in cycle.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" -> ")?;
+                    }
+                    f.write_fmt(format_args!("{}", path.display()))?;
+                }
+                Ok(())
+            }
+            InterpreterError::UndefinedExport(path, name) => {
+                f.write_fmt(format_args!("Module {path:?} has no '{name}'"))
             }
         }
     }
 }
 
+impl InterpreterError {
+    /// Whether a `catch` can catch this, rather than it being about how the
+    /// script is written or run
+    pub fn is_catchable(&self) -> bool {
+        match self {
+            InterpreterError::LexicalError(_)
+            | InterpreterError::ParserError(_)
+            | InterpreterError::AstError(_)
+            | InterpreterError::Unwind(Unwind::Return(_)) => false,
+            InterpreterError::InModule(_, err) => err.is_catchable(),
+            err => !err.stops_script(),
+        }
+    }
+
+    /// Whether this ends the script at once, without running `finally`
+    pub fn stops_script(&self) -> bool {
+        matches!(
+            self,
+            InterpreterError::Unwind(Unwind::Interrupted)
+                | InterpreterError::StepLimit(_)
+                | InterpreterError::CallDepthLimit(_)
+                | InterpreterError::HeapLimit(_)
+                | InterpreterError::DeadlineExceeded
+        )
+    }
+
+    /// Notes the module an error came from, unless it already does or it
+    /// isn't an error in the module's code
+    pub fn in_module(self, path: &Rc<Path>) -> Self {
+        match self {
+            InterpreterError::Unwind(Unwind::Thrown(_) | Unwind::Return(_))
+            | InterpreterError::InModule(..)
+            | InterpreterError::ImportCycle(_) => self,
+            err if err.stops_script() => err,
+            err => InterpreterError::InModule(path.clone(), Box::new(err)),
+        }
+    }
+}
+
+impl From<Unwind> for InterpreterError {
+    fn from(value: Unwind) -> Self {
+        Self::Unwind(value)
+    }
+}
+
 impl From<io::Error> for InterpreterError {
     fn from(value: io::Error) -> Self {
         Self::Io(value)
     }
 }
 
+/// Why the scanner couldn't read a token, with the row and column it
+/// starts at
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum LexicalError {
+    /// A character that doesn't start any token
     UnexpectedChar(char, usize, usize),
+    /// A string still open at the end of the script
     UnterminatedString(usize, usize),
+    /// Digits that aren't a number, such as an int too big for 64 bits
     ParseNumberError(usize, usize),
+    /// A backslash in a string that doesn't start a known escape
+    InvalidEscape(usize, usize),
+}
+
+impl LexicalError {
+    /// The row and column the error was found at
+    pub fn position(&self) -> (usize, usize) {
+        match *self {
+            LexicalError::UnexpectedChar(_, row, col)
+            | LexicalError::UnterminatedString(row, col)
+            | LexicalError::ParseNumberError(row, col)
+            | LexicalError::InvalidEscape(row, col) => (row, col),
+        }
+    }
+}
+
+impl Display for LexicalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexicalError::UnexpectedChar(char, _, _) => {
+                f.write_fmt(format_args!("Unexpected {char:?}"))
+            }
+            LexicalError::UnterminatedString(_, _) => f.write_str("Unterminated string"),
+            LexicalError::ParseNumberError(_, _) => f.write_str("Invalid number"),
+            LexicalError::InvalidEscape(_, _) => f.write_str("Invalid escape sequence"),
+        }
+    }
 }
 
 impl From<LexicalError> for InterpreterError {
@@ -62,15 +270,58 @@ impl From<LexicalError> for InterpreterError {
     }
 }
 
+/// Why the parser couldn't read a program
 #[derive(Debug, PartialEq)]
 pub enum LoxParserError {
+    /// A token couldn't be scanned
     LexicalError(LexicalError),
+    /// Syntax the language reserves but doesn't have yet
     Unsupported,
+    /// An operator where it can't be, or none where one should be
     BadOperator(Option<Operator>),
+    /// Punctuation where it can't be, or none where it should be
     BadStructure(Option<Structure>),
+    /// The script ended in the middle of an expression
     EndOfFile,
+    /// The script ended where a particular token was expected
     EndOfFileConsume,
+    /// What was expected, such as "expected ';' after statement"
     Message(&'static str),
+    /// Another error, located at the token the parser was looking at
+    At(Box<LoxParserError>, usize, usize),
+}
+
+impl LoxParserError {
+    /// The row and column the error was found at, if it is known
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            LoxParserError::LexicalError(err) => Some(err.position()),
+            LoxParserError::At(_, row, col) => Some((*row, *col)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LoxParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxParserError::LexicalError(err) => Display::fmt(err, f),
+            LoxParserError::Unsupported => f.write_str("Unsupported syntax"),
+            LoxParserError::BadOperator(Some(op)) => {
+                f.write_fmt(format_args!("Unexpected operator `{op}`"))
+            }
+            LoxParserError::BadOperator(None) => f.write_str("Expected an operator"),
+            LoxParserError::BadStructure(Some(st)) => {
+                f.write_fmt(format_args!("Unexpected `{st}`"))
+            }
+            LoxParserError::BadStructure(None) => f.write_str("Expected punctuation"),
+            LoxParserError::EndOfFile | LoxParserError::EndOfFileConsume => {
+                f.write_str("Unexpected end of file")
+            }
+            LoxParserError::Message(msg) => f.write_str(msg),
+            LoxParserError::At(err, _, _) => Display::fmt(err, f),
+        }
+    }
 }
 
 impl From<LexicalError> for LoxParserError {
@@ -90,3 +341,115 @@ impl From<&'static str> for LoxParserError {
         Self::Message(value)
     }
 }
+
+/// Why text isn't JSON, with the byte offset it was found at
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JsonError {
+    /// A character that can't be where it is
+    UnexpectedChar(char, usize),
+    /// The text ended before the value did
+    UnexpectedEnd,
+    /// A number that doesn't parse
+    InvalidNumber(usize),
+    /// A backslash in a string that doesn't start a known escape
+    InvalidEscape(usize),
+    /// Arrays and objects nested deeper than the reader follows
+    NestingTooDeep(usize),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::UnexpectedChar(char, at) => {
+                f.write_fmt(format_args!("Unexpected {char:?} at byte {at}"))
+            }
+            JsonError::UnexpectedEnd => f.write_str("Unexpected end of JSON input"),
+            JsonError::InvalidNumber(at) => {
+                f.write_fmt(format_args!("Invalid number at byte {at}"))
+            }
+            JsonError::InvalidEscape(at) => {
+                f.write_fmt(format_args!("Invalid escape at byte {at}"))
+            }
+            JsonError::NestingTooDeep(at) => {
+                f.write_fmt(format_args!("Nesting too deep at byte {at}"))
+            }
+        }
+    }
+}
+
+/// Why JSON or s-expressions couldn't be read as a syntax tree, with
+/// where in the JSON as a path such as `[0].expression.left`, or where in
+/// the s-expressions as a byte offset
+#[derive(Debug, PartialEq, Clone)]
+pub enum AstError {
+    /// The text isn't JSON at all
+    Json(JsonError),
+    /// The path and what should have been there
+    Expected(String, String),
+    /// The path and the kind of node
+    UnknownKind(String, String),
+    /// The path to the node nested too deep to run, print or drop
+    NestingTooDeep(String),
+    /// The s-expressions ended in the middle of a form
+    UnexpectedEnd,
+    /// The byte offset and what should have been there
+    ExpectedAt(usize, String),
+    /// The byte offset of the form nested too deep to run, print or drop
+    NestingTooDeepAt(usize),
+    /// The byte offset and the head of the form
+    UnknownForm(usize, String),
+}
+
+impl AstError {
+    pub(crate) fn expected(path: &str, what: &str) -> Self {
+        Self::Expected(path.into(), what.into())
+    }
+}
+
+impl Display for AstError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AstError::Json(err) => Display::fmt(err, f),
+            AstError::Expected(path, what) => {
+                f.write_fmt(format_args!("Expected {what} at {}", root(path)))
+            }
+            AstError::UnknownKind(path, kind) => {
+                f.write_fmt(format_args!("Unknown kind {kind:?} at {}", root(path)))
+            }
+            AstError::NestingTooDeep(path) => {
+                f.write_fmt(format_args!("Nesting too deep at {}", root(path)))
+            }
+            AstError::UnexpectedEnd => f.write_str("Unexpected end of input"),
+            AstError::ExpectedAt(at, what) => {
+                f.write_fmt(format_args!("Expected {what} at byte {at}"))
+            }
+            AstError::NestingTooDeepAt(at) => {
+                f.write_fmt(format_args!("Nesting too deep at byte {at}"))
+            }
+            AstError::UnknownForm(at, head) => {
+                f.write_fmt(format_args!("Unknown form {head:?} at byte {at}"))
+            }
+        }
+    }
+}
+
+/// The path to the whole document is empty
+fn root(path: &str) -> &str {
+    if path.is_empty() {
+        "the top level"
+    } else {
+        path
+    }
+}
+
+impl From<JsonError> for AstError {
+    fn from(value: JsonError) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<AstError> for InterpreterError {
+    fn from(value: AstError) -> Self {
+        Self::AstError(value)
+    }
+}
@@ -0,0 +1,96 @@
+//! What `readLine` and `readAll` read, a line at a time so that nothing
+//! else reading the same stream, such as the REPL or the debugger, loses
+//! the lines after
+
+use std::io::{self, BufRead, Read};
+
+/// Input that reads no further ahead than the end of the line asked for
+pub struct Lines<F> {
+    read: F,
+    line: Vec<u8>,
+    consumed: usize,
+}
+
+impl<F: FnMut(&mut Vec<u8>) -> io::Result<usize>> Lines<F> {
+    /// `read` appends the next line, or as much of it as it has, to the
+    /// buffer, returning 0 at the end
+    pub fn new(read: F) -> Self {
+        Self {
+            read,
+            line: Vec::new(),
+            consumed: 0,
+        }
+    }
+}
+
+/// The process's stdin, locked only while it is read, a buffer at a time
+/// so that a long line can be charged to the heap limit as it is read
+pub fn stdin() -> Lines<impl FnMut(&mut Vec<u8>) -> io::Result<usize>> {
+    Lines::new(|line| {
+        let mut stdin = io::stdin().lock();
+        let available = stdin.fill_buf()?;
+        let len = available
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(available.len(), |newline| newline + 1);
+        line.extend_from_slice(&available[..len]);
+        stdin.consume(len);
+        Ok(len)
+    })
+}
+
+impl<F: FnMut(&mut Vec<u8>) -> io::Result<usize>> Read for Lines<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<F: FnMut(&mut Vec<u8>) -> io::Result<usize>> BufRead for Lines<F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed == self.line.len() {
+            self.line.clear();
+            self.consumed = 0;
+            (self.read)(&mut self.line)?;
+        }
+        Ok(&self.line[self.consumed..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed = (self.consumed + amt).min(self.line.len());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        io::{BufRead, Cursor, Read},
+        rc::Rc,
+    };
+
+    use super::Lines;
+
+    #[test]
+    fn readers_sharing_a_stream_take_turns_by_line() {
+        let stream = Rc::new(RefCell::new(Cursor::new("one\ntwo\nthree\n")));
+        let reader = |stream: Rc<RefCell<Cursor<&'static str>>>| {
+            Lines::new(move |line: &mut Vec<u8>| stream.borrow_mut().read_until(b'\n', line))
+        };
+        let mut first = reader(stream.clone());
+        let mut second = reader(stream);
+
+        let mut line = String::new();
+        first.read_line(&mut line).unwrap();
+        assert_eq!("one\n", line);
+        line.clear();
+        second.read_line(&mut line).unwrap();
+        assert_eq!("two\n", line);
+        let mut rest = String::new();
+        first.read_to_string(&mut rest).unwrap();
+        assert_eq!("three\n", rest);
+    }
+}
@@ -1,70 +1,524 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    fmt::Display,
+    io::{stderr, stdout, BufRead, Write},
+    path::Path,
+    rc::Rc,
+};
+
 use crate::{
-    error::InterpreterError,
+    environment::Environment,
+    error::{InterpreterError, LoxParserError},
+    input, natives,
+    parser::LoxParser,
+    stack::StackBase,
     syntax::{
         self,
         visit::{ExprVisitor, StmtVisitor},
-        BinOp, Expr, Literal, Stmt, UnOp,
+        BinOp, Expr, Import, Imported, Literal, Stmt, Target, Try, UnOp,
     },
-    value::Value,
+    token::TokenMeta,
+    value::{cmp_int_float, exact_int, Function, Map, Value},
 };
 
-#[derive(Debug, Default, Clone)]
-pub struct Interpreter {}
+use self::{limits::Usage, modules::Modules};
+
+pub use self::limits::Limits;
+
+/// Called before every statement is executed, this is how the debugger
+/// pauses a script
+pub trait ExecuteHook {
+    /// `file` is the file the statement is in, `depth` counts the blocks
+    /// the statement is nested in and `calls` are the function calls it is
+    /// running in, innermost last
+    fn before_execute(
+        &mut self,
+        stmt: &Stmt,
+        file: Option<&Path>,
+        environment: &Environment,
+        depth: usize,
+        calls: &[Frame],
+    ) -> Result<(), InterpreterError>;
+}
+
+/// How the interpreter unwinds for something other than an error in the
+/// script, kept out of the public API behind [`InterpreterError::Unwind`]
+#[derive(Debug)]
+pub enum Unwind {
+    /// A `return` on its way out to the function call
+    Return(Value),
+    /// A value thrown by `throw` that no `catch` has caught yet
+    Thrown(Value),
+    /// The debugger stopped the script
+    Interrupted,
+}
+
+impl Display for Unwind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unwind::Return(_) => f.write_str("Can't return from outside a function"),
+            Unwind::Thrown(value) => f.write_fmt(format_args!("Uncaught {value}")),
+            Unwind::Interrupted => f.write_str("Interrupted"),
+        }
+    }
+}
+
+/// A function call being run, what the function was called as and where
+/// it was called from
+pub struct Frame {
+    /// The variable the function was called through, none for a function
+    /// called any other way
+    pub name: Option<String>,
+    /// The file of the call, none for the main script
+    pub file: Option<Rc<Path>>,
+    /// The statement the call is in
+    pub location: TokenMeta,
+    /// The scope the call was made in
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>,
+    /// The scopes the current one replaced, innermost last
+    scopes: Vec<Rc<RefCell<Environment>>>,
+    /// How many statements and expressions are being run inside each other
+    nesting: usize,
+    /// Where the outermost of them started on the stack
+    stack: StackBase,
+    /// Where the statement being executed starts, the location of any
+    /// runtime error it causes
+    location: TokenMeta,
+    /// The file the statement being executed is in, imports are relative
+    /// to it
+    file: Option<Rc<Path>>,
+    /// The function calls being run, innermost last
+    calls: Vec<Frame>,
+    modules: Modules,
+    hook: Option<Box<dyn ExecuteHook>>,
+    console: Console,
+    limits: Limits,
+    usage: Usage,
+}
+
+/// Where a script's input comes from and its output goes, `readLine`
+/// reads `stdin`, `print` writes to `stdout` and `eprint` to `stderr`
+pub struct Console {
+    pub stdin: Box<dyn BufRead>,
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            stdin: Box::new(input::stdin()),
+            stdout: Box::new(stdout()),
+            stderr: Box::new(stderr()),
+        }
+    }
+}
+
+/// How deeply statements and expressions can be run inside each other,
+/// mostly by functions calling themselves
+const MAX_NESTING: usize = 10_000;
+
+/// A global scope with only the natives in it
+fn globals() -> Environment {
+    let mut globals = Environment::default();
+    natives::define(&mut globals);
+    globals
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self {
+            environment: Rc::new(RefCell::new(globals())),
+            scopes: Vec::new(),
+            nesting: 0,
+            stack: StackBase::here(),
+            location: TokenMeta { row: 1, col: 1 },
+            file: None,
+            calls: Vec::new(),
+            modules: Modules::default(),
+            hook: None,
+            console: Console::default(),
+            limits: Limits::default(),
+            usage: Usage::default(),
+        }
+    }
+}
 
 impl Interpreter {
     pub fn new() -> Self {
         Default::default()
     }
 
+    pub(crate) fn console(&mut self) -> &mut Console {
+        &mut self.console
+    }
+
+    /// Where `readLine` and `readAll` read from, stdin by default
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.console.stdin = input;
+    }
+
+    /// Where `print` writes to, stdout by default
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.console.stdout = output;
+    }
+
+    /// Where `eprint` writes to, stderr by default
+    pub fn set_error_output(&mut self, output: Box<dyn Write>) {
+        self.console.stderr = output;
+    }
+
+    /// The file of the script, without one imports are relative to the
+    /// working directory
+    pub fn set_file(&mut self, path: &Path) {
+        self.file = Some(path.into());
+    }
+
+    /// Parses a script or module with what is left of the stack limit
+    pub fn parse(&self, source: &str) -> Result<Vec<Stmt>, LoxParserError> {
+        let used = if self.nesting > 0 {
+            self.stack.used()
+        } else {
+            0
+        };
+        let stack_bytes = self
+            .limits
+            .stack_bytes
+            .map(|limit| limit.saturating_sub(used));
+        LoxParser::new(source).with_stack_bytes(stack_bytes).parse()
+    }
+
     pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), InterpreterError> {
-        for stmt in statements {
-            self.execute(stmt)?;
+        self.reset_steps();
+        let file = self.file.clone();
+        if let Some(file) = &file {
+            self.modules.start(file);
         }
-        Ok(())
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        if file.is_some() {
+            self.modules.finish();
+        }
+        result
+    }
+
+    pub fn set_hook(&mut self, hook: Box<dyn ExecuteHook>) {
+        self.hook = Some(hook);
     }
 
     pub fn execute(&mut self, stmt: &Stmt) -> Result<(), InterpreterError> {
-        stmt.accept(self)
+        if let Some(hook) = &mut self.hook {
+            let file = self.file.as_deref();
+            let environment = self.environment.borrow();
+            hook.before_execute(stmt, file, &environment, self.scopes.len(), &self.calls)?;
+        }
+        self.step()?;
+        self.location = stmt.meta.clone();
+        self.nested(|interpreter| stmt.accept(interpreter))
+    }
+
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), InterpreterError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        self.scopes.push(previous);
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = self.scopes.pop().expect("the scope pushed above");
+        result
     }
 
     pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, InterpreterError> {
-        expr.accept(self)
+        self.step()?;
+        let value = self.nested(|interpreter| expr.accept(interpreter))?;
+        self.allocated(&value)?;
+        Ok(value)
+    }
+
+    /// Runs a statement or expression inside the one being run, failing
+    /// before deep recursion in the script overflows the stack
+    fn nested<T>(
+        &mut self,
+        run: impl FnOnce(&mut Self) -> Result<T, InterpreterError>,
+    ) -> Result<T, InterpreterError> {
+        if self.nesting == 0 {
+            self.stack = StackBase::here();
+        }
+        let stack_full = self
+            .limits
+            .stack_bytes
+            .is_some_and(|limit| self.stack.used() > limit);
+        if self.nesting >= MAX_NESTING || stack_full {
+            return Err(InterpreterError::NestingTooDeep);
+        }
+        self.nesting += 1;
+        let result = run(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// Defines a variable in the current scope, the globals unless a script
+    /// is running
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.environment.borrow_mut().define(name, value);
+    }
+
+    /// The value of a variable in the current scope
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.environment.borrow().get(name)
+    }
+
+    /// Calls a native or Lox function with already evaluated arguments
+    pub fn call_value(
+        &mut self,
+        callee: &Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        match callee {
+            Value::NativeFunction(function) => function.call(self, &arguments),
+            Value::Function(function) => self.call(function, arguments, None),
+            callee => Err(InterpreterError::TypeError(callee.clone())),
+        }
+    }
+
+    fn call(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+        name: Option<&str>,
+    ) -> Result<Value, InterpreterError> {
+        let params = &function.declaration.params;
+        if arguments.len() != params.len() {
+            return Err(InterpreterError::WrongArity(params.len(), arguments.len()));
+        }
+        let mut environment = Environment::new(function.closure.clone());
+        for (param, argument) in params.iter().zip(arguments) {
+            environment.define(param, argument);
+        }
+        let environment = Rc::new(RefCell::new(environment));
+        self.enter_call(Frame {
+            name: name.map(Into::into),
+            file: self.file.clone(),
+            location: self.location.clone(),
+            environment: self.environment.clone(),
+        })?;
+        let previous = std::mem::replace(&mut self.file, function.file.clone());
+        let location = self.location.clone();
+        let result = self.execute_block(&function.declaration.body, environment);
+        self.exit_call();
+        let file = std::mem::replace(&mut self.file, previous);
+        let value = match result {
+            Ok(()) => Value::Nil,
+            Err(InterpreterError::Unwind(Unwind::Return(value))) => value,
+            Err(err) => match file {
+                Some(file) if self.file.as_ref() != Some(&file) => return Err(err.in_module(&file)),
+                _ => return Err(err),
+            },
+        };
+        // errors keep the location in the function, but the caller carries
+        // on from its own
+        self.location = location;
+        Ok(value)
     }
 
-    fn numeric(value: Value) -> Result<f64, InterpreterError> {
+    /// What a `catch` binds for an error, runtime errors become a map of
+    /// their message and location while anything else keeps unwinding, the
+    /// map names the file when the error came from another module
+    fn caught(&self, err: InterpreterError) -> Result<Value, InterpreterError> {
+        let (file, err) = match err {
+            InterpreterError::Unwind(Unwind::Thrown(value)) => return Ok(value),
+            err if !err.is_catchable() => return Err(err),
+            InterpreterError::InModule(file, err) => (Some(file), *err),
+            err => (None, err),
+        };
+        let error = Map::default();
+        error.set(&"message".into(), err.to_string().into())?;
+        if let Some(file) = file {
+            error.set(&"file".into(), file.display().to_string().into())?;
+        }
+        error.set(&"line".into(), (self.location.row as i64).into())?;
+        error.set(&"column".into(), (self.location.col as i64).into())?;
+        Ok(Value::Map(error))
+    }
+
+    /// A number as a float, ints are promoted
+    fn float(value: Value) -> Result<f64, InterpreterError> {
         match value {
             Value::Number(n) => Ok(n),
+            Value::Int(n) => Ok(n as f64),
             value => Err(InterpreterError::TypeError(value)),
         }
     }
 
-    fn numeric_op<F: FnOnce(f64, f64) -> f64>(
+    /// Two ints stay ints, which fail on overflow, otherwise both are
+    /// promoted to floats
+    fn numeric_op<I, F>(
+        left: Value,
+        right: Value,
+        int: I,
+        float: F,
+    ) -> Result<Value, InterpreterError>
+    where
+        I: FnOnce(i64, i64) -> Option<i64>,
+        F: FnOnce(f64, f64) -> f64,
+    {
+        match (left, right) {
+            (Value::Int(left), Value::Int(right)) => int(left, right)
+                .map(Value::Int)
+                .ok_or(InterpreterError::IntegerOverflow),
+            (left, right) => Ok(float(Self::float(left)?, Self::float(right)?).into()),
+        }
+    }
+
+    /// Bitwise operators only work on ints
+    fn int(value: Value) -> Result<i64, InterpreterError> {
+        match value {
+            Value::Int(n) => Ok(n),
+            value => Err(InterpreterError::NotAnInt(value)),
+        }
+    }
+
+    fn bitwise_op<F: FnOnce(i64, i64) -> i64>(
+        left: Value,
+        right: Value,
+        f: F,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Int(f(Self::int(left)?, Self::int(right)?)))
+    }
+
+    /// Shifts by less than the 64 bits of an int
+    fn shift_op<F: FnOnce(i64, u32) -> Option<i64>>(
         left: Value,
         right: Value,
         f: F,
     ) -> Result<Value, InterpreterError> {
-        Ok(f(Self::numeric(left)?, Self::numeric(right)?).into())
+        let (n, by) = (Self::int(left)?, Self::int(right)?);
+        u32::try_from(by)
+            .ok()
+            .and_then(|by| f(n, by))
+            .map(Value::Int)
+            .ok_or(InterpreterError::InvalidShift(by))
     }
 
-    fn cmp_op<F: FnOnce(f64, f64) -> bool>(
+    /// Compares numbers exactly, an int against a float without rounding
+    /// either, NaN is not ordered so every comparison with it is false
+    fn cmp_op<F: FnOnce(Ordering) -> bool>(
         left: Value,
         right: Value,
         f: F,
     ) -> Result<Value, InterpreterError> {
-        Ok(f(Self::numeric(left)?, Self::numeric(right)?).into())
+        let ordering = match (left, right) {
+            (Value::Int(left), Value::Int(right)) => Some(left.cmp(&right)),
+            (Value::Int(left), Value::Number(right)) => cmp_int_float(left, right),
+            (Value::Number(left), Value::Int(right)) => {
+                cmp_int_float(right, left).map(Ordering::reverse)
+            }
+            (left, right) => Self::float(left)?.partial_cmp(&Self::float(right)?),
+        };
+        Ok(ordering.is_some_and(f).into())
     }
 
     fn eq(left: &Value, right: &Value) -> bool {
         match (left, right) {
             (Value::String(left), Value::String(right)) => left == right,
             (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::Int(left), Value::Int(right)) => left == right,
+            (&Value::Int(left), &Value::Number(right))
+            | (&Value::Number(right), &Value::Int(left)) => exact_int(right) == Some(left),
             (Value::Bool(left), Value::Bool(right)) => left == right,
             (Value::Nil, Value::Nil) => true,
+            (Value::List(left), Value::List(right)) => left == right,
+            (Value::Map(left), Value::Map(right)) => left == right,
+            (Value::NativeFunction(left), Value::NativeFunction(right)) => left == right,
+            (Value::Function(left), Value::Function(right)) => left == right,
 
             _ => false,
         }
     }
 
+    /// Applies an operator that always needs both operands
+    fn binary_op(
+        &mut self,
+        operator: BinOp,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, InterpreterError> {
+        match operator {
+            BinOp::Add => match (left, right) {
+                (Value::String(left), Value::String(right)) => {
+                    self.reserve(left.len().saturating_add(right.len()))?;
+                    Ok((left + &right).into())
+                }
+                (Value::String(_), right) => Err(InterpreterError::TypeError(right)),
+                (left, Value::String(_)) => Err(InterpreterError::TypeError(left)),
+                (left, right) => Self::numeric_op(left, right, i64::checked_add, |l, r| l + r),
+            },
+            BinOp::Sub => Self::numeric_op(left, right, i64::checked_sub, |l, r| l - r),
+            BinOp::Mul => Self::numeric_op(left, right, i64::checked_mul, |l, r| l * r),
+            // always a float, even between ints
+            BinOp::Div => Ok((Self::float(left)? / Self::float(right)?).into()),
+            // both truncate towards zero, so that `a == (a ~/ b) * b + a % b`
+            BinOp::IntDiv | BinOp::Rem
+                if right == Value::Int(0) && matches!(left, Value::Int(_)) =>
+            {
+                Err(InterpreterError::DivisionByZero)
+            }
+            BinOp::IntDiv => {
+                Self::numeric_op(left, right, i64::checked_div, |l, r| (l / r).trunc())
+            }
+            // only `i64::MIN % -1` wraps, and to the right answer of 0
+            BinOp::Rem => {
+                Self::numeric_op(left, right, |l, r| Some(l.wrapping_rem(r)), |l, r| l % r)
+            }
+            BinOp::Pow => match (left, right) {
+                // a negative exponent makes a fraction
+                (Value::Int(base), Value::Int(exp)) if exp >= 0 => u32::try_from(exp)
+                    .ok()
+                    .and_then(|exp| base.checked_pow(exp))
+                    .map(Value::Int)
+                    .ok_or(InterpreterError::IntegerOverflow),
+                (left, right) => Ok(Self::float(left)?.powf(Self::float(right)?).into()),
+            },
+
+            BinOp::BitAnd => Self::bitwise_op(left, right, |l, r| l & r),
+            BinOp::BitOr => Self::bitwise_op(left, right, |l, r| l | r),
+            BinOp::BitXor => Self::bitwise_op(left, right, |l, r| l ^ r),
+            BinOp::Shl => Self::shift_op(left, right, i64::checked_shl),
+            BinOp::Shr => Self::shift_op(left, right, i64::checked_shr),
+
+            BinOp::Ne => Ok((!Self::eq(&left, &right)).into()),
+            BinOp::Eq => Ok((Self::eq(&left, &right)).into()),
+
+            BinOp::Gt => Self::cmp_op(left, right, Ordering::is_gt),
+            BinOp::Ge => Self::cmp_op(left, right, Ordering::is_ge),
+            BinOp::Lt => Self::cmp_op(left, right, Ordering::is_lt),
+            BinOp::Le => Self::cmp_op(left, right, Ordering::is_le),
+
+            BinOp::And | BinOp::Or => unreachable!("short-circuits before the right operand"),
+        }
+    }
+
+    fn get_index(object: Value, index: &Value) -> Result<Value, InterpreterError> {
+        match object {
+            Value::List(list) => list.get(index),
+            Value::Map(map) => map.get(index),
+            object => Err(InterpreterError::TypeError(object)),
+        }
+    }
+
+    fn set_index(object: Value, index: &Value, value: Value) -> Result<(), InterpreterError> {
+        match object {
+            Value::List(list) => list.set(index, value),
+            Value::Map(map) => map.set(index, value),
+            object => Err(InterpreterError::TypeError(object)),
+        }
+    }
+
     fn truthy(value: &Value) -> bool {
         !matches!(*value, Value::Nil | Value::Bool(false))
     }
@@ -78,79 +532,264 @@ impl StmtVisitor<Result<(), InterpreterError>> for Interpreter {
 
     fn visit_print(&mut self, expr: &Expr) -> Result<(), InterpreterError> {
         let value = self.evaluate(expr)?;
-        println!("{value}");
+        writeln!(self.console.stdout, "{value}")?;
+        Ok(())
+    }
+
+    fn visit_var(
+        &mut self,
+        name: &str,
+        initializer: Option<&Expr>,
+    ) -> Result<(), InterpreterError> {
+        let value = match initializer {
+            Some(expr) => self.evaluate(expr)?,
+            None => Value::Nil,
+        };
+        self.environment.borrow_mut().define(name, value);
         Ok(())
     }
 
-    #[allow(unused_variables)]
-    fn visit_var(&mut self, name: &str, expr: &Expr) -> Result<(), InterpreterError> {
-        todo!()
+    fn visit_block(&mut self, statements: &[Stmt]) -> Result<(), InterpreterError> {
+        let environment = Environment::new(self.environment.clone());
+        self.execute_block(statements, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_return(&mut self, value: Option<&Expr>) -> Result<(), InterpreterError> {
+        let value = match value {
+            Some(expr) => self.evaluate(expr)?,
+            None => Value::Nil,
+        };
+        Err(Unwind::Return(value).into())
+    }
+
+    fn visit_import(&mut self, import: &Import) -> Result<(), InterpreterError> {
+        let exports = self.import(&import.path)?;
+        let mut environment = self.environment.borrow_mut();
+        match &import.names {
+            Imported::Module(name) => environment.define(name, Value::Map(exports)),
+            Imported::Names(names) => {
+                for name in names {
+                    let value = exports.get(&name.as_str().into()).map_err(|_| {
+                        InterpreterError::UndefinedExport(import.path.clone(), name.clone())
+                    })?;
+                    environment.define(name, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_throw(&mut self, value: &Expr) -> Result<(), InterpreterError> {
+        Err(Unwind::Thrown(self.evaluate(value)?).into())
+    }
+
+    fn visit_try(&mut self, statement: &Try) -> Result<(), InterpreterError> {
+        let mut result = self.visit_block(&statement.body);
+        if let Some(catch) = &statement.catch {
+            if let Err(err) = result {
+                result = self.caught(err).and_then(|value| {
+                    let mut environment = Environment::new(self.environment.clone());
+                    environment.define(&catch.name, value);
+                    self.execute_block(&catch.body, Rc::new(RefCell::new(environment)))
+                });
+            }
+        }
+        // runs after returns as well, but not once the debugger has stopped
+        // the script or it has run out of a limit
+        if let Some(finally) = &statement.finally {
+            if !matches!(&result, Err(err) if err.stops_script()) {
+                // an error still unwinding keeps its own location
+                let location = self.location.clone();
+                self.visit_block(finally)?;
+                self.location = location;
+            }
+        }
+        result
     }
 }
 
 impl ExprVisitor<Result<Value, InterpreterError>> for Interpreter {
+    fn visit_assign(&mut self, assign: &syntax::Assign) -> Result<Value, InterpreterError> {
+        let value = self.evaluate(&assign.value)?;
+        if self
+            .environment
+            .borrow_mut()
+            .assign(&assign.name, value.clone())
+        {
+            Ok(value)
+        } else {
+            Err(InterpreterError::UndefinedVariable(assign.name.clone()))
+        }
+    }
+
     fn visit_binary(&mut self, binary: &syntax::Binary) -> Result<Value, InterpreterError> {
         let left = self.evaluate(&binary.left)?;
-        let right = self.evaluate(&binary.right)?;
+        // the right operand is only evaluated when it decides the result
         match binary.operator {
-            BinOp::Add => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok((left + right).into()),
-                (Value::String(left), Value::String(right)) => Ok((left + &right).into()),
-                (left, Value::Number(_) | Value::String(_)) => {
-                    Err(InterpreterError::TypeError(left))
-                }
-                (_, right) => Err(InterpreterError::TypeError(right)),
-            },
-            BinOp::Sub => Self::numeric_op(left, right, |l, r| l - r),
-            BinOp::Div => Self::numeric_op(left, right, |l, r| l / r),
-            BinOp::Mul => Self::numeric_op(left, right, |l, r| l * r),
+            BinOp::And if !Self::truthy(&left) => return Ok(false.into()),
+            BinOp::Or if Self::truthy(&left) => return Ok(true.into()),
+            BinOp::And | BinOp::Or => {
+                let right = self.evaluate(&binary.right)?;
+                return Ok(Self::truthy(&right).into());
+            }
+            _ => {}
+        }
+        let right = self.evaluate(&binary.right)?;
+        self.binary_op(binary.operator, left, right)
+    }
 
-            BinOp::Ne => Ok((!Self::eq(&left, &right)).into()),
-            BinOp::Eq => Ok((Self::eq(&left, &right)).into()),
+    fn visit_call(&mut self, call: &syntax::Call) -> Result<Value, InterpreterError> {
+        let callee = self.evaluate(&call.callee)?;
+        let arguments = call
+            .arguments
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        match (&callee, &call.callee) {
+            (Value::Function(function), Expr::Literal(Literal::Identifier(name))) => {
+                self.call(function, arguments, Some(name))
+            }
+            _ => self.call_value(&callee, arguments),
+        }
+    }
 
-            BinOp::Gt => Self::cmp_op(left, right, |l, r| l > r),
-            BinOp::Ge => Self::cmp_op(left, right, |l, r| l >= r),
-            BinOp::Lt => Self::cmp_op(left, right, |l, r| l < r),
-            BinOp::Le => Self::cmp_op(left, right, |l, r| l <= r),
+    fn visit_comma(&mut self, comma: &syntax::Comma) -> Result<Value, InterpreterError> {
+        self.evaluate(&comma.left)?;
+        self.evaluate(&comma.right)
+    }
 
-            BinOp::And => {
-                let b = Self::truthy(&left) && Self::truthy(&right);
-                Ok(b.into())
-            }
-            BinOp::Or => {
-                let b = Self::truthy(&left) || Self::truthy(&right);
-                Ok(b.into())
-            }
+    fn visit_conditional(
+        &mut self,
+        conditional: &syntax::Conditional,
+    ) -> Result<Value, InterpreterError> {
+        if Self::truthy(&self.evaluate(&conditional.condition)?) {
+            self.evaluate(&conditional.then_branch)
+        } else {
+            self.evaluate(&conditional.else_branch)
         }
     }
 
+    fn visit_function(
+        &mut self,
+        function: &Rc<syntax::Function>,
+    ) -> Result<Value, InterpreterError> {
+        Ok(Value::Function(Function {
+            declaration: function.clone(),
+            closure: self.environment.clone(),
+            file: self.file.clone(),
+        }))
+    }
+
     fn visit_group(&mut self, group: &syntax::Grouping) -> Result<Value, InterpreterError> {
         self.evaluate(&group.expression)
     }
 
+    fn visit_index(&mut self, index: &syntax::Index) -> Result<Value, InterpreterError> {
+        let object = self.evaluate(&index.object)?;
+        let i = self.evaluate(&index.index)?;
+        Self::get_index(object, &i)
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> Result<Value, InterpreterError> {
+        let values = parts
+            .iter()
+            .map(|part| self.evaluate(part))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut string = self.charged_string();
+        for value in &values {
+            string.push_value(value)?;
+        }
+        Ok(string.finish().into())
+    }
+
+    fn visit_list(&mut self, elements: &[Expr]) -> Result<Value, InterpreterError> {
+        let values = elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values.into())
+    }
+
     fn visit_literal(&mut self, lit: &Literal) -> Result<Value, InterpreterError> {
         match *lit {
             Literal::String(ref s) => Ok(Value::String(s.clone())),
+            Literal::Int(n) => Ok(Value::Int(n)),
             Literal::Number(n) => Ok(Value::Number(n)),
             Literal::True => Ok(Value::Bool(true)),
             Literal::False => Ok(Value::Bool(false)),
             Literal::Nil => Ok(Value::Nil),
-            Literal::Identifier(_) => todo!(),
+            Literal::Identifier(ref name) => self
+                .environment
+                .borrow()
+                .get(name)
+                .ok_or_else(|| InterpreterError::UndefinedVariable(name.clone())),
         }
     }
 
+    fn visit_map(&mut self, entries: &[(Expr, Expr)]) -> Result<Value, InterpreterError> {
+        let map = Map::default();
+        for (key, value) in entries {
+            let key = self.evaluate(key)?;
+            let value = self.evaluate(value)?;
+            map.set(&key, value)?;
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn visit_set_index(&mut self, set: &syntax::SetIndex) -> Result<Value, InterpreterError> {
+        let object = self.evaluate(&set.object)?;
+        let i = self.evaluate(&set.index)?;
+        let value = self.evaluate(&set.value)?;
+        Self::set_index(object, &i, value.clone())?;
+        Ok(value)
+    }
+
     fn visit_unary(&mut self, unary: &syntax::Unary) -> Result<Value, InterpreterError> {
         let value = self.evaluate(&unary.expression)?;
 
         match unary.operator {
-            UnOp::Neg => {
-                let n = Self::numeric(value)?;
-                Ok(Value::Number(-n))
-            }
+            UnOp::Neg => match value {
+                Value::Int(n) => n
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or(InterpreterError::IntegerOverflow),
+                value => Ok(Value::Number(-Self::float(value)?)),
+            },
+            UnOp::BitNot => Ok(Value::Int(!Self::int(value)?)),
             UnOp::Not => {
                 let b = Self::truthy(&value);
                 Ok(Value::Bool(!b))
             }
         }
     }
+    fn visit_update(&mut self, update: &syntax::Update) -> Result<Value, InterpreterError> {
+        let (old, new) = match &update.target {
+            Target::Variable(name) => {
+                let old = self
+                    .environment
+                    .borrow()
+                    .get(name)
+                    .ok_or_else(|| InterpreterError::UndefinedVariable(name.clone()))?;
+                let value = self.evaluate(&update.value)?;
+                let new = self.binary_op(update.operator, old.clone(), value)?;
+                if !self.environment.borrow_mut().assign(name, new.clone()) {
+                    return Err(InterpreterError::UndefinedVariable(name.clone()));
+                }
+                (old, new)
+            }
+            Target::Index(index) => {
+                let object = self.evaluate(&index.object)?;
+                let i = self.evaluate(&index.index)?;
+                let old = Self::get_index(object.clone(), &i)?;
+                let value = self.evaluate(&update.value)?;
+                let new = self.binary_op(update.operator, old.clone(), value)?;
+                Self::set_index(object, &i, new.clone())?;
+                (old, new)
+            }
+        };
+        Ok(if update.postfix { old } else { new })
+    }
 }
+
+mod limits;
+mod modules;
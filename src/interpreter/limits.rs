@@ -0,0 +1,427 @@
+//! Limits on what a script can use, so one that isn't trusted can't hang
+//! or exhaust the process running it
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Write},
+    mem,
+    time::Instant,
+};
+
+use crate::{
+    environment::Environment, error::InterpreterError, stack::DEFAULT_STACK_BYTES, value::Value,
+};
+
+use super::{Frame, Interpreter};
+
+/// Steps between looking at the clock
+const CLOCK_INTERVAL: u64 = 256;
+/// The least the script allocates before the heap is measured again
+const MIN_MEASURE_INTERVAL: usize = 1024;
+/// Shallow enough that a script runs into it before the interpreter's own
+/// nesting limit, whatever stack it has
+pub const DEFAULT_CALL_DEPTH: usize = 1000;
+
+/// Limits on what a script can use, `None` for no limit
+///
+/// Running into any of them but `stack_bytes` ends the script with an
+/// error that `catch` can't catch and that skips `finally`.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Statements executed and expressions evaluated, counted from the
+    /// start of each run
+    pub steps: Option<u64>,
+    /// Calls to Lox functions in progress at once, 1000 by default
+    pub call_depth: Option<usize>,
+    /// Bytes held by the values reachable from the script's variables,
+    /// measured as values are made. Concatenation, interpolation and the
+    /// natives whose results grow with their arguments check it before
+    /// they allocate.
+    pub heap_bytes: Option<usize>,
+    /// When a script still running stops, checked every so many steps
+    pub deadline: Option<Instant>,
+    /// Bytes of the Rust stack parsing and running may use below where
+    /// they started, past it nesting fails with "nesting too deep". It
+    /// also bounds how deep the parsed tree can be, 2 KiB for each level.
+    /// The default of 1 MiB fits a thread with the default stack, `None`
+    /// trusts the thread to have enough for any nesting.
+    pub stack_bytes: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            steps: None,
+            call_depth: Some(DEFAULT_CALL_DEPTH),
+            heap_bytes: None,
+            deadline: None,
+            stack_bytes: Some(DEFAULT_STACK_BYTES),
+        }
+    }
+}
+
+/// What the script has used so far
+#[derive(Default)]
+pub(super) struct Usage {
+    steps: u64,
+    /// The heap when it was last measured
+    heap: usize,
+    /// Roughly what has been allocated since then
+    allocated: usize,
+}
+
+impl Interpreter {
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Starts counting steps again, for a new run
+    pub(super) fn reset_steps(&mut self) {
+        self.usage.steps = 0;
+    }
+
+    /// Counts a statement or an expression
+    pub(super) fn step(&mut self) -> Result<(), InterpreterError> {
+        self.usage.steps += 1;
+        if let Some(limit) = self.limits.steps {
+            if self.usage.steps > limit {
+                return Err(InterpreterError::StepLimit(limit));
+            }
+        }
+        if let Some(deadline) = self.limits.deadline {
+            if self.usage.steps % CLOCK_INTERVAL == 1 && Instant::now() >= deadline {
+                return Err(InterpreterError::DeadlineExceeded);
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn enter_call(&mut self, frame: Frame) -> Result<(), InterpreterError> {
+        if let Some(limit) = self.limits.call_depth {
+            if self.calls.len() >= limit {
+                return Err(InterpreterError::CallDepthLimit(limit));
+            }
+        }
+        self.calls.push(frame);
+        Ok(())
+    }
+
+    pub(super) fn exit_call(&mut self) {
+        self.calls.pop();
+    }
+
+    /// Notes a value an expression made, measuring the heap once enough
+    /// has been made since it last was
+    pub(super) fn allocated(&mut self, value: &Value) -> Result<(), InterpreterError> {
+        let Some(limit) = self.limits.heap_bytes else {
+            return Ok(());
+        };
+        self.usage.allocated += match value {
+            Value::String(str) => str.len(),
+            Value::List(list) => list.len() * mem::size_of::<Value>(),
+            Value::Map(map) => map.len() * 2 * mem::size_of::<Value>(),
+            _ => 0,
+        };
+        if self.usage.allocated < (self.usage.heap / 2).max(MIN_MEASURE_INTERVAL) {
+            return Ok(());
+        }
+        self.usage.allocated = 0;
+        self.usage.heap = self.heap_size(value);
+        if self.usage.heap > limit {
+            return Err(InterpreterError::HeapLimit(limit));
+        }
+        Ok(())
+    }
+
+    /// Fails if `bytes` more would take the heap past its limit, for a
+    /// native to call before it allocates them
+    pub(crate) fn reserve(&mut self, bytes: usize) -> Result<(), InterpreterError> {
+        let Some(limit) = self.limits.heap_bytes else {
+            return Ok(());
+        };
+        let estimate = self.usage.heap.saturating_add(self.usage.allocated);
+        if estimate.saturating_add(bytes) <= limit {
+            return Ok(());
+        }
+        // what was allocated since the heap was measured may be gone
+        self.usage.allocated = 0;
+        self.usage.heap = self.heap_size(&Value::Nil);
+        if self.usage.heap.saturating_add(bytes) > limit {
+            return Err(InterpreterError::HeapLimit(limit));
+        }
+        Ok(())
+    }
+
+    /// A string to build that reserves what it grows by first
+    pub(crate) fn charged_string(&mut self) -> ChargedString<'_> {
+        ChargedString {
+            interpreter: self,
+            string: String::new(),
+            error: None,
+        }
+    }
+
+    /// The bytes reachable from every scope still in use, the exports of
+    /// the modules and `value`, which may not be in a variable yet
+    fn heap_size(&self, value: &Value) -> usize {
+        let mut seen = HashSet::new();
+        let scopes = self.scopes.iter().chain([&self.environment]);
+        let scopes: usize = scopes
+            .map(|scope| Environment::heap_size(scope, &mut seen))
+            .sum();
+        let exports: usize = self
+            .modules
+            .exports()
+            .map(|exports| Value::Map(exports.clone()).heap_size(&mut seen))
+            .sum();
+        scopes + exports + value.heap_size(&mut seen)
+    }
+}
+
+/// A string built from values, whose display can be far larger than the
+/// memory they hold when they share a list, so it can't be reserved up
+/// front
+pub(crate) struct ChargedString<'a> {
+    interpreter: &'a mut Interpreter,
+    string: String,
+    /// Why the last write failed, `fmt::Error` can't say
+    error: Option<InterpreterError>,
+}
+
+impl ChargedString<'_> {
+    pub(crate) fn push_str(&mut self, str: &str) -> Result<(), InterpreterError> {
+        self.interpreter
+            .reserve(self.string.len().saturating_add(str.len()))?;
+        self.string.push_str(str);
+        Ok(())
+    }
+
+    /// Appends the value as `print` shows it
+    pub(crate) fn push_value(&mut self, value: &Value) -> Result<(), InterpreterError> {
+        write!(self, "{value}").map_err(|_| {
+            self.error
+                .take()
+                .expect("only reserving fails to write to a string")
+        })
+    }
+
+    pub(crate) fn finish(self) -> String {
+        self.string
+    }
+}
+
+impl Write for ChargedString<'_> {
+    fn write_str(&mut self, str: &str) -> fmt::Result {
+        self.push_str(str).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env, fs,
+        io::Cursor,
+        process, thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        error::InterpreterError, interpreter::Interpreter, parser::LoxParser, value::Value,
+    };
+
+    use super::Limits;
+
+    const COUNTDOWN: &str = "var down = fun (n) { return n == 0 ? 0 : down(n - 1); };";
+
+    fn run(interpreter: &mut Interpreter, script: &str) -> Result<(), InterpreterError> {
+        interpreter.interpret(&LoxParser::new(script).parse()?)
+    }
+
+    fn limited(limits: Limits) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(limits);
+        interpreter
+    }
+
+    #[test]
+    fn step_limit() {
+        let mut interpreter = limited(Limits {
+            steps: Some(1000),
+            ..Default::default()
+        });
+        let result = run(&mut interpreter, &format!("{COUNTDOWN} down(10);"));
+        assert!(result.is_ok(), "{result:?}");
+        let result = run(&mut interpreter, "down(500);");
+        assert!(matches!(result, Err(InterpreterError::StepLimit(1000))));
+        // each run has the whole budget
+        let result = run(&mut interpreter, "down(10);");
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn call_depth_limit() {
+        let mut interpreter = limited(Limits {
+            call_depth: Some(50),
+            ..Default::default()
+        });
+        let result = run(&mut interpreter, &format!("{COUNTDOWN} down(49);"));
+        assert!(result.is_ok(), "{result:?}");
+        let result = run(&mut interpreter, "down(50);");
+        assert!(matches!(result, Err(InterpreterError::CallDepthLimit(50))));
+        let result = run(&mut interpreter, "down(49);");
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn heap_limit() {
+        let limits = Limits {
+            heap_bytes: Some(100_000),
+            ..Default::default()
+        };
+        let grow =
+            "var s = \"x\"; var grow = fun (n) { s = s + s; return n > 0 ? grow(n - 1) : nil; };";
+        let result = run(&mut limited(limits.clone()), &format!("{grow} grow(10);"));
+        assert!(result.is_ok(), "{result:?}");
+        let result = run(&mut limited(limits.clone()), &format!("{grow} grow(40);"));
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+
+        // values held only by the scopes of calls still running count too
+        let hold = "var hold = fun (n) { var held = repeat(\"x\", 1000); \
+                    return n > 0 ? hold(n - 1) : nil; };";
+        let result = run(&mut limited(limits.clone()), &format!("{hold} hold(200);"));
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+
+        // natives fail before they allocate past the limit
+        let result = run(
+            &mut limited(limits.clone()),
+            "repeat(\"x\", 1000000000000);",
+        );
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+        let mut interpreter = limited(limits.clone());
+        interpreter.set_input(Box::new(Cursor::new("x".repeat(200_000))));
+        let result = run(&mut interpreter, "var input = readAll();");
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+
+        // and so does concatenation, before it copies both sides
+        let big = "var s = repeat(\"x\", 60000);";
+        let result = run(
+            &mut limited(limits.clone()),
+            &format!("{big} var t = s + s;"),
+        );
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+        let result = run(
+            &mut limited(limits),
+            &format!("{big} var t = \"${{s}}${{s}}\";"),
+        );
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+    }
+
+    #[test]
+    fn natives_reserve_before_they_allocate() {
+        let limits = Limits {
+            heap_bytes: Some(100_000),
+            ..Default::default()
+        };
+        let small = "var s = repeat(\"x\", 2000);";
+        let big = "var s = repeat(\"x\", 60000);";
+        // a list shared a hundred times over shows as far more than it holds
+        let shared = "var s = repeat(\"x\", 2000); var l = [s, s];\n\
+                      var m = [l, l, l, l, l, l, l, l, l, l];\n\
+                      var n = [m, m, m, m, m, m, m, m, m, m];";
+        let cases = [
+            format!("{small} replace(s, \"x\", s);"),
+            format!("{small} split(s, \"x\");"),
+            format!("{small} chars(s);"),
+            format!("{shared} join(n, \"\");"),
+            format!("{shared} format(\"{{}}\", n);"),
+            format!("{shared} \"${{n}}\";"),
+            format!("{big} upper(s);"),
+            format!("{big} lower(s);"),
+            format!("{big} trim(s);"),
+            format!("{big} substring(s, 0, 60000);"),
+            format!("{big} values({{\"s\": s}});"),
+            format!("{big} keys({{s: 1}});"),
+        ];
+        for script in cases {
+            let result = run(&mut limited(limits.clone()), &script);
+            assert!(
+                matches!(result, Err(InterpreterError::HeapLimit(100_000))),
+                "{script}: {result:?}"
+            );
+        }
+
+        let mut interpreter = limited(limits.clone());
+        interpreter.set_input(Box::new(Cursor::new("x".repeat(200_000))));
+        let result = run(&mut interpreter, "readLine();");
+        assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+
+        let dir = env::temp_dir().join(format!("lox-limits-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("big.txt"), "x".repeat(200_000)).unwrap();
+        for i in 0..2000 {
+            fs::write(dir.join(format!("{i:040}")), "").unwrap();
+        }
+        let path = dir.to_str().unwrap().replace('\\', "/");
+        let results = [
+            run(
+                &mut limited(limits.clone()),
+                &format!("readFile(\"{path}/big.txt\");"),
+            ),
+            run(&mut limited(limits), &format!("listDir(\"{path}\");")),
+        ];
+        fs::remove_dir_all(&dir).unwrap();
+        for result in results {
+            assert!(matches!(result, Err(InterpreterError::HeapLimit(100_000))));
+        }
+    }
+
+    #[test]
+    fn call_depth_is_limited_by_default() {
+        // a thread with more stack than the default limit fits
+        let result = thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(|| {
+                let mut interpreter = limited(Limits {
+                    stack_bytes: None,
+                    ..Default::default()
+                });
+                let result = run(&mut interpreter, &format!("{COUNTDOWN} down(5000);"));
+                format!("{result:?}")
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!("Err(CallDepthLimit(1000))", result);
+    }
+
+    #[test]
+    fn deadline() {
+        let mut interpreter = limited(Limits {
+            deadline: Some(Instant::now() + Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let spin = "var spin = fun (n) { return n > 0 ? spin(n - 1) + spin(n - 1) : 1; };";
+        let result = run(&mut interpreter, &format!("{spin} spin(30);"));
+        assert!(matches!(result, Err(InterpreterError::DeadlineExceeded)));
+    }
+
+    #[test]
+    fn limits_are_not_caught() {
+        let mut interpreter = limited(Limits {
+            steps: Some(1000),
+            ..Default::default()
+        });
+        let script = format!(
+            "var caught = false; var finished = false; \
+             try {{ {COUNTDOWN} down(500); }} catch (e) {{ caught = true; }} \
+             finally {{ finished = true; }}"
+        );
+        let result = run(&mut interpreter, &script);
+        assert!(matches!(result, Err(InterpreterError::StepLimit(1000))));
+        assert_eq!(Some(Value::Bool(false)), interpreter.get("caught"));
+        assert_eq!(Some(Value::Bool(false)), interpreter.get("finished"));
+    }
+}
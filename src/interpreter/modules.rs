@@ -0,0 +1,96 @@
+//! `import` runs each module once, with globals of its own, and keeps the
+//! map of what it exports
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{error::InterpreterError, value::Map};
+
+use super::Interpreter;
+
+#[derive(Default)]
+pub struct Modules {
+    /// The exports of the modules that have finished running, by canonical path
+    loaded: HashMap<PathBuf, Map>,
+    /// The files still running, outermost first, by canonical path and by
+    /// the path they were found at
+    running: Vec<(PathBuf, Rc<Path>)>,
+}
+
+impl Modules {
+    /// The exports of the modules that have finished running
+    pub fn exports(&self) -> impl Iterator<Item = &Map> {
+        self.loaded.values()
+    }
+
+    /// Notes the file of the main script while it runs, so importing it
+    /// is a cycle
+    pub fn start(&mut self, file: &Rc<Path>) {
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        self.running.push((canonical, file.clone()));
+    }
+
+    /// Notes that the main script has stopped, however it stopped
+    pub fn finish(&mut self) {
+        self.running.pop();
+    }
+}
+
+impl Interpreter {
+    /// The exports of the module at `path`, relative to the running file
+    pub(super) fn import(&mut self, path: &str) -> Result<Map, InterpreterError> {
+        let path: Rc<Path> = match &self.file {
+            Some(file) => file.parent().unwrap_or(Path::new("")).join(path).into(),
+            None => Path::new(path).into(),
+        };
+        let io_error = |err: io::Error| {
+            let message = format!("{}: {err}", path.display());
+            InterpreterError::NativeIo(io::Error::new(err.kind(), message))
+        };
+        let canonical = path.canonicalize().map_err(io_error)?;
+        if let Some(exports) = self.modules.loaded.get(&canonical) {
+            return Ok(exports.clone());
+        }
+        let running = &self.modules.running;
+        if let Some(start) = running.iter().position(|(file, _)| *file == canonical) {
+            let mut cycle: Vec<_> = running[start..]
+                .iter()
+                .map(|(_, file)| file.clone())
+                .collect();
+            cycle.push(path);
+            return Err(InterpreterError::ImportCycle(cycle));
+        }
+        let source = fs::read_to_string(&path).map_err(io_error)?;
+
+        self.modules.running.push((canonical.clone(), path.clone()));
+        let exports = self.run_module(&source, path.clone());
+        self.modules.running.pop();
+        let exports = exports.map_err(|err| err.in_module(&path))?;
+        self.modules.loaded.insert(canonical, exports.clone());
+        Ok(exports)
+    }
+
+    fn run_module(&mut self, source: &str, file: Rc<Path>) -> Result<Map, InterpreterError> {
+        let statements = self.parse(source)?;
+        let globals = Rc::new(RefCell::new(super::globals()));
+        let previous = self.file.replace(file);
+        let location = self.location.clone();
+        let result = self.execute_block(&statements, globals.clone());
+        self.file = previous;
+        result?;
+        // errors keep the location in the module, but the importer carries on
+        // from its own
+        self.location = location;
+
+        let exports = Map::default();
+        for (name, value) in globals.borrow().variables() {
+            exports.set(&name.into(), value.clone())?;
+        }
+        Ok(exports)
+    }
+}
@@ -0,0 +1,472 @@
+//! A small JSON reader and writer, enough for the editor protocols
+//!
+//! Objects keep their fields in insertion order so that output is
+//! deterministic.
+
+use std::{
+    fmt::{self, Display, Formatter, Write as _},
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    error::JsonError,
+    stack::{StackBase, DEFAULT_STACK_BYTES},
+};
+
+/// How deeply arrays and objects can nest, deep enough for the syntax tree
+/// of any script the parser accepts
+const MAX_DEPTH: usize = 2048;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(input: &str) -> Result<Self, JsonError> {
+        let mut reader = Reader {
+            input,
+            index: 0,
+            depth: 0,
+            stack: StackBase::here(),
+        };
+        let json = reader.value()?;
+        reader.skip_whitespace();
+        match reader.peek() {
+            None => Ok(json),
+            Some(c) => Err(JsonError::UnexpectedChar(c, reader.index)),
+        }
+    }
+
+    pub fn object<'k>(fields: impl IntoIterator<Item = (&'k str, Json)>) -> Self {
+        Self::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    /// Looks up a field of an object, anything else has no fields
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| n.fract() == 0.0 && *n >= 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Self::Array(value)
+    }
+}
+
+fn write_string(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => f.write_fmt(format_args!("\\u{:04x}", c as u32))?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => f.write_fmt(format_args!("{b}")),
+            Json::Number(n) if n.is_finite() => f.write_fmt(format_args!("{n}")),
+            Json::Number(_) => f.write_str("null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    Display::fmt(item, f)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    f.write_char(':')?;
+                    Display::fmt(value, f)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    index: usize,
+    /// How many arrays and objects the reader is inside
+    depth: usize,
+    /// Where reading started on the stack
+    stack: StackBase,
+}
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.index..].chars().next()
+    }
+
+    fn advance(&mut self) -> Result<char, JsonError> {
+        let c = self.peek().ok_or(JsonError::UnexpectedEnd)?;
+        self.index += c.len_utf8();
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        let at = self.index;
+        match self.advance()? {
+            c if c == expected => Ok(()),
+            c => Err(JsonError::UnexpectedChar(c, at)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.index += 1;
+        }
+    }
+
+    fn keyword(&mut self, word: &str, json: Json) -> Result<Json, JsonError> {
+        if self.input[self.index..].starts_with(word) {
+            self.index += word.len();
+            Ok(json)
+        } else {
+            let c = self.peek().ok_or(JsonError::UnexpectedEnd)?;
+            Err(JsonError::UnexpectedChar(c, self.index))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek().ok_or(JsonError::UnexpectedEnd)? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => self.nested(Self::array),
+            '{' => self.nested(Self::object),
+            '-' | '0'..='9' => self.number(),
+            c => Err(JsonError::UnexpectedChar(c, self.index)),
+        }
+    }
+
+    /// Reads an array or object, failing before nesting overflows the stack
+    fn nested(
+        &mut self,
+        read: fn(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        if self.depth >= MAX_DEPTH || self.stack.used() > DEFAULT_STACK_BYTES {
+            return Err(JsonError::NestingTooDeep(self.index));
+        }
+        self.depth += 1;
+        let json = read(self);
+        self.depth -= 1;
+        json
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.index += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            let at = self.index;
+            match self.advance()? {
+                ',' => continue,
+                ']' => return Ok(Json::Array(items)),
+                c => return Err(JsonError::UnexpectedChar(c, at)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.index += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            let at = self.index;
+            match self.advance()? {
+                ',' => continue,
+                '}' => return Ok(Json::Object(fields)),
+                c => return Err(JsonError::UnexpectedChar(c, at)),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let at = self.index;
+        let digits = self
+            .input
+            .get(self.index..self.index + 4)
+            .ok_or(JsonError::InvalidEscape(at))?;
+        let n = u32::from_str_radix(digits, 16).map_err(|_| JsonError::InvalidEscape(at))?;
+        self.index += 4;
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let at = self.index;
+            match self.advance()? {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.advance()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let high = self.hex4()?;
+                            let code = if (0xD800..0xDC00).contains(&high) {
+                                self.expect('\\')?;
+                                self.expect('u')?;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(JsonError::InvalidEscape(at));
+                                }
+                                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                            } else {
+                                high
+                            };
+                            char::from_u32(code).ok_or(JsonError::InvalidEscape(at))?
+                        }
+                        _ => return Err(JsonError::InvalidEscape(at)),
+                    };
+                    s.push(c);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.index;
+        while matches!(self.peek(), Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+            self.index += 1;
+        }
+        self.input[start..self.index]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| JsonError::InvalidNumber(start))
+    }
+}
+
+/// Reads one `Content-Length` framed message, the base protocol shared by
+/// LSP and DAP. Returns `None` once the input is exhausted.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes `message` with a `Content-Length` header
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::JsonError;
+
+    use super::{read_message, write_message, Json};
+
+    #[test]
+    fn parse_nested_document() {
+        let input = r#" {"a": [1, -2.5e1, true, null], "b": {"c": "d"}} "#;
+        let json = Json::parse(input).unwrap();
+
+        let expected = Json::object([
+            (
+                "a",
+                Json::Array(vec![
+                    Json::Number(1.),
+                    Json::Number(-25.),
+                    Json::Bool(true),
+                    Json::Null,
+                ]),
+            ),
+            ("b", Json::object([("c", "d".into())])),
+        ]);
+
+        assert_eq!(expected, json);
+    }
+
+    #[test]
+    fn parse_string_escapes() {
+        let json = Json::parse(r#""a\"b\\c\né😀""#).unwrap();
+
+        assert_eq!(Some("a\"b\\c\né😀"), json.as_str());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(JsonError::UnexpectedEnd), Json::parse("[1, 2"));
+        assert_eq!(
+            Err(JsonError::UnexpectedChar('}', 5)),
+            Json::parse("[1, 2}")
+        );
+        assert_eq!(Err(JsonError::InvalidEscape(1)), Json::parse(r#""\q""#));
+        assert!(matches!(
+            Json::parse(&"[".repeat(100_000)),
+            Err(JsonError::NestingTooDeep(_))
+        ));
+    }
+
+    #[test]
+    fn write_compact() {
+        let json = Json::object([
+            ("id", 1usize.into()),
+            ("text", "say \"hi\"\n".into()),
+            ("list", Json::Array(vec![Json::Null, false.into()])),
+        ]);
+
+        let expected = r#"{"id":1,"text":"say \"hi\"\n","list":[null,false]}"#;
+
+        assert_eq!(expected, json.to_string());
+        assert_eq!(Ok(json), Json::parse(expected));
+    }
+
+    #[test]
+    fn framed_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &Json::object([("é", Json::Null)])).unwrap();
+
+        assert!(buffer.starts_with(b"Content-Length: 11\r\n\r\n"));
+
+        let mut input = &buffer[..];
+        let body = read_message(&mut input).unwrap();
+        assert_eq!(Some(r#"{"é":null}"#.to_string()), body);
+        assert_eq!(None, read_message(&mut input).unwrap());
+    }
+}
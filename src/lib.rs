@@ -0,0 +1,327 @@
+//! # Lox
+//!
+//! arithmatic operators work on numbers
+//!
+//! comparison operators always return a boolean
+//!
+//! equality of different types is always false
+//!
+//! against implicit conversions
+//!
+//! logical operators work on booleans, (`!`, `and`, `or`)
+//!
+//! boolean operators short-circuit
+//!
+//! `var` to declare variables
+//!
+//! `fun` declares functions
+//!
+//! ## Embedding
+//!
+//! [`Lox`] runs scripts, and the host can hand values to them and read
+//! values back
+//!
+//! ```
+//! use lox::{Lox, Value};
+//!
+//! let mut lox = Lox::new();
+//! lox.define("width", Value::Int(3));
+//! lox.run("var area = width * 4;").unwrap();
+//! assert_eq!(lox.get("area"), Some(Value::Int(12)));
+//! ```
+//!
+//! What scripts print goes to [`Lox::set_output`] and
+//! [`Lox::set_error_output`], and [`run_captured`] collects both for tests.
+//! What they read comes from [`Lox::set_input`].
+//! Scripts that aren't trusted can be given [`Limits`] on the steps they
+//! take, how deep they call, the memory they hold and when they must stop.
+//!
+//! Parsing and running recurse on the Rust stack. They measure how much
+//! they have used and fail with "nesting too deep" past
+//! [`Limits::stack_bytes`], 1 MiB by default, which fits any thread with
+//! the default stack. A host that runs scripts on a thread with more can
+//! raise it to let them recurse deeper.
+//!
+//! ## Versioning
+//!
+//! The public API is [`Lox`] and the functions beside it, [`Value`], the
+//! errors in [`error`] and the tree in [`syntax`]. The scanner, parser,
+//! language server and debug adapter behind them are private.
+//!
+//! The public API follows semantic versioning through [`VERSION`]. While
+//! the crate is below 1.0 a minor release may break it and a patch release
+//! won't.
+//!
+//! [`InterpreterError`], [`Value`], [`syntax::Expr`] and
+//! [`syntax::StmtKind`] are `#[non_exhaustive]` because they gain variants
+//! as the language grows, so match them with a wildcard arm.
+
+#![warn(missing_docs)]
+
+use std::{
+    cell::RefCell,
+    io::{stdin, stdout, BufRead, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{debugger::Debugger, interpreter::Interpreter, parser::LoxParser, syntax::Stmt};
+
+pub use crate::{
+    capture::{run_captured, Captured},
+    error::InterpreterError,
+    interpreter::Limits,
+    value::Value,
+};
+
+mod capture;
+mod dap;
+mod debugger;
+mod environment;
+pub mod error;
+mod input;
+mod interpreter;
+mod json;
+mod lsp;
+mod natives;
+mod parser;
+mod scanner;
+mod stack;
+pub mod syntax;
+mod token;
+pub mod value;
+
+/// The version of this crate, and of its API
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Parses a script without running it
+pub fn parse(script: &str) -> Result<Vec<Stmt>, InterpreterError> {
+    Ok(LoxParser::new(script).parse()?)
+}
+
+/// Parses a script, or reads a syntax tree from a `.json` file
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Vec<Stmt>, InterpreterError> {
+    let path = path.as_ref();
+    let data = std::fs::read_to_string(path)?;
+    if is_json(path) {
+        Ok(syntax::from_json(&data)?)
+    } else {
+        parse(&data)
+    }
+}
+
+/// Serves the Language Server Protocol over `input` and `output` until
+/// the client exits, as `lox lsp` does over stdio
+pub fn serve_lsp<R: BufRead, W: Write>(input: R, output: W) -> Result<(), InterpreterError> {
+    lsp::serve(input, output)
+}
+
+/// Serves the Debug Adapter Protocol over `input` and `output` until the
+/// client disconnects, as `lox dap` does over stdio
+pub fn serve_dap<R, W>(input: R, output: W) -> Result<(), InterpreterError>
+where
+    R: BufRead + 'static,
+    W: Write + 'static,
+{
+    dap::serve(input, output)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+/// An interpreter and the globals it keeps between runs
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+impl Lox {
+    /// An interpreter with only the natives defined
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+        }
+    }
+    /// Reads and runs lines from stdin until it closes
+    pub fn run_prompt(&mut self) -> Result<(), InterpreterError> {
+        loop {
+            print!("> ");
+            let mut line = Default::default();
+            stdin().read_line(&mut line)?;
+            if line.is_empty() {
+                return Ok(());
+            }
+            self.run(&line)?;
+        }
+    }
+
+    /// Runs a script, the files it imports are relative to it
+    ///
+    /// A `.json` file is read as a syntax tree, see [`syntax::from_json`].
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), InterpreterError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)?;
+        let statements = if is_json(path) {
+            syntax::from_json(&data)?
+        } else {
+            self.interpreter.parse(&data)?
+        };
+        self.interpreter.set_file(path);
+        self.interpreter.interpret(&statements)
+    }
+
+    /// Runs a file under the interactive debugger, reading commands from
+    /// stdin between the lines the script reads
+    pub fn debug_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), InterpreterError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)?;
+        let stdin = Rc::new(RefCell::new(stdin().lock()));
+        let shared = || {
+            let stdin = stdin.clone();
+            input::Lines::new(move |line: &mut Vec<u8>| stdin.borrow_mut().read_until(b'\n', line))
+        };
+        let debugger = Debugger::new(path.display().to_string(), &data, shared(), stdout());
+        self.interpreter.set_hook(Box::new(debugger));
+        self.interpreter.set_input(Box::new(shared()));
+        self.interpreter.set_file(path);
+
+        let statements = self.interpreter.parse(&data)?;
+        self.interpreter.interpret(&statements)
+    }
+
+    /// Runs a script, its globals are kept for the next run
+    pub fn run(&mut self, script: &str) -> Result<(), InterpreterError> {
+        let statements = self.interpreter.parse(script)?;
+        self.interpreter.interpret(&statements)
+    }
+
+    /// Runs statements that are already parsed, such as ones read with
+    /// [`syntax::from_json`]
+    pub fn run_ast(&mut self, statements: &[Stmt]) -> Result<(), InterpreterError> {
+        self.interpreter.interpret(statements)
+    }
+
+    /// Where `readLine` and `readAll` read from, stdin by default
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.interpreter.set_input(input);
+    }
+
+    /// Where `print` writes to, stdout by default
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.interpreter.set_output(output);
+    }
+
+    /// Where `eprint` writes to, stderr by default
+    pub fn set_error_output(&mut self, output: Box<dyn Write>) {
+        self.interpreter.set_error_output(output);
+    }
+
+    /// Limits on what scripts can use. By default calls nest at most 1000
+    /// deep and the stack is limited to 1 MiB, with no other limit.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.set_limits(limits);
+    }
+
+    /// Defines a global variable, replacing any with the same name
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.interpreter.define(name, value);
+    }
+
+    /// The value of a global variable
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.interpreter.get(name)
+    }
+
+    /// Calls a function value, such as one a script stored in a global
+    pub fn call(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        self.interpreter.call_value(function, arguments)
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{error::InterpreterError, run_captured, value::Value};
+
+    use super::Lox;
+
+    #[test]
+    fn run_with_unexpected_char() {
+        let mut lox = Lox::new();
+        let bad_input = "{}#+-";
+        let result = lox.run(bad_input);
+        println!("{:?}", result);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn example_numeric_expression() {
+        let input = "print 1 + 2 * 3 == 7;";
+        let captured = run_captured(input);
+        captured.result.unwrap();
+        assert_eq!("true\n", captured.stdout);
+    }
+
+    #[test]
+    fn example_string_concat() {
+        let input = "print \"hello, \" + \"world!\" == \"hello, world!\";";
+        let captured = run_captured(input);
+        captured.result.unwrap();
+        assert_eq!("true\n", captured.stdout);
+    }
+
+    #[test]
+    fn example_variables_in_blocks() {
+        let input = "var a = 1; { var a = 2; a = a + 1; } a = a * 10; print a == 10;";
+        let captured = run_captured(input);
+        captured.result.unwrap();
+        assert_eq!("true\n", captured.stdout);
+    }
+
+    #[test]
+    fn undefined_variable() {
+        let mut lox = Lox::new();
+        let result = lox.run("{ var a = 1; } print a;");
+        assert!(matches!(result, Err(InterpreterError::UndefinedVariable(name)) if name == "a"));
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let mut lox = Lox::new();
+        let result = lox.run("var big = 9223372036854775807; print -big - 1; print big + 1;");
+        assert!(matches!(result, Err(InterpreterError::IntegerOverflow)));
+    }
+
+    #[test]
+    fn host_values() {
+        let mut lox = Lox::new();
+        lox.define("scale", Value::Int(10));
+        lox.run("var double = fun (x) { return x * 2 * scale; };")
+            .unwrap();
+
+        let double = lox.get("double").unwrap();
+        let result = lox.call(&double, vec![Value::Int(4)]).unwrap();
+        assert_eq!(result, Value::Int(80));
+        assert_eq!(lox.get("missing"), None);
+    }
+
+    #[test]
+    fn calling_a_non_function() {
+        let mut lox = Lox::new();
+        let result = lox.call(&Value::Nil, vec![]);
+        assert!(matches!(
+            result,
+            Err(InterpreterError::TypeError(Value::Nil))
+        ));
+    }
+}
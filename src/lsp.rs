@@ -0,0 +1,394 @@
+//! A Language Server Protocol server over stdio, started with `lox lsp`
+//!
+//! Documents are synchronised in full on every change, which re-publishes
+//! the diagnostics from the `Scanner` and [`LoxParser`].
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    error::InterpreterError,
+    json::{read_message, write_message, Json},
+    parser::LoxParser,
+    scanner::Scanner,
+};
+
+use index::{SymbolIndex, SymbolKind};
+
+const PARSE_ERROR: f64 = -32700.;
+const METHOD_NOT_FOUND: f64 = -32601.;
+
+/// Serves requests from `input` until it ends or the client sends `exit`
+pub fn serve<R: BufRead, W: Write>(mut input: R, output: W) -> Result<(), InterpreterError> {
+    let mut server = LanguageServer {
+        output,
+        documents: HashMap::new(),
+    };
+    while let Some(body) = read_message(&mut input)? {
+        let message = match Json::parse(&body) {
+            Ok(message) => message,
+            Err(err) => {
+                server.error(Json::Null, PARSE_ERROR, err.to_string())?;
+                continue;
+            }
+        };
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct LanguageServer<W> {
+    output: W,
+    /// The text of each open document, by uri
+    documents: HashMap<String, String>,
+}
+
+impl<W: Write> LanguageServer<W> {
+    /// Handles one message, returns `false` when the server should exit
+    fn handle(&mut self, message: &Json) -> io::Result<bool> {
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        let result = match method {
+            "exit" => return Ok(false),
+            "initialize" => capabilities(),
+            "shutdown" => Json::Null,
+            "textDocument/didOpen" => {
+                let document = params.get("textDocument");
+                let uri = document.and_then(|d| d.get("uri")).and_then(Json::as_str);
+                let text = document.and_then(|d| d.get("text")).and_then(Json::as_str);
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    self.documents.insert(uri.into(), text.into());
+                    self.publish_diagnostics(uri)?;
+                }
+                return Ok(true);
+            }
+            "textDocument/didChange" => {
+                let uri = params
+                    .get("textDocument")
+                    .and_then(|d| d.get("uri"))
+                    .and_then(Json::as_str);
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    self.documents.insert(uri.into(), text.into());
+                    self.publish_diagnostics(uri)?;
+                }
+                return Ok(true);
+            }
+            "textDocument/didClose" => {
+                let uri = params
+                    .get("textDocument")
+                    .and_then(|d| d.get("uri"))
+                    .and_then(Json::as_str);
+                if let Some(uri) = uri {
+                    self.documents.remove(uri);
+                    self.notify(
+                        "textDocument/publishDiagnostics",
+                        Json::object([("uri", uri.into()), ("diagnostics", Json::Array(vec![]))]),
+                    )?;
+                }
+                return Ok(true);
+            }
+            "textDocument/hover" => self.hover(params).unwrap_or(Json::Null),
+            "textDocument/definition" => self.definition(params).unwrap_or(Json::Null),
+            "textDocument/references" => self.references(params).unwrap_or(Json::Null),
+            "textDocument/documentSymbol" => self.symbols(params).unwrap_or(Json::Null),
+            _ => {
+                if let Some(id) = id {
+                    self.error(id, METHOD_NOT_FOUND, format!("Unknown method {method:?}"))?;
+                }
+                return Ok(true);
+            }
+        };
+
+        if let Some(id) = id {
+            let response =
+                Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)]);
+            write_message(&mut self.output, &response)?;
+        }
+        Ok(true)
+    }
+
+    fn error(&mut self, id: Json, code: f64, message: String) -> io::Result<()> {
+        let response = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id),
+            (
+                "error",
+                Json::object([("code", code.into()), ("message", message.into())]),
+            ),
+        ]);
+        write_message(&mut self.output, &response)
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        let notification = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ]);
+        write_message(&mut self.output, &notification)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let text = &self.documents[uri];
+        let diagnostics = diagnostics(text);
+        self.notify(
+            "textDocument/publishDiagnostics",
+            Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+        )
+    }
+
+    /// The document and the position in it that a request is about
+    fn document_position<'a>(&'a self, params: &Json) -> Option<(&'a str, &'a str, usize, usize)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let (uri, text) = self.documents.get_key_value(uri)?;
+        let (row, col) = from_position(text, params.get("position")?)?;
+        Some((uri, text, row, col))
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let (_, text, row, col) = self.document_position(params)?;
+        let index = SymbolIndex::new(text);
+        let occurrence = index.occurrence_at(row, col)?;
+        let symbol = &index.symbols[occurrence.symbol];
+        let value = format!(
+            "({}) `{}`\n\ndeclared at line {}, column {}",
+            symbol.kind, symbol.name, symbol.decl.row, symbol.decl.col
+        );
+        let len = symbol.name.chars().count();
+        Some(Json::object([
+            (
+                "contents",
+                Json::object([("kind", "markdown".into()), ("value", value.into())]),
+            ),
+            (
+                "range",
+                range(text, occurrence.meta.row, occurrence.meta.col, len),
+            ),
+        ]))
+    }
+
+    fn definition(&self, params: &Json) -> Option<Json> {
+        let (uri, text, row, col) = self.document_position(params)?;
+        let index = SymbolIndex::new(text);
+        let occurrence = index.occurrence_at(row, col)?;
+        let symbol = &index.symbols[occurrence.symbol];
+        let len = symbol.name.chars().count();
+        Some(location(
+            uri,
+            range(text, symbol.decl.row, symbol.decl.col, len),
+        ))
+    }
+
+    fn references(&self, params: &Json) -> Option<Json> {
+        let (uri, text, row, col) = self.document_position(params)?;
+        let include_declaration = params
+            .get("context")
+            .and_then(|context| context.get("includeDeclaration"))
+            .and_then(Json::as_bool)
+            .unwrap_or(true);
+        let index = SymbolIndex::new(text);
+        let symbol = index.occurrence_at(row, col)?.symbol;
+        let len = index.symbols[symbol].name.chars().count();
+        let locations = index
+            .references(symbol)
+            .filter(|occurrence| include_declaration || !occurrence.declaration)
+            .map(|occurrence| {
+                location(
+                    uri,
+                    range(text, occurrence.meta.row, occurrence.meta.col, len),
+                )
+            })
+            .collect::<Vec<_>>();
+        Some(locations.into())
+    }
+
+    fn symbols(&self, params: &Json) -> Option<Json> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let text = self.documents.get(uri)?;
+        let index = SymbolIndex::new(text);
+        let symbols = index
+            .symbols
+            .iter()
+            .filter_map(|symbol| {
+                // the values of the LSP `SymbolKind` enumeration
+                let kind = match symbol.kind {
+                    SymbolKind::Variable => 13.,
+                    SymbolKind::Function => 12.,
+                    SymbolKind::Parameter => return None,
+                };
+                let range = range(
+                    text,
+                    symbol.decl.row,
+                    symbol.decl.col,
+                    symbol.name.chars().count(),
+                );
+                Some(Json::object([
+                    ("name", symbol.name.as_str().into()),
+                    ("kind", kind.into()),
+                    ("range", range.clone()),
+                    ("selectionRange", range),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        Some(symbols.into())
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // full document sync
+                ("textDocumentSync", 1usize.into()),
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+                ("referencesProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "lox".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn diagnostics(text: &str) -> Vec<Json> {
+    let mut diagnostics: Vec<_> = Scanner::new(text)
+        .filter_map(Result::err)
+        .map(|err| {
+            let (row, col) = err.position();
+            diagnostic(text, row, col, err.to_string())
+        })
+        .collect();
+
+    // the parser stops at the first lexical error, which is already reported
+    if diagnostics.is_empty() {
+        if let Err(err) = LoxParser::new(text).parse() {
+            let (row, col) = err.position().unwrap_or((1, 1));
+            diagnostics.push(diagnostic(text, row, col, err.to_string()));
+        }
+    }
+    diagnostics
+}
+
+fn diagnostic(text: &str, row: usize, col: usize, message: String) -> Json {
+    Json::object([
+        ("range", range(text, row, col, 1)),
+        // error
+        ("severity", 1usize.into()),
+        ("source", "lox".into()),
+        ("message", message.into()),
+    ])
+}
+
+fn location(uri: &str, range: Json) -> Json {
+    Json::object([("uri", uri.into()), ("range", range)])
+}
+
+/// LSP positions count lines and UTF-16 code units from zero, while
+/// [`crate::token::TokenMeta`] counts rows and chars from one
+fn position(text: &str, row: usize, col: usize) -> Json {
+    let line = text.lines().nth(row - 1).unwrap_or("");
+    let character: usize = line.chars().take(col - 1).map(char::len_utf16).sum();
+    Json::object([("line", (row - 1).into()), ("character", character.into())])
+}
+
+fn range(text: &str, row: usize, col: usize, len: usize) -> Json {
+    Json::object([
+        ("start", position(text, row, col)),
+        ("end", position(text, row, col + len)),
+    ])
+}
+
+fn from_position(text: &str, position: &Json) -> Option<(usize, usize)> {
+    let line = position.get("line")?.as_usize()?;
+    let character = position.get("character")?.as_usize()?;
+    let mut units = 0;
+    let mut col = 1;
+    for c in text.lines().nth(line)?.chars() {
+        if units >= character {
+            break;
+        }
+        units += c.len_utf16();
+        col += 1;
+    }
+    Some((line + 1, col))
+}
+
+mod index;
+
+#[cfg(test)]
+mod test {
+    use crate::json::{read_message, Json};
+
+    use super::serve;
+
+    fn frame(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn session(messages: &[&str]) -> Vec<Json> {
+        let input: String = messages.iter().map(|m| frame(m)).collect();
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output).unwrap();
+
+        let mut output = &output[..];
+        let mut responses = Vec::new();
+        while let Some(body) = read_message(&mut output).unwrap() {
+            responses.push(Json::parse(&body).unwrap());
+        }
+        responses
+    }
+
+    #[test]
+    fn open_publishes_lexical_diagnostics() {
+        let responses = session(&[
+            r##"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","text":"print 1;\nprint \"😀\"#;"}}}"##,
+        ]);
+
+        let diagnostics = responses[0]
+            .get("params")
+            .and_then(|p| p.get("diagnostics"))
+            .and_then(Json::as_array)
+            .unwrap();
+        assert_eq!(1, diagnostics.len());
+        let start = diagnostics[0]
+            .get("range")
+            .and_then(|r| r.get("start"))
+            .unwrap();
+        assert_eq!(
+            &Json::object([("line", 1usize.into()), ("character", 10usize.into())]),
+            start
+        );
+    }
+
+    #[test]
+    fn unknown_requests_are_errors() {
+        let responses = session(&[
+            r#"{"jsonrpc":"2.0","id":7,"method":"workspace/unknown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+            r#"{"jsonrpc":"2.0","id":8,"method":"shutdown"}"#,
+        ]);
+
+        assert_eq!(1, responses.len());
+        assert_eq!(Some(7.), responses[0].get("id").and_then(Json::as_f64));
+        assert!(responses[0].get("error").is_some());
+    }
+}
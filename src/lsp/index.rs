@@ -0,0 +1,432 @@
+//! Resolves identifiers to their declarations straight from the token
+//! stream, so it keeps working on files that don't parse.
+
+use std::fmt::Display;
+
+use crate::{
+    scanner::Scanner,
+    token::{Keyword, Operator, Structure, Token, TokenKind, TokenMeta},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+    Parameter,
+}
+
+impl Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolKind::Variable => f.write_str("variable"),
+            SymbolKind::Function => f.write_str("function"),
+            SymbolKind::Parameter => f.write_str("parameter"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub decl: TokenMeta,
+}
+
+/// An identifier token which refers to a symbol
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    pub symbol: usize,
+    pub meta: TokenMeta,
+    pub declaration: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    pub symbols: Vec<Symbol>,
+    /// Sorted by position in the source
+    pub occurrences: Vec<Occurrence>,
+}
+
+fn is_identifier(token: &&Token) -> bool {
+    matches!(token.kind, TokenKind::Identifier(_))
+}
+
+fn is_arrow(token: &&Token) -> bool {
+    token.kind == TokenKind::Operator(Operator::Arrow)
+}
+
+/// How many tokens after a `(` are the parameters of an arrow function and
+/// its `)`, none if the parenthesis isn't the start of one
+fn arrow_params<'a>(mut tokens: impl Iterator<Item = &'a Token>) -> Option<usize> {
+    let close = TokenKind::Structure(Structure::RightParen);
+    let mut count = 0;
+    loop {
+        let token = tokens.next()?;
+        count += 1;
+        match &token.kind {
+            kind if *kind == close => break,
+            TokenKind::Identifier(_) if count % 2 == 1 => {}
+            TokenKind::Structure(Structure::Comma) if count % 2 == 0 => {}
+            _ => return None,
+        }
+    }
+    tokens.next().filter(is_arrow).map(|_| count)
+}
+
+/// Whether the tokens after the name in a `var` assign it a function,
+/// `= fun (params)` or `= (params) =>`
+fn assigns_function<'a>(mut tokens: impl Iterator<Item = &'a Token>) -> bool {
+    if !tokens
+        .next()
+        .is_some_and(|token| token.kind == TokenKind::Operator(Operator::Equal))
+    {
+        return false;
+    }
+    match tokens.next().map(|token| &token.kind) {
+        Some(TokenKind::Keyword(Keyword::Fun)) => true,
+        Some(TokenKind::Structure(Structure::LeftParen)) => arrow_params(tokens).is_some(),
+        _ => false,
+    }
+}
+
+/// The name in the `(name)` after a `catch`, none if that isn't what
+/// `tokens` start with
+fn catch_binding<'a>(
+    mut tokens: impl Iterator<Item = &'a Token>,
+) -> Option<(&'a String, &'a TokenMeta)> {
+    let open = tokens.next()?;
+    let Token {
+        kind: TokenKind::Identifier(name),
+        meta,
+    } = tokens.next()?
+    else {
+        return None;
+    };
+    let close = tokens.next()?;
+    (open.kind == TokenKind::Structure(Structure::LeftParen)
+        && close.kind == TokenKind::Structure(Structure::RightParen))
+    .then_some((name, meta))
+}
+
+/// The name in the `"path" as name` after an `import`, none if that isn't
+/// what `tokens` start with
+fn module_binding<'a>(
+    mut tokens: impl Iterator<Item = &'a Token>,
+) -> Option<(&'a String, &'a TokenMeta)> {
+    let path = tokens.next()?;
+    let word = tokens.next()?;
+    let Token {
+        kind: TokenKind::Identifier(name),
+        meta,
+    } = tokens.next()?
+    else {
+        return None;
+    };
+    (is_path(&path) && matches!(&word.kind, TokenKind::Identifier(word) if word == "as"))
+        .then_some((name, meta))
+}
+
+fn is_path(token: &&Token) -> bool {
+    matches!(token.kind, TokenKind::String(_))
+}
+
+impl SymbolIndex {
+    pub fn new(source: &str) -> Self {
+        let mut index = Self::default();
+        // each scope lists the symbols declared in it, a reference resolves to
+        // the latest declaration in the innermost scope that has one
+        let mut scopes = vec![Vec::new()];
+        let mut parameters = Vec::new();
+        let mut unresolved = Vec::new();
+        // how many brackets are open, and how many were at the start of the
+        // body of each arrow function being read, a body ends at a `,` or
+        // `;` outside any bracket it opened, or at a bracket it didn't open
+        let mut brackets: usize = 0;
+        let mut arrows = Vec::new();
+
+        let tokens: Vec<_> = Scanner::new(source).filter_map(Result::ok).collect();
+        let mut tokens = tokens.iter().peekable();
+        while let Some(token) = tokens.next() {
+            let ends = match token.kind {
+                TokenKind::Structure(Structure::Comma | Structure::SemiColon) => Some(brackets),
+                TokenKind::Structure(
+                    Structure::RightParen | Structure::RightBracket | Structure::RightBrace,
+                ) => {
+                    let ends = brackets;
+                    brackets = brackets.saturating_sub(1);
+                    Some(ends)
+                }
+                TokenKind::Structure(
+                    Structure::LeftParen | Structure::LeftBracket | Structure::LeftBrace,
+                ) => {
+                    brackets += 1;
+                    None
+                }
+                _ => None,
+            };
+            if let Some(ends) = ends {
+                while arrows.last().is_some_and(|&arrow| arrow >= ends) {
+                    arrows.pop();
+                    scopes.pop();
+                }
+            }
+            match &token.kind {
+                TokenKind::Keyword(Keyword::Var) => {
+                    if let Some(Token {
+                        kind: TokenKind::Identifier(name),
+                        meta,
+                    }) = tokens.next_if(is_identifier)
+                    {
+                        let kind = if assigns_function(tokens.clone()) {
+                            SymbolKind::Function
+                        } else {
+                            SymbolKind::Variable
+                        };
+                        let symbol = index.declare(name.clone(), kind, meta);
+                        scopes.last_mut().unwrap().push(symbol);
+                    }
+                }
+                TokenKind::Keyword(Keyword::Fun) => {
+                    let open = TokenKind::Structure(Structure::LeftParen);
+                    let close = TokenKind::Structure(Structure::RightParen);
+                    if tokens.next_if(|token| token.kind == open).is_some() {
+                        while let Some(token) = tokens.next_if(|token| token.kind != close) {
+                            if let TokenKind::Identifier(name) = &token.kind {
+                                let symbol =
+                                    index.declare(name.clone(), SymbolKind::Parameter, &token.meta);
+                                parameters.push(symbol);
+                            }
+                        }
+                        tokens.next_if(|token| token.kind == close);
+                    }
+                }
+                TokenKind::Structure(Structure::LeftParen) => {
+                    if let Some(count) = arrow_params(tokens.clone()) {
+                        brackets -= 1;
+                        for token in tokens.by_ref().take(count) {
+                            if let TokenKind::Identifier(name) = &token.kind {
+                                let symbol =
+                                    index.declare(name.clone(), SymbolKind::Parameter, &token.meta);
+                                parameters.push(symbol);
+                            }
+                        }
+                    }
+                }
+                TokenKind::Keyword(Keyword::Import) => {
+                    if let Some((name, meta)) = module_binding(tokens.clone()) {
+                        tokens.nth(2);
+                        let symbol = index.declare(name.clone(), SymbolKind::Variable, meta);
+                        scopes.last_mut().unwrap().push(symbol);
+                    }
+                }
+                // `from "path" import names`, `from` is only a keyword when a
+                // path follows it
+                TokenKind::Identifier(from)
+                    if from == "from" && tokens.next_if(is_path).is_some() =>
+                {
+                    let import = TokenKind::Keyword(Keyword::Import);
+                    if tokens.next_if(|token| token.kind == import).is_some() {
+                        while let Some(Token {
+                            kind: TokenKind::Identifier(name),
+                            meta,
+                        }) = tokens.next_if(is_identifier)
+                        {
+                            let symbol = index.declare(name.clone(), SymbolKind::Variable, meta);
+                            scopes.last_mut().unwrap().push(symbol);
+                            let comma = TokenKind::Structure(Structure::Comma);
+                            if tokens.next_if(|token| token.kind == comma).is_none() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                TokenKind::Keyword(Keyword::Catch) => {
+                    if let Some((name, meta)) = catch_binding(tokens.clone()) {
+                        tokens.nth(2);
+                        let symbol = index.declare(name.clone(), SymbolKind::Variable, meta);
+                        parameters.push(symbol);
+                    }
+                }
+                TokenKind::Operator(Operator::Arrow) => {
+                    scopes.push(std::mem::take(&mut parameters));
+                    arrows.push(brackets);
+                }
+                TokenKind::Structure(Structure::LeftBrace) => {
+                    scopes.push(std::mem::take(&mut parameters));
+                }
+                TokenKind::Structure(Structure::RightBrace) if scopes.len() > 1 => {
+                    scopes.pop();
+                }
+                TokenKind::Identifier(name) => {
+                    let symbol = scopes.iter().rev().find_map(|scope| {
+                        scope
+                            .iter()
+                            .rev()
+                            .copied()
+                            .find(|&symbol| index.symbols[symbol].name == *name)
+                    });
+                    match symbol {
+                        Some(symbol) => index.occurrences.push(Occurrence {
+                            symbol,
+                            meta: token.meta.clone(),
+                            declaration: false,
+                        }),
+                        None => unresolved.push((name, &token.meta)),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // functions may be used before they are declared
+        for (name, meta) in unresolved {
+            let symbol = scopes[0]
+                .iter()
+                .copied()
+                .find(|&symbol| index.symbols[symbol].name == *name);
+            if let Some(symbol) = symbol {
+                index.occurrences.push(Occurrence {
+                    symbol,
+                    meta: meta.clone(),
+                    declaration: false,
+                });
+            }
+        }
+
+        index
+            .occurrences
+            .sort_by_key(|occurrence| (occurrence.meta.row, occurrence.meta.col));
+        index
+    }
+
+    fn declare(&mut self, name: String, kind: SymbolKind, meta: &TokenMeta) -> usize {
+        let symbol = self.symbols.len();
+        self.occurrences.push(Occurrence {
+            symbol,
+            meta: meta.clone(),
+            declaration: true,
+        });
+        self.symbols.push(Symbol {
+            name,
+            kind,
+            decl: meta.clone(),
+        });
+        symbol
+    }
+
+    /// The occurrence whose identifier covers `row` and `col`, including
+    /// the position just past its end
+    pub fn occurrence_at(&self, row: usize, col: usize) -> Option<&Occurrence> {
+        self.occurrences.iter().find(|occurrence| {
+            let len = self.symbols[occurrence.symbol].name.chars().count();
+            occurrence.meta.row == row
+                && occurrence.meta.col <= col
+                && col <= occurrence.meta.col + len
+        })
+    }
+
+    pub fn references(&self, symbol: usize) -> impl Iterator<Item = &Occurrence> {
+        self.occurrences
+            .iter()
+            .filter(move |occurrence| occurrence.symbol == symbol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SymbolIndex, SymbolKind};
+
+    #[test]
+    fn resolve_shadowed_variables() {
+        let input = "var a = 1;\n{\n  var a = 2;\n  print a;\n}\nprint a;";
+        let index = SymbolIndex::new(input);
+
+        let inner = index.occurrence_at(4, 9).unwrap();
+        assert_eq!((3, 7), {
+            let decl = &index.symbols[inner.symbol].decl;
+            (decl.row, decl.col)
+        });
+
+        let outer = index.occurrence_at(6, 7).unwrap();
+        assert_eq!(2, index.references(outer.symbol).count());
+        assert_eq!(1, index.symbols[outer.symbol].decl.row);
+    }
+
+    #[test]
+    fn resolve_parameters_and_later_functions() {
+        let input = "var a = fun (x) { return b(x); };\nvar b = (y) => y;";
+        let index = SymbolIndex::new(input);
+
+        let x = index.occurrence_at(1, 28).unwrap();
+        assert_eq!(SymbolKind::Parameter, index.symbols[x.symbol].kind);
+
+        let b = index.occurrence_at(1, 26).unwrap();
+        let symbol = &index.symbols[b.symbol];
+        assert_eq!((SymbolKind::Function, 2), (symbol.kind, symbol.decl.row));
+    }
+
+    #[test]
+    fn resolve_arrow_parameters() {
+        let input = "var a = 1;\nvar f = (a) => a;\nvar g = (b) => b + a;\nprint a;";
+        let index = SymbolIndex::new(input);
+        let decl = |row, col| {
+            let occurrence = index.occurrence_at(row, col).unwrap();
+            let symbol = &index.symbols[occurrence.symbol];
+            (symbol.kind, symbol.decl.row, symbol.decl.col)
+        };
+
+        assert_eq!((SymbolKind::Parameter, 2, 10), decl(2, 16));
+        assert_eq!((SymbolKind::Parameter, 3, 10), decl(3, 16));
+        assert_eq!((SymbolKind::Variable, 1, 5), decl(3, 20));
+        assert_eq!((SymbolKind::Variable, 1, 5), decl(4, 7));
+
+        let index = SymbolIndex::new("var a = 1;\nvar f = fun (g) { return g((a) => a, a); };");
+        let decl = |col| {
+            index.symbols[index.occurrence_at(2, col).unwrap().symbol]
+                .decl
+                .row
+        };
+        assert_eq!(2, decl(35));
+        assert_eq!(1, decl(38));
+    }
+
+    #[test]
+    fn resolve_catch_bindings() {
+        let input = "var e = 1;\ntry {\n  throw 2;\n} catch (e) {\n  print e;\n}\nprint e;";
+        let index = SymbolIndex::new(input);
+
+        let caught = index.occurrence_at(5, 9).unwrap();
+        assert_eq!((4, 10), {
+            let decl = &index.symbols[caught.symbol].decl;
+            (decl.row, decl.col)
+        });
+
+        let outer = index.occurrence_at(7, 7).unwrap();
+        assert_eq!(1, index.symbols[outer.symbol].decl.row);
+    }
+
+    #[test]
+    fn resolve_imported_names() {
+        let input =
+            "import \"a.lox\" as a;\nfrom \"b.lox\" import f, g;\nprint a[\"x\"] + f() + g();";
+        let index = SymbolIndex::new(input);
+        let decl = |col| {
+            let occurrence = index.occurrence_at(3, col).unwrap();
+            let decl = &index.symbols[occurrence.symbol].decl;
+            (decl.row, decl.col)
+        };
+
+        assert_eq!((1, 19), decl(7));
+        assert_eq!((2, 21), decl(16));
+        assert_eq!((2, 24), decl(22));
+    }
+
+    #[test]
+    fn ignore_lexical_errors() {
+        let index = SymbolIndex::new("# var a;\nvar b = \"a\" + a;");
+
+        let names: Vec<_> = index.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(["a", "b"], &names[..]);
+        assert_eq!(2, index.references(0).count());
+    }
+}
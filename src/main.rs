@@ -1,118 +1,143 @@
-//! # Lox
-//!
-//! arithmatic operators work on numbers
-//!
-//! comparison operators always return a boolean
-//!
-//! equality of different types is always false
-//!
-//! against implicit conversions
-//!
-//! logical operators work on booleans, (`!`, `and`, `or`)
-//!
-//! boolean operators short-circuit
-//!
-//! `var` to declare variables
-//!
-//! `fun` declares functions
+//! The `lox` command line, a thin wrapper over the library
 
-use std::{io::stdin, path::Path};
+use std::{
+    fmt::Display,
+    io::{self, stdin, stdout},
+    panic,
+    process::ExitCode,
+    thread,
+};
 
-use interpreter::Interpreter;
+use lox::{syntax, InterpreterError, Limits, Lox};
 
-use crate::{error::InterpreterError, parser::LoxParser};
+mod test_runner;
 
-mod error;
-mod interpreter;
-mod parser;
-mod scanner;
-mod syntax;
-mod token;
-mod value;
+/// Scripts recurse on the Rust stack, and the deepest they are allowed to
+/// go needs more than the main thread has
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
-fn main() -> Result<(), error::InterpreterError> {
-    let mut lox = Lox::new();
-    let args: Vec<_> = std::env::args().collect();
-    match &args[..] {
-        [] => lox.run_prompt().map_err(Into::into),
-        [script] => lox.run_file(script).map_err(Into::into),
-        _ => {
-            eprintln!("Usage: lox [script]");
-            Err(error::InterpreterError::TooManyArgs)
-        }
-    }
+fn main() -> ExitCode {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("start the interpreter thread")
+        .join()
+        .unwrap_or_else(|panic| panic::resume_unwind(panic))
 }
 
-pub struct Lox {
-    interpreter: Interpreter,
+/// Why a command failed
+#[derive(Debug)]
+enum Error {
+    /// The arguments aren't a command
+    Usage,
+    /// The format `lox ast --format` was given isn't one it writes
+    AstFormat(String),
+    /// How many of the scripts run by `lox test` failed
+    TestsFailed(usize),
+    Lox(InterpreterError),
 }
 
-impl Lox {
-    pub fn new() -> Self {
-        Self {
-            interpreter: Interpreter::new(),
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Usage => f.write_str("Error: Too many arguments"),
+            Error::AstFormat(format) => f.write_fmt(format_args!(
+                "Error: Unknown AST format {format:?}, expected lisp, json or dot"
+            )),
+            Error::TestsFailed(1) => f.write_str("1 test failed"),
+            Error::TestsFailed(n) => f.write_fmt(format_args!("{n} tests failed")),
+            Error::Lox(err) => Display::fmt(err, f),
         }
     }
-    pub fn run_prompt(&mut self) -> Result<(), InterpreterError> {
-        loop {
-            print!("> ");
-            let mut line = Default::default();
-            stdin().read_line(&mut line)?;
-            if line.is_empty() {
-                return Ok(());
-            }
-            self.run(&line)?;
-        }
-    }
-
-    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), InterpreterError> {
-        let data = std::fs::read_to_string(path)?;
-        self.run(&data)?;
+}
 
-        Ok(())
+impl From<InterpreterError> for Error {
+    fn from(value: InterpreterError) -> Self {
+        Self::Lox(value)
     }
+}
 
-    pub fn run(&mut self, script: &str) -> Result<(), InterpreterError> {
-        let mut parser = LoxParser::new(script);
-        let statements = parser.parse()?;
-        for stmt in &statements {
-            println!("{}", stmt.display_lisp());
-        }
-        self.interpreter.interpret(&statements)?;
-        Ok(())
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Lox(value.into())
     }
 }
 
-impl Default for Lox {
-    fn default() -> Self {
-        Self::new()
+fn run() -> ExitCode {
+    let mut lox = Lox::new();
+    // this thread has the stack for all the nesting the parser allows
+    lox.set_limits(Limits {
+        stack_bytes: Some(STACK_SIZE / 2),
+        ..Limits::default()
+    });
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let args: Vec<_> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        [] => lox.run_prompt().map_err(Error::from),
+        ["lsp"] => lox::serve_lsp(stdin().lock(), stdout().lock()).map_err(Error::from),
+        ["dap"] => lox::serve_dap(stdin().lock(), stdout()).map_err(Error::from),
+        ["debug", script] => lox.debug_file(script).map_err(Error::from),
+        ["ast", script] => print_ast("lisp", script),
+        ["ast", format, script] if format.starts_with("--format=") => {
+            print_ast(&format["--format=".len()..], script)
+        }
+        ["test"] => test_runner::run("examples"),
+        ["test", dir] => test_runner::run(dir),
+        ["--version"] => {
+            println!("lox {}", lox::VERSION);
+            Ok(())
+        }
+        [script] => lox.run_file(script).map_err(Error::from),
+        _ => {
+            eprintln!("Usage: lox [script]");
+            eprintln!("       lox debug script");
+            eprintln!("       lox ast [--format=lisp|json|dot] script");
+            eprintln!("       lox test [directory]");
+            eprintln!("       lox dap");
+            eprintln!("       lox lsp");
+            eprintln!("       lox --version");
+            Err(Error::Usage)
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::from(exit_code(&err))
+        }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::Lox;
-
-    #[test]
-    fn run_with_unexpected_char() {
-        let mut lox = Lox::new();
-        let bad_input = "{}#+-";
-        let result = lox.run(bad_input);
-        println!("{:?}", result);
-        assert!(matches!(result, Err(_)));
+/// Prints the syntax tree of a script, or of one read from JSON
+fn print_ast(format: &str, script: &str) -> Result<(), Error> {
+    let statements = lox::parse_file(script)?;
+    match format {
+        "lisp" => statements
+            .iter()
+            .for_each(|stmt| println!("{}", stmt.display_lisp())),
+        "json" => println!("{}", syntax::to_json(&statements)),
+        "dot" => print!("{}", syntax::to_dot(&statements)),
+        _ => return Err(Error::AstFormat(format.into())),
     }
+    Ok(())
+}
 
-    #[test]
-    fn example_numeric_expression() {
-        let mut lox = Lox::new();
-        let input = "print 1 + 2 * 3 == 7;";
-        lox.run(input).unwrap();
+/// Exit codes from `sysexits.h`, as used by the reference implementation
+fn exit_code(err: &Error) -> u8 {
+    match err {
+        Error::Usage | Error::AstFormat(_) => 64,
+        Error::TestsFailed(_) => 1,
+        Error::Lox(err) => lox_exit_code(err),
     }
+}
 
-    #[test]
-    fn example_string_concat() {
-        let mut lox = Lox::new();
-        let input = "print \"hello, \" + \"world!\" == \"hello, world!\";";
-        lox.run(input).unwrap();
+fn lox_exit_code(err: &InterpreterError) -> u8 {
+    match err {
+        InterpreterError::LexicalError(_)
+        | InterpreterError::ParserError(_)
+        | InterpreterError::AstError(_) => 65,
+        InterpreterError::Io(_) => 74,
+        InterpreterError::InModule(_, err) => lox_exit_code(err),
+        _ => 70,
     }
 }
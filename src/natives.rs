@@ -0,0 +1,23 @@
+//! Functions implemented in Rust that every script can call
+
+use crate::{environment::Environment, value::Value};
+
+/// Defines the native functions and constants as globals
+pub fn define(globals: &mut Environment) {
+    let functions = collections::FUNCTIONS
+        .into_iter()
+        .chain(math::FUNCTIONS)
+        .chain(strings::FUNCTIONS)
+        .chain(io::FUNCTIONS);
+    for function in functions {
+        globals.define_native(function.name, Value::NativeFunction(function));
+    }
+    for (name, value) in math::CONSTANTS {
+        globals.define_native(name, Value::Number(value));
+    }
+}
+
+mod collections;
+mod io;
+mod math;
+mod strings;
@@ -0,0 +1,115 @@
+use crate::{
+    error::InterpreterError,
+    interpreter::Interpreter,
+    value::{
+        Arity::Exactly,
+        List, Map,
+        Native::{self, Pure},
+        NativeFunction, Value,
+    },
+};
+
+pub const FUNCTIONS: [NativeFunction; 8] = [
+    NativeFunction {
+        name: "len",
+        arity: Exactly(1),
+        function: Pure(len),
+    },
+    NativeFunction {
+        name: "push",
+        arity: Exactly(2),
+        function: Pure(push),
+    },
+    NativeFunction {
+        name: "pop",
+        arity: Exactly(1),
+        function: Pure(pop),
+    },
+    NativeFunction {
+        name: "insert",
+        arity: Exactly(3),
+        function: Pure(insert),
+    },
+    NativeFunction {
+        name: "remove",
+        arity: Exactly(2),
+        function: Pure(remove),
+    },
+    NativeFunction {
+        name: "has",
+        arity: Exactly(2),
+        function: Pure(has),
+    },
+    NativeFunction {
+        name: "keys",
+        arity: Exactly(1),
+        function: Native::Interpreter(keys),
+    },
+    NativeFunction {
+        name: "values",
+        arity: Exactly(1),
+        function: Native::Interpreter(values),
+    },
+];
+
+fn list(value: &Value) -> Result<&List, InterpreterError> {
+    match value {
+        Value::List(list) => Ok(list),
+        value => Err(InterpreterError::TypeError(value.clone())),
+    }
+}
+
+fn map(value: &Value) -> Result<&Map, InterpreterError> {
+    match value {
+        Value::Map(map) => Ok(map),
+        value => Err(InterpreterError::TypeError(value.clone())),
+    }
+}
+
+/// The number of elements, entries or chars
+fn len(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let len = match &arguments[0] {
+        Value::Map(map) => map.len(),
+        Value::String(string) => string.chars().count(),
+        value => list(value)?.len(),
+    };
+    Ok(Value::Int(len as i64))
+}
+
+fn push(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    list(&arguments[0])?.push(arguments[1].clone());
+    Ok(Value::Nil)
+}
+
+fn pop(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    list(&arguments[0])?.pop()
+}
+
+fn insert(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    list(&arguments[0])?.insert(&arguments[1], arguments[2].clone())?;
+    Ok(Value::Nil)
+}
+
+/// Removes an element of a list by index, or an entry of a map by key
+fn remove(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    match &arguments[0] {
+        Value::Map(map) => map.remove(&arguments[1]),
+        value => list(value)?.remove(&arguments[1]),
+    }
+}
+
+fn has(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(map(&arguments[0])?.has(&arguments[1])?.into())
+}
+
+fn keys(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let map = map(&arguments[0])?;
+    interpreter.reserve(map.copied_sizes().0)?;
+    Ok(map.keys().into())
+}
+
+fn values(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let map = map(&arguments[0])?;
+    interpreter.reserve(map.copied_sizes().1)?;
+    Ok(map.values().into())
+}
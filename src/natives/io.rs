@@ -0,0 +1,213 @@
+//! Console and file system access, failures are
+//! `InterpreterError::NativeIo` with the path in the message
+//!
+//! Deleting a file is `removeFile` rather than `remove`, since `remove`
+//! already takes an element out of a list or map.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, Write},
+    mem,
+    path::Path,
+};
+
+use crate::{
+    error::InterpreterError,
+    interpreter::{Console, Interpreter},
+    value::{
+        Arity::Exactly,
+        Native::{self, Pure},
+        NativeFunction, Value,
+    },
+};
+
+pub const FUNCTIONS: [NativeFunction; 10] = [
+    NativeFunction {
+        name: "readLine",
+        arity: Exactly(0),
+        function: Native::Interpreter(read_line),
+    },
+    NativeFunction {
+        name: "readAll",
+        arity: Exactly(0),
+        function: Native::Interpreter(read_all),
+    },
+    NativeFunction {
+        name: "eprint",
+        arity: Exactly(1),
+        function: Native::Console(eprint),
+    },
+    NativeFunction {
+        name: "readFile",
+        arity: Exactly(1),
+        function: Native::Interpreter(read_file),
+    },
+    NativeFunction {
+        name: "writeFile",
+        arity: Exactly(2),
+        function: Pure(write_file),
+    },
+    NativeFunction {
+        name: "appendFile",
+        arity: Exactly(2),
+        function: Pure(append_file),
+    },
+    NativeFunction {
+        name: "exists",
+        arity: Exactly(1),
+        function: Pure(exists),
+    },
+    NativeFunction {
+        name: "listDir",
+        arity: Exactly(1),
+        function: Native::Interpreter(list_dir),
+    },
+    // not `remove`, the collections native has that name
+    NativeFunction {
+        name: "removeFile",
+        arity: Exactly(1),
+        function: Pure(remove_file),
+    },
+    NativeFunction {
+        name: "mkdir",
+        arity: Exactly(1),
+        function: Pure(mkdir),
+    },
+];
+
+fn string(value: &Value) -> Result<&str, InterpreterError> {
+    match value {
+        Value::String(string) => Ok(string),
+        value => Err(InterpreterError::TypeError(value.clone())),
+    }
+}
+
+/// Runs `f` on the path, adding the path to any error
+fn with_path<T>(
+    value: &Value,
+    f: impl FnOnce(&Path) -> io::Result<T>,
+) -> Result<T, InterpreterError> {
+    let path = string(value)?;
+    f(Path::new(path)).map_err(|err| {
+        InterpreterError::NativeIo(io::Error::new(err.kind(), format!("{path}: {err}")))
+    })
+}
+
+/// Reads the input up to and including the next line ending, or to the
+/// end if `line` is false, charging it to the heap limit as it is read
+/// since its length isn't known up front
+fn read_input(interpreter: &mut Interpreter, line: bool) -> Result<String, InterpreterError> {
+    let mut input = Vec::new();
+    loop {
+        let stdin = &mut interpreter.console().stdin;
+        let available = stdin.fill_buf().map_err(InterpreterError::NativeIo)?;
+        let newline = available.iter().position(|&byte| line && byte == b'\n');
+        let len = newline.map_or(available.len(), |newline| newline + 1);
+        if len == 0 {
+            break;
+        }
+        interpreter.reserve(input.len() + len)?;
+        let stdin = &mut interpreter.console().stdin;
+        input.extend_from_slice(&stdin.fill_buf().map_err(InterpreterError::NativeIo)?[..len]);
+        stdin.consume(len);
+        if newline.is_some() {
+            break;
+        }
+    }
+    String::from_utf8(input)
+        .map_err(|err| InterpreterError::NativeIo(io::Error::new(io::ErrorKind::InvalidData, err)))
+}
+
+/// The next line without its line ending, or nil at the end of the input
+fn read_line(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, InterpreterError> {
+    let mut line = read_input(interpreter, true)?;
+    if line.is_empty() {
+        return Ok(Value::Nil);
+    }
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok(line.into())
+}
+
+/// The rest of the input
+fn read_all(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(read_input(interpreter, false)?.into())
+}
+
+/// `print`, but to stderr
+fn eprint(console: &mut Console, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    writeln!(console.stderr, "{}", arguments[0]).map_err(InterpreterError::NativeIo)?;
+    Ok(Value::Nil)
+}
+
+fn read_file(
+    interpreter: &mut Interpreter,
+    arguments: &[Value],
+) -> Result<Value, InterpreterError> {
+    let len = with_path(&arguments[0], |path| fs::metadata(path))?.len();
+    interpreter.reserve(usize::try_from(len).unwrap_or(usize::MAX))?;
+    Ok(with_path(&arguments[0], |path| fs::read_to_string(path))?.into())
+}
+
+/// Creates the file, or replaces what it held
+fn write_file(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let contents = string(&arguments[1])?;
+    with_path(&arguments[0], |path| fs::write(path, contents))?;
+    Ok(Value::Nil)
+}
+
+/// Creates the file if it doesn't exist yet
+fn append_file(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let contents = string(&arguments[1])?;
+    with_path(&arguments[0], |path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(contents.as_bytes())
+    })?;
+    Ok(Value::Nil)
+}
+
+fn exists(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(with_path(&arguments[0], Path::try_exists)?.into())
+}
+
+/// The names in a directory, sorted
+fn list_dir(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let entries = with_path(&arguments[0], |path| fs::read_dir(path))?;
+    let mut names = Vec::new();
+    // the count of entries isn't known up front
+    let mut bytes = 0;
+    for entry in entries {
+        let name = with_path(&arguments[0], |_| entry)?.file_name();
+        let name = name.to_string_lossy().into_owned();
+        bytes += mem::size_of::<Value>() + name.len();
+        interpreter.reserve(bytes)?;
+        names.push(name);
+    }
+    names.sort();
+    Ok(names
+        .into_iter()
+        .map(Value::from)
+        .collect::<Vec<_>>()
+        .into())
+}
+
+/// Removes a file or an empty directory
+fn remove_file(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    with_path(&arguments[0], |path| {
+        if path.is_dir() {
+            fs::remove_dir(path)
+        } else {
+            fs::remove_file(path)
+        }
+    })?;
+    Ok(Value::Nil)
+}
+
+/// Creates the directory along with any missing parents
+fn mkdir(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    with_path(&arguments[0], |path| fs::create_dir_all(path))?;
+    Ok(Value::Nil)
+}
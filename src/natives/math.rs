@@ -0,0 +1,224 @@
+use std::f64::consts;
+
+use crate::{
+    error::InterpreterError,
+    value::{Arity::Exactly, Native::Pure, NativeFunction, Value},
+};
+
+pub const CONSTANTS: [(&str, f64); 4] = [
+    ("PI", consts::PI),
+    ("E", consts::E),
+    ("INF", f64::INFINITY),
+    ("NAN", f64::NAN),
+];
+
+pub const FUNCTIONS: [NativeFunction; 19] = [
+    NativeFunction {
+        name: "abs",
+        arity: Exactly(1),
+        function: Pure(abs),
+    },
+    NativeFunction {
+        name: "sqrt",
+        arity: Exactly(1),
+        function: Pure(sqrt),
+    },
+    NativeFunction {
+        name: "pow",
+        arity: Exactly(2),
+        function: Pure(pow),
+    },
+    NativeFunction {
+        name: "exp",
+        arity: Exactly(1),
+        function: Pure(exp),
+    },
+    NativeFunction {
+        name: "log",
+        arity: Exactly(1),
+        function: Pure(log),
+    },
+    NativeFunction {
+        name: "log2",
+        arity: Exactly(1),
+        function: Pure(log2),
+    },
+    NativeFunction {
+        name: "log10",
+        arity: Exactly(1),
+        function: Pure(log10),
+    },
+    NativeFunction {
+        name: "floor",
+        arity: Exactly(1),
+        function: Pure(floor),
+    },
+    NativeFunction {
+        name: "ceil",
+        arity: Exactly(1),
+        function: Pure(ceil),
+    },
+    NativeFunction {
+        name: "round",
+        arity: Exactly(1),
+        function: Pure(round),
+    },
+    NativeFunction {
+        name: "min",
+        arity: Exactly(2),
+        function: Pure(min),
+    },
+    NativeFunction {
+        name: "max",
+        arity: Exactly(2),
+        function: Pure(max),
+    },
+    NativeFunction {
+        name: "sin",
+        arity: Exactly(1),
+        function: Pure(sin),
+    },
+    NativeFunction {
+        name: "cos",
+        arity: Exactly(1),
+        function: Pure(cos),
+    },
+    NativeFunction {
+        name: "tan",
+        arity: Exactly(1),
+        function: Pure(tan),
+    },
+    NativeFunction {
+        name: "asin",
+        arity: Exactly(1),
+        function: Pure(asin),
+    },
+    NativeFunction {
+        name: "acos",
+        arity: Exactly(1),
+        function: Pure(acos),
+    },
+    NativeFunction {
+        name: "atan",
+        arity: Exactly(1),
+        function: Pure(atan),
+    },
+    NativeFunction {
+        name: "atan2",
+        arity: Exactly(2),
+        function: Pure(atan2),
+    },
+];
+
+/// A number as a float, ints are promoted
+fn number(value: &Value) -> Result<f64, InterpreterError> {
+    match *value {
+        Value::Number(n) => Ok(n),
+        Value::Int(n) => Ok(n as f64),
+        ref value => Err(InterpreterError::TypeError(value.clone())),
+    }
+}
+
+/// Ints are already whole, so they are left as they are
+fn whole(value: &Value, round: fn(f64) -> f64) -> Result<Value, InterpreterError> {
+    match *value {
+        Value::Int(n) => Ok(Value::Int(n)),
+        ref value => Ok(round(number(value)?).into()),
+    }
+}
+
+/// Compares ints exactly, rather than as floats
+fn less(left: &Value, right: &Value) -> Result<bool, InterpreterError> {
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => Ok(left < right),
+        (left, right) => Ok(number(left)? < number(right)?),
+    }
+}
+
+fn abs(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    match arguments[0] {
+        Value::Int(n) => n
+            .checked_abs()
+            .map(Value::Int)
+            .ok_or(InterpreterError::IntegerOverflow),
+        ref value => Ok(number(value)?.abs().into()),
+    }
+}
+
+fn sqrt(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.sqrt().into())
+}
+
+/// Always a float, `**` keeps ints as ints
+fn pow(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.powf(number(&arguments[1])?).into())
+}
+
+fn exp(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.exp().into())
+}
+
+/// The natural logarithm
+fn log(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.ln().into())
+}
+
+fn log2(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.log2().into())
+}
+
+fn log10(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.log10().into())
+}
+
+fn floor(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    whole(&arguments[0], f64::floor)
+}
+
+fn ceil(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    whole(&arguments[0], f64::ceil)
+}
+
+/// Halves round away from zero
+fn round(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    whole(&arguments[0], f64::round)
+}
+
+fn min(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let [left, right] = [&arguments[0], &arguments[1]];
+    Ok(if less(right, left)? { right } else { left }.clone())
+}
+
+fn max(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let [left, right] = [&arguments[0], &arguments[1]];
+    Ok(if less(left, right)? { right } else { left }.clone())
+}
+
+fn sin(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.sin().into())
+}
+
+fn cos(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.cos().into())
+}
+
+fn tan(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.tan().into())
+}
+
+fn asin(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.asin().into())
+}
+
+fn acos(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.acos().into())
+}
+
+fn atan(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.atan().into())
+}
+
+/// The angle of the point `(x, y)`, taking `y` first
+fn atan2(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    Ok(number(&arguments[0])?.atan2(number(&arguments[1])?).into())
+}
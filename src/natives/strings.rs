@@ -0,0 +1,281 @@
+//! Indices and lengths count chars, the Unicode scalar values of a
+//! string, rather than bytes
+
+use std::mem;
+
+use crate::{
+    error::InterpreterError,
+    interpreter::Interpreter,
+    value::{
+        Arity::{AtLeast, Exactly},
+        Native::{self, Pure},
+        NativeFunction, Value,
+    },
+};
+
+pub const FUNCTIONS: [NativeFunction; 13] = [
+    NativeFunction {
+        name: "substring",
+        arity: Exactly(3),
+        function: Native::Interpreter(substring),
+    },
+    NativeFunction {
+        name: "indexOf",
+        arity: Exactly(2),
+        function: Pure(index_of),
+    },
+    NativeFunction {
+        name: "split",
+        arity: Exactly(2),
+        function: Native::Interpreter(split),
+    },
+    NativeFunction {
+        name: "join",
+        arity: Exactly(2),
+        function: Native::Interpreter(join),
+    },
+    NativeFunction {
+        name: "trim",
+        arity: Exactly(1),
+        function: Native::Interpreter(trim),
+    },
+    NativeFunction {
+        name: "upper",
+        arity: Exactly(1),
+        function: Native::Interpreter(upper),
+    },
+    NativeFunction {
+        name: "lower",
+        arity: Exactly(1),
+        function: Native::Interpreter(lower),
+    },
+    NativeFunction {
+        name: "replace",
+        arity: Exactly(3),
+        function: Native::Interpreter(replace),
+    },
+    NativeFunction {
+        name: "startsWith",
+        arity: Exactly(2),
+        function: Pure(starts_with),
+    },
+    NativeFunction {
+        name: "endsWith",
+        arity: Exactly(2),
+        function: Pure(ends_with),
+    },
+    NativeFunction {
+        name: "repeat",
+        arity: Exactly(2),
+        function: Native::Interpreter(repeat),
+    },
+    NativeFunction {
+        name: "chars",
+        arity: Exactly(1),
+        function: Native::Interpreter(chars),
+    },
+    NativeFunction {
+        name: "format",
+        arity: AtLeast(1),
+        function: Native::Interpreter(format),
+    },
+];
+
+fn string(value: &Value) -> Result<&str, InterpreterError> {
+    match value {
+        Value::String(string) => Ok(string),
+        value => Err(InterpreterError::TypeError(value.clone())),
+    }
+}
+
+fn int(value: &Value) -> Result<i64, InterpreterError> {
+    match *value {
+        Value::Int(n) => Ok(n),
+        ref value => Err(InterpreterError::InvalidIndex(value.clone())),
+    }
+}
+
+/// The chars from `start` up to but not including `end`
+fn substring(
+    interpreter: &mut Interpreter,
+    arguments: &[Value],
+) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    let (start, end) = (int(&arguments[1])?, int(&arguments[2])?);
+    let len = string.chars().count();
+    let range = usize::try_from(start)
+        .ok()
+        .zip(usize::try_from(end).ok())
+        .filter(|&(start, end)| start <= end && end <= len);
+    let Some((start, end)) = range else {
+        return Err(InterpreterError::RangeOutOfBounds(start, end, len));
+    };
+    // the byte the nth char starts at
+    let byte = |n| {
+        string
+            .char_indices()
+            .nth(n)
+            .map_or(string.len(), |(byte, _)| byte)
+    };
+    let substring = &string[byte(start)..byte(end)];
+    interpreter.reserve(substring.len())?;
+    Ok(substring.into())
+}
+
+/// Where `part` first starts in the string, or -1 if it isn't there
+fn index_of(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    let part = self::string(&arguments[1])?;
+    let index = match string.find(part) {
+        Some(byte) => string[..byte].chars().count() as i64,
+        None => -1,
+    };
+    Ok(Value::Int(index))
+}
+
+/// Reserves a list of `parts` strings of `bytes` in all
+fn reserve_parts(
+    interpreter: &mut Interpreter,
+    parts: usize,
+    bytes: usize,
+) -> Result<(), InterpreterError> {
+    let slots = parts.saturating_mul(mem::size_of::<Value>());
+    interpreter.reserve(slots.saturating_add(bytes))
+}
+
+/// Splitting on an empty separator splits into chars
+fn split(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    let separator = self::string(&arguments[1])?;
+    if separator.is_empty() {
+        return chars(interpreter, arguments);
+    }
+    let parts = string.matches(separator).count() + 1;
+    reserve_parts(interpreter, parts, string.len())?;
+    let parts: Vec<Value> = string.split(separator).map(Value::from).collect();
+    Ok(parts.into())
+}
+
+/// Joins the elements of a list as `print` would show them
+fn join(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let Value::List(list) = &arguments[0] else {
+        return Err(InterpreterError::TypeError(arguments[0].clone()));
+    };
+    let separator = string(&arguments[1])?;
+    let mut joined = interpreter.charged_string();
+    for (i, element) in list.elements().iter().enumerate() {
+        if i > 0 {
+            joined.push_str(separator)?;
+        }
+        joined.push_value(element)?;
+    }
+    Ok(joined.finish().into())
+}
+
+fn trim(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let trimmed = string(&arguments[0])?.trim();
+    interpreter.reserve(trimmed.len())?;
+    Ok(trimmed.into())
+}
+
+fn upper(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    // a char can map to more than one, and to ones that take more bytes
+    let len = string
+        .chars()
+        .flat_map(char::to_uppercase)
+        .map(char::len_utf8)
+        .sum();
+    interpreter.reserve(len)?;
+    Ok(string.to_uppercase().into())
+}
+
+fn lower(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    let len = string
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(char::len_utf8)
+        .sum();
+    interpreter.reserve(len)?;
+    Ok(string.to_lowercase().into())
+}
+
+/// Replaces every occurrence
+fn replace(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    let from = self::string(&arguments[1])?;
+    let to = self::string(&arguments[2])?;
+    // an empty `from` matches between every char and at both ends
+    let count = string.matches(from).count();
+    let len = (string.len() - count * from.len()).saturating_add(count.saturating_mul(to.len()));
+    interpreter.reserve(len)?;
+    Ok(string.replace(from, to).into())
+}
+
+fn starts_with(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    Ok(string.starts_with(self::string(&arguments[1])?).into())
+}
+
+fn ends_with(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    Ok(string.ends_with(self::string(&arguments[1])?).into())
+}
+
+fn repeat(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    let n = match arguments[1] {
+        Value::Int(n) => n,
+        ref value => return Err(InterpreterError::NotAnInt(value.clone())),
+    };
+    let n = usize::try_from(n).map_err(|_| InterpreterError::InvalidCount(n))?;
+    // no string can be longer than isize::MAX bytes
+    let len = string
+        .len()
+        .checked_mul(n)
+        .filter(|&len| isize::try_from(len).is_ok())
+        .ok_or(InterpreterError::IntegerOverflow)?;
+    interpreter.reserve(len)?;
+    Ok(string.repeat(n).into())
+}
+
+fn chars(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let string = string(&arguments[0])?;
+    reserve_parts(interpreter, string.chars().count(), string.len())?;
+    let chars: Vec<Value> = string.chars().map(|c| Value::from(c.to_string())).collect();
+    Ok(chars.into())
+}
+
+/// Fills each `{}` in the template with the next value, `{{` and `}}`
+/// are literal braces
+fn format(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let template = string(&arguments[0])?;
+    let mut values = arguments[1..].iter();
+    let mut holes = 0;
+    let mut formatted = interpreter.charged_string();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push_str(c.encode_utf8(&mut [0; 4]))?;
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                holes += 1;
+                if let Some(value) = values.next() {
+                    formatted.push_value(value)?;
+                }
+            }
+            (c, _) => formatted.push_str(c.encode_utf8(&mut [0; 4]))?,
+        }
+    }
+    if holes != arguments.len() - 1 {
+        return Err(InterpreterError::FormatArguments(
+            holes,
+            arguments.len() - 1,
+        ));
+    }
+    Ok(formatted.finish().into())
+}
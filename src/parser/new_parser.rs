@@ -1,8 +1,8 @@
 use crate::{
     error::{LexicalError, LoxParserError},
     scanner::Scanner,
-    syntax::{BinOp, Expr, Stmt, UnOp},
-    token::{Literal, Operator, Structure, Token, TokenKind},
+    syntax::{BinOp, Expr, Stmt, StmtKind, UnOp},
+    token::{Literal, Operator, Structure, Token, TokenKind, TokenMeta},
 };
 
 pub struct LoxParser {
@@ -21,9 +21,12 @@ impl LoxParser {
 
     #[allow(dead_code)]
     pub fn parse(&mut self) -> Result<Vec<Stmt>, LoxParserError> {
+        let meta = self
+            .peek()
+            .map_or(TokenMeta { row: 1, col: 1 }, |token| token.meta.clone());
         let expr = self.expression()?;
 
-        Ok(vec![Stmt::Expr(expr)])
+        Ok(vec![Stmt::new(StmtKind::Expr(expr), meta)])
     }
 
     fn expression(&mut self) -> Result<Expr, LoxParserError> {
@@ -106,6 +109,10 @@ impl LoxParser {
                     self.current += 1;
                     Ok(Expr::from_string(s.clone()))
                 }
+                TokenKind::Int(n) => {
+                    self.current += 1;
+                    Ok(Expr::from_int(*n))
+                }
                 TokenKind::Number(n) => {
                     self.current += 1;
                     Ok(Expr::from_number(*n))
@@ -118,9 +125,13 @@ impl LoxParser {
                             TokenKind::Structure(Structure::RightParen) => {
                                 Ok(Expr::from_grouping(expr))
                             }
-                            _ => Err(LoxParserError::Message("Expected ')', but found something else")),
+                            _ => Err(LoxParserError::Message(
+                                "Expected ')', but found something else",
+                            )),
                         },
-                        _ => Err(LoxParserError::Message("Tokens end without closing parenthesis")),
+                        _ => Err(LoxParserError::Message(
+                            "Tokens end without closing parenthesis",
+                        )),
                     }
                 }
                 _ => Err(LoxParserError::Message("Invalid primary expression")),
@@ -1,16 +1,34 @@
-
 use crate::{
     error::{LexicalError, LoxParserError},
     scanner::Scanner,
-    syntax::{BinOp, Expr, UnOp, Stmt},
-    token::{Operator, Structure, Token, TokenKind, Keyword, Literal},
+    stack::{StackBase, DEFAULT_STACK_BYTES},
+    syntax::{self, BinOp, Expr, Imported, Stmt, StmtKind, Target, UnOp},
+    token::{Keyword, Literal, Operator, Structure, Token, TokenKind, TokenMeta},
 };
 
 pub struct LoxParser<'a> {
     tokens: Scanner<'a>,
     peeked: Option<Option<Result<Token, LexicalError>>>,
+    /// Where the most recently advanced token starts, used to locate errors
+    last: TokenMeta,
+    /// How many function bodies enclose the current statement, `return`
+    /// is only allowed inside one
+    functions: usize,
+    /// How deeply the expression or statement being parsed is nested, a
+    /// bound on how deep its tree is
+    depth: usize,
+    /// Where parsing started on the stack
+    stack: StackBase,
+    /// The stack parsing may use, which also bounds how deep the tree
+    /// can be, `None` for no limit
+    stack_bytes: Option<usize>,
 }
 
+/// The stack each level of the tree is allowed, a chain like `a + b + c`
+/// is parsed by a loop but nests the tree, and running, printing and
+/// dropping it recurse once for each level
+const STACK_PER_LEVEL: usize = 2048;
+
 pub type LoxParseResult<T> = Result<T, LoxParserError>;
 
 impl<'a> LoxParser<'a> {
@@ -18,47 +36,345 @@ impl<'a> LoxParser<'a> {
         Self {
             tokens: Scanner::new(input),
             peeked: None,
+            last: TokenMeta { row: 1, col: 1 },
+            functions: 0,
+            depth: 0,
+            stack: StackBase::here(),
+            stack_bytes: Some(DEFAULT_STACK_BYTES),
         }
     }
 
+    /// Limits the stack parsing uses, which is 1 MiB by default
+    pub fn with_stack_bytes(mut self, stack_bytes: Option<usize>) -> Self {
+        self.stack_bytes = stack_bytes;
+        self
+    }
+
     pub fn parse(&mut self) -> LoxParseResult<Vec<Stmt>> {
+        self.stack = StackBase::here();
         let mut statements = Vec::new();
         while let Some(peek) = self.advance()? {
-            let stmt = self.statement(peek)?;
+            let stmt = self
+                .nested(|parser| parser.statement(peek))
+                .map_err(|err| self.locate(err))?;
             statements.push(stmt);
         }
 
         Ok(statements)
     }
 
+    /// Attaches the position of the last token to errors that don't know where they are
+    fn locate(&self, err: LoxParserError) -> LoxParserError {
+        if err.position().is_some() {
+            err
+        } else {
+            LoxParserError::At(Box::new(err), self.last.row, self.last.col)
+        }
+    }
+
     fn statement(&mut self, peek: Token) -> LoxParseResult<Stmt> {
-        let stmt = match &peek.kind {
+        let meta = peek.meta.clone();
+        let kind = match &peek.kind {
             TokenKind::Keyword(Keyword::Var) => self.var_statement()?,
             TokenKind::Keyword(Keyword::Print) => self.print_statement()?,
-            _ => self.expression(peek).map(Stmt::Expr)?,
+            TokenKind::Keyword(Keyword::Return) => self.return_statement()?,
+            TokenKind::Keyword(Keyword::Throw) => self.throw_statement()?,
+            TokenKind::Keyword(Keyword::Import) => self.import_statement()?,
+            // `from` is only a keyword when a path follows it
+            TokenKind::Identifier(ref from)
+                if from == "from"
+                    && matches!(
+                        self.peek()?,
+                        Some(Token {
+                            kind: TokenKind::String(_),
+                            ..
+                        })
+                    ) =>
+            {
+                self.import_names_statement()?
+            }
+            TokenKind::Keyword(Keyword::Try) => {
+                return self.try_statement().map(|kind| Stmt::new(kind, meta))
+            }
+            TokenKind::Structure(Structure::LeftBrace) => {
+                return self
+                    .block()
+                    .map(|statements| Stmt::new(StmtKind::Block(statements), meta))
+            }
+            _ => self.expression(peek).map(StmtKind::Expr)?,
         };
-        self.consume(TokenKind::Structure(Structure::SemiColon))?;
-        Ok(stmt)
+        if !self.consume(TokenKind::Structure(Structure::SemiColon))? {
+            Err("expected ';' after statement")?
+        }
+        Ok(Stmt::new(kind, meta))
     }
 
-    fn print_statement(&mut self) -> LoxParseResult<Stmt> {
+    fn print_statement(&mut self) -> LoxParseResult<StmtKind> {
         let peek = self
             .advance()?
             .ok_or("print statement with nothing following")?;
-        self.expression(peek).map(Stmt::Print)
+        self.expression(peek).map(StmtKind::Print)
+    }
+
+    fn return_statement(&mut self) -> LoxParseResult<StmtKind> {
+        if self.functions == 0 {
+            Err("can't return from outside a function")?
+        }
+        match self.peek()? {
+            Some(Token {
+                kind: TokenKind::Structure(Structure::SemiColon),
+                ..
+            }) => Ok(StmtKind::Return(None)),
+            _ => {
+                let peek = self.advance()?.ok_or("return statement without ';'")?;
+                self.expression(peek)
+                    .map(|expr| StmtKind::Return(Some(expr)))
+            }
+        }
+    }
+
+    fn var_statement(&mut self) -> LoxParseResult<StmtKind> {
+        let name = match self.advance()? {
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                ..
+            }) => name,
+            _ => Err("expected a variable name after 'var'")?,
+        };
+        let initializer = match self.peek()? {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Equal),
+                ..
+            }) => {
+                self.advance()?;
+                let peek = self
+                    .advance()?
+                    .ok_or("variable declaration without initializer")?;
+                Some(self.element(peek)?)
+            }
+            _ => None,
+        };
+        Ok(StmtKind::Var(name, initializer))
+    }
+
+    /// `import "path" as name`, after the `import`
+    fn import_statement(&mut self) -> LoxParseResult<StmtKind> {
+        let path = self.path("expected a path after 'import'")?;
+        match self.advance()? {
+            Some(Token {
+                kind: TokenKind::Identifier(word),
+                ..
+            }) if word == "as" => {}
+            _ => Err("expected 'as' after the imported path")?,
+        }
+        let name = self.identifier("expected a name for the module")?;
+        Ok(StmtKind::Import(syntax::Import {
+            path,
+            names: Imported::Module(name),
+        }))
+    }
+
+    /// `from "path" import names`, after the `from`
+    fn import_names_statement(&mut self) -> LoxParseResult<StmtKind> {
+        let path = self.path("expected a path after 'from'")?;
+        if !self.consume(TokenKind::Keyword(Keyword::Import))? {
+            Err("expected 'import' after the path")?
+        }
+        let mut names = vec![self.identifier("expected a name to import")?];
+        while self.matches(TokenKind::Structure(Structure::Comma))? {
+            names.push(self.identifier("expected a name to import")?);
+        }
+        Ok(StmtKind::Import(syntax::Import {
+            path,
+            names: Imported::Names(names),
+        }))
+    }
+
+    fn path(&mut self, message: &'static str) -> LoxParseResult<String> {
+        match self.advance()? {
+            Some(Token {
+                kind: TokenKind::String(path),
+                ..
+            }) => Ok(path),
+            _ => Err(message)?,
+        }
+    }
+
+    fn identifier(&mut self, message: &'static str) -> LoxParseResult<String> {
+        match self.advance()? {
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                ..
+            }) => Ok(name),
+            _ => Err(message)?,
+        }
+    }
+
+    fn throw_statement(&mut self) -> LoxParseResult<StmtKind> {
+        let peek = self.advance()?.ok_or("throw statement without a value")?;
+        self.expression(peek).map(StmtKind::Throw)
     }
 
-    fn var_statement(&mut self) -> LoxParseResult<Stmt> {
-        todo!()
+    fn try_statement(&mut self) -> LoxParseResult<StmtKind> {
+        if !self.consume(TokenKind::Structure(Structure::LeftBrace))? {
+            Err("expected '{' after 'try'")?
+        }
+        let body = self.block()?;
+        let catch = if self.matches(TokenKind::Keyword(Keyword::Catch))? {
+            if !self.consume(TokenKind::Structure(Structure::LeftParen))? {
+                Err("expected '(' after 'catch'")?
+            }
+            let name = match self.advance()? {
+                Some(Token {
+                    kind: TokenKind::Identifier(name),
+                    ..
+                }) => name,
+                _ => Err("expected a name for what was caught")?,
+            };
+            if !self.consume(TokenKind::Structure(Structure::RightParen))? {
+                Err("expected ')' after the caught name")?
+            }
+            if !self.consume(TokenKind::Structure(Structure::LeftBrace))? {
+                Err("expected '{' after 'catch'")?
+            }
+            let body = self.block()?;
+            Some(syntax::Catch { name, body })
+        } else {
+            None
+        };
+        let finally = if self.matches(TokenKind::Keyword(Keyword::Finally))? {
+            if !self.consume(TokenKind::Structure(Structure::LeftBrace))? {
+                Err("expected '{' after 'finally'")?
+            }
+            Some(self.block()?)
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            Err("expected 'catch' or 'finally' after try block")?
+        }
+        Ok(StmtKind::Try(Box::new(syntax::Try {
+            body,
+            catch,
+            finally,
+        })))
+    }
+
+    /// The statements up to the closing brace, the opening brace is already consumed
+    fn block(&mut self) -> LoxParseResult<Vec<Stmt>> {
+        let mut statements = Vec::new();
+        loop {
+            let peek = self.advance()?.ok_or("block without closing brace")?;
+            if peek.kind == TokenKind::Structure(Structure::RightBrace) {
+                return Ok(statements);
+            }
+            statements.push(self.nested(|parser| parser.statement(peek))?);
+        }
     }
 
     fn expression(&mut self, peek: Token) -> LoxParseResult<Expr> {
+        self.element(peek).and_then(|expr| self.comma(expr))
+    }
+
+    /// An expression without the comma operator, for where commas separate
+    /// arguments or elements
+    fn element(&mut self, peek: Token) -> LoxParseResult<Expr> {
+        self.bitwise(peek)
+            .and_then(|expr| self.comparison(expr))
+            .and_then(|expr| self.equality(expr))
+            .and_then(|expr| self.logical(expr))
+            .and_then(|expr| self.conditional(expr))
+            .and_then(|expr| self.assignment(expr))
+    }
+
+    fn comma(&mut self, mut expr: Expr) -> LoxParseResult<Expr> {
+        while let Some(Token {
+            kind: TokenKind::Structure(Structure::Comma),
+            ..
+        }) = self.peek()?
+        {
+            self.advance()?;
+            self.nest()?;
+            let peek = self.advance()?.ok_or("',' without right operand")?;
+            let right = self.element(peek)?;
+            expr = Expr::from_comma(expr, right);
+        }
+        Ok(expr)
+    }
+
+    /// Everything that binds tighter than comparisons
+    fn bitwise(&mut self, peek: Token) -> LoxParseResult<Expr> {
         self.unary(peek)
             .and_then(|expr| self.factor(expr))
             .and_then(|expr| self.term(expr))
+            .and_then(|expr| self.shift(expr))
+            .and_then(|expr| self.bit_and(expr))
+            .and_then(|expr| self.bit_xor(expr))
+            .and_then(|expr| self.bit_or(expr))
+    }
+
+    /// `?:` is right associative, and anything can go between `?` and `:`
+    fn conditional(&mut self, condition: Expr) -> LoxParseResult<Expr> {
+        match self.peek()? {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Question),
+                ..
+            }) => {}
+            _ => return Ok(condition),
+        }
+        self.advance()?;
+        let peek = self.advance()?.ok_or("'?' without a value")?;
+        let then_branch = self.nested(|parser| parser.expression(peek))?;
+        if !self.consume(TokenKind::Structure(Structure::Colon))? {
+            Err("expected ':' in conditional expression")?
+        }
+        let peek = self.advance()?.ok_or("':' without a value")?;
+        let else_branch = self
+            .bitwise(peek)
             .and_then(|expr| self.comparison(expr))
             .and_then(|expr| self.equality(expr))
             .and_then(|expr| self.logical(expr))
+            .and_then(|expr| self.nested(|parser| parser.conditional(expr)))?;
+        Ok(Expr::from_conditional(condition, then_branch, else_branch))
+    }
+
+    /// Assignment is right associative, so the value is a whole expression
+    /// but for the comma operator
+    fn assignment(&mut self, target: Expr) -> LoxParseResult<Expr> {
+        let op = match self.peek()? {
+            Some(Token {
+                kind: TokenKind::Operator(op),
+                ..
+            }) => match op {
+                Operator::Equal => None,
+                Operator::PlusEqual => Some(BinOp::Add),
+                Operator::MinusEqual => Some(BinOp::Sub),
+                Operator::StarEqual => Some(BinOp::Mul),
+                Operator::SlashEqual => Some(BinOp::Div),
+                _ => return Ok(target),
+            },
+            _ => return Ok(target),
+        };
+        self.advance()?;
+        let peek = self.advance()?.ok_or("assignment without a value")?;
+        let value = self.nested(|parser| parser.element(peek))?;
+        if let Some(op) = op {
+            return Ok(Expr::from_update(Self::target(target)?, op, value, false));
+        }
+        match target {
+            Expr::Literal(syntax::Literal::Identifier(name)) => Ok(Expr::from_assign(name, value)),
+            Expr::Index(index) => Ok(Expr::from_set_index(index.object, index.index, value)),
+            _ => Err("invalid assignment target")?,
+        }
+    }
+
+    /// What an update operator can read from and write back to
+    fn target(expr: Expr) -> LoxParseResult<Target> {
+        match expr {
+            Expr::Literal(syntax::Literal::Identifier(name)) => Ok(Target::Variable(name)),
+            Expr::Index(index) => Ok(Target::Index(*index)),
+            _ => Err("invalid assignment target")?,
+        }
     }
 
     fn logical(&mut self, mut expr: Expr) -> Result<Expr, LoxParserError> {
@@ -68,13 +384,13 @@ impl<'a> LoxParser<'a> {
                 TokenKind::Operator(Operator::Or) => BinOp::Or,
                 _ => break,
             };
+            self.advance()?;
+            self.nest()?;
             let peek = self
                 .advance()?
                 .ok_or("logical operator without right operand")?;
             let right = self
-                .unary(peek)
-                .and_then(|expr| self.factor(expr))
-                .and_then(|expr| self.term(expr))
+                .bitwise(peek)
                 .and_then(|expr| self.comparison(expr))
                 .and_then(|expr| self.equality(expr))?;
             expr = Expr::from_binary(expr, op, right);
@@ -91,15 +407,12 @@ impl<'a> LoxParser<'a> {
             };
 
             self.advance()?;
+            self.nest()?;
             let peek = self
                 .advance()?
                 .ok_or("equality operator without right operand")?;
 
-            let right = self
-                .unary(peek)
-                .and_then(|expr| self.factor(expr))
-                .and_then(|expr| self.term(expr))
-                .and_then(|expr| self.comparison(expr))?;
+            let right = self.bitwise(peek).and_then(|expr| self.comparison(expr))?;
             left = Expr::from_binary(left, op, right);
         }
 
@@ -117,9 +430,88 @@ impl<'a> LoxParser<'a> {
                 _ => break,
             };
             self.advance()?;
+            self.nest()?;
             let peek = self
                 .advance()?
                 .ok_or("comparison operator without right operand")?;
+            let right = self.bitwise(peek)?;
+            expr = Expr::from_binary(expr, op, right);
+        }
+        Ok(expr)
+    }
+
+    fn bit_or(&mut self, mut expr: Expr) -> Result<Expr, LoxParserError> {
+        while let Some(Token {
+            kind: TokenKind::Operator(Operator::Pipe),
+            ..
+        }) = self.peek()?
+        {
+            self.advance()?;
+            self.nest()?;
+            let peek = self.advance()?.ok_or("'|' without right operand")?;
+            let right = self
+                .unary(peek)
+                .and_then(|expr| self.factor(expr))
+                .and_then(|expr| self.term(expr))
+                .and_then(|expr| self.shift(expr))
+                .and_then(|expr| self.bit_and(expr))
+                .and_then(|expr| self.bit_xor(expr))?;
+            expr = Expr::from_binary(expr, BinOp::BitOr, right);
+        }
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self, mut expr: Expr) -> Result<Expr, LoxParserError> {
+        while let Some(Token {
+            kind: TokenKind::Operator(Operator::Caret),
+            ..
+        }) = self.peek()?
+        {
+            self.advance()?;
+            self.nest()?;
+            let peek = self.advance()?.ok_or("'^' without right operand")?;
+            let right = self
+                .unary(peek)
+                .and_then(|expr| self.factor(expr))
+                .and_then(|expr| self.term(expr))
+                .and_then(|expr| self.shift(expr))
+                .and_then(|expr| self.bit_and(expr))?;
+            expr = Expr::from_binary(expr, BinOp::BitXor, right);
+        }
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self, mut expr: Expr) -> Result<Expr, LoxParserError> {
+        while let Some(Token {
+            kind: TokenKind::Operator(Operator::Ampersand),
+            ..
+        }) = self.peek()?
+        {
+            self.advance()?;
+            self.nest()?;
+            let peek = self.advance()?.ok_or("'&' without right operand")?;
+            let right = self
+                .unary(peek)
+                .and_then(|expr| self.factor(expr))
+                .and_then(|expr| self.term(expr))
+                .and_then(|expr| self.shift(expr))?;
+            expr = Expr::from_binary(expr, BinOp::BitAnd, right);
+        }
+        Ok(expr)
+    }
+
+    fn shift(&mut self, mut expr: Expr) -> Result<Expr, LoxParserError> {
+        while let Some(token) = self.peek()? {
+            let op = match &token.kind {
+                TokenKind::Operator(Operator::LessLess) => BinOp::Shl,
+                TokenKind::Operator(Operator::GreaterGreater) => BinOp::Shr,
+                _ => break,
+            };
+            self.advance()?;
+            self.nest()?;
+            let peek = self
+                .advance()?
+                .ok_or("shift operator without right operand")?;
             let right = self
                 .unary(peek)
                 .and_then(|expr| self.factor(expr))
@@ -138,6 +530,7 @@ impl<'a> LoxParser<'a> {
                 _ => break,
             };
             self.advance()?;
+            self.nest()?;
 
             let peek = self
                 .advance()?
@@ -154,9 +547,12 @@ impl<'a> LoxParser<'a> {
             let op = match &token.kind {
                 TokenKind::Operator(Slash) => BinOp::Div,
                 TokenKind::Operator(Star) => BinOp::Mul,
+                TokenKind::Operator(TildeSlash) => BinOp::IntDiv,
+                TokenKind::Operator(Percent) => BinOp::Rem,
                 _ => break,
             };
             self.advance()?;
+            self.nest()?;
             let peek = self
                 .advance()?
                 .ok_or("factor operator without right operand")?;
@@ -170,13 +566,158 @@ impl<'a> LoxParser<'a> {
         let op = match &peek.kind {
             TokenKind::Operator(Operator::Minus) => UnOp::Neg,
             TokenKind::Operator(Operator::Bang) => UnOp::Not,
-            _ => return self.primary(peek),
+            TokenKind::Operator(Operator::Tilde) => UnOp::BitNot,
+            _ => return self.nested(|parser| parser.power(peek)),
         };
         let peek = self.advance()?.ok_or("unary operator without operand")?;
-        let expr = self.unary(peek)?;
+        let expr = self.nested(|parser| parser.unary(peek))?;
         Ok(Expr::from_unary(op, expr))
     }
 
+    /// `**` is right associative and binds tighter than the unary
+    /// operators on its left, but not on its right, so `-2 ** -1` is
+    /// `-(2 ** (-1))`
+    fn power(&mut self, peek: Token) -> LoxParseResult<Expr> {
+        let base = self.increment(peek)?;
+        match self.peek()? {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::StarStar),
+                ..
+            }) => {}
+            _ => return Ok(base),
+        }
+        self.advance()?;
+        let peek = self.advance()?.ok_or("'**' without an exponent")?;
+        let exponent = self.unary(peek)?;
+        Ok(Expr::from_binary(base, BinOp::Pow, exponent))
+    }
+
+    /// Prefix `++` and `--`, which bind as tightly as the postfix ones
+    fn increment(&mut self, peek: Token) -> LoxParseResult<Expr> {
+        let op = match &peek.kind {
+            TokenKind::Operator(Operator::PlusPlus) => BinOp::Add,
+            TokenKind::Operator(Operator::MinusMinus) => BinOp::Sub,
+            _ => return self.call(peek),
+        };
+        let peek = self.advance()?.ok_or("increment without a target")?;
+        let target = Self::target(self.call(peek)?)?;
+        Ok(Expr::from_update(target, op, Expr::from_int(1), false))
+    }
+
+    /// Calls, indexing and postfix `++` and `--`, which all follow what
+    /// they apply to
+    fn call(&mut self, peek: Token) -> LoxParseResult<Expr> {
+        let mut expr = self.primary(peek)?;
+        loop {
+            match self.peek()? {
+                Some(Token {
+                    kind: TokenKind::Structure(Structure::LeftParen),
+                    ..
+                }) => {
+                    self.advance()?;
+                    self.nest()?;
+                    let arguments = self.sequence(Structure::RightParen)?;
+                    expr = Expr::from_call(expr, arguments);
+                }
+                Some(Token {
+                    kind: TokenKind::Structure(Structure::LeftBracket),
+                    ..
+                }) => {
+                    self.advance()?;
+                    self.nest()?;
+                    let peek = self.advance()?.ok_or("index without an expression")?;
+                    let index = self.expression(peek)?;
+                    if !self.consume(TokenKind::Structure(Structure::RightBracket))? {
+                        Err("expected ']' after index")?
+                    }
+                    expr = Expr::from_index(expr, index);
+                }
+                Some(Token {
+                    kind: TokenKind::Operator(op @ (Operator::PlusPlus | Operator::MinusMinus)),
+                    ..
+                }) => {
+                    let op = if *op == Operator::PlusPlus {
+                        BinOp::Add
+                    } else {
+                        BinOp::Sub
+                    };
+                    self.advance()?;
+                    self.nest()?;
+                    expr = Expr::from_update(Self::target(expr)?, op, Expr::from_int(1), true);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// Comma separated expressions up to `close`, the opening token is already consumed
+    fn sequence(&mut self, close: Structure) -> LoxParseResult<Vec<Expr>> {
+        let mut expressions = Vec::new();
+        let peek = self.advance()?.ok_or(LoxParserError::EndOfFile)?;
+        if peek.kind == TokenKind::Structure(close) {
+            return Ok(expressions);
+        }
+        expressions.push(self.element(peek)?);
+        loop {
+            match self.advance()?.map(|token| token.kind) {
+                Some(TokenKind::Structure(Structure::Comma)) => {
+                    let peek = self.advance()?.ok_or(LoxParserError::EndOfFile)?;
+                    expressions.push(self.element(peek)?);
+                }
+                Some(TokenKind::Structure(st)) if st == close => return Ok(expressions),
+                _ if close == Structure::RightParen => Err("expected ',' or ')' after argument")?,
+                _ => Err("expected ',' or ']' after element")?,
+            }
+        }
+    }
+
+    /// The rest of an interpolated string, `part` is the text before the first hole
+    fn interpolation(&mut self, mut part: String) -> LoxParseResult<Expr> {
+        let mut parts = Vec::new();
+        loop {
+            if !part.is_empty() {
+                parts.push(Expr::from_string(part));
+            }
+            let peek = self.advance()?.ok_or("unterminated interpolation")?;
+            parts.push(self.expression(peek)?);
+            match self.advance()?.map(|token| token.kind) {
+                Some(TokenKind::Interpolation(next)) => part = next,
+                Some(TokenKind::String(last)) => {
+                    if !last.is_empty() {
+                        parts.push(Expr::from_string(last));
+                    }
+                    return Ok(Expr::Interpolation(parts));
+                }
+                _ => return Err("expected '}' after interpolated expression".into()),
+            }
+        }
+    }
+
+    /// The `key: value` entries of a map up to the closing brace, the opening
+    /// brace is already consumed
+    fn map(&mut self) -> LoxParseResult<Expr> {
+        let mut entries = Vec::new();
+        let mut peek = self.advance()?.ok_or("map without closing brace")?;
+        if peek.kind == TokenKind::Structure(Structure::RightBrace) {
+            return Ok(Expr::Map(entries));
+        }
+        loop {
+            let key = self.element(peek)?;
+            if !self.consume(TokenKind::Structure(Structure::Colon))? {
+                Err("expected ':' after map key")?
+            }
+            let value_peek = self.advance()?.ok_or("map entry without a value")?;
+            entries.push((key, self.element(value_peek)?));
+            match self.advance()?.map(|token| token.kind) {
+                Some(TokenKind::Structure(Structure::Comma)) => {
+                    peek = self.advance()?.ok_or("map without closing brace")?;
+                }
+                Some(TokenKind::Structure(Structure::RightBrace)) => return Ok(Expr::Map(entries)),
+                _ => return Err("expected ',' or '}' after map entry".into()),
+            }
+        }
+    }
+
     fn primary(&mut self, peek: Token) -> LoxParseResult<Expr> {
         match peek.kind {
             TokenKind::Literal(lit) => match lit {
@@ -189,23 +730,122 @@ impl<'a> LoxParser<'a> {
                     let peek = self
                         .advance()?
                         .ok_or("parenthesis without following expression")?;
+                    if peek.kind == TokenKind::Structure(Structure::RightParen) {
+                        return self.arrow(Vec::new());
+                    }
                     let expr = self.expression(peek)?;
                     if !self.consume(TokenKind::Structure(Structure::RightParen))? {
                         Err("no terminating parenthesis")?
-                    } else {
-                        Ok(Expr::from_grouping(expr))
+                    }
+                    match self.peek()? {
+                        Some(Token {
+                            kind: TokenKind::Operator(Operator::Arrow),
+                            ..
+                        }) => {
+                            let mut params = Vec::new();
+                            Self::arrow_params(expr, &mut params)?;
+                            self.arrow(params)
+                        }
+                        _ => Ok(Expr::from_grouping(expr)),
                     }
                 }
+                Structure::LeftBracket => self.sequence(Structure::RightBracket).map(Expr::List),
+                Structure::LeftBrace => self.map(),
                 st => Err(LoxParserError::BadStructure(Some(st))),
             },
+            TokenKind::Int(n) => Ok(Expr::from_int(n)),
             TokenKind::Number(n) => Ok(Expr::from_number(n)),
             TokenKind::String(s) => Ok(Expr::from_string(s)),
+            TokenKind::Interpolation(s) => self.interpolation(s),
             TokenKind::Identifier(id) => Ok(Expr::from_ident(id)),
             TokenKind::Operator(op) => Err(LoxParserError::BadOperator(Some(op))),
+            TokenKind::Keyword(Keyword::Fun) => self.function(),
             TokenKind::Keyword(_) => Err("This keyword is not yet supported")?,
         }
     }
 
+    /// `fun (params) { body }`, the `fun` is already consumed
+    fn function(&mut self) -> LoxParseResult<Expr> {
+        if !self.consume(TokenKind::Structure(Structure::LeftParen))? {
+            Err("expected '(' after 'fun'")?
+        }
+        let mut params = Vec::new();
+        let mut peek = self.advance()?.ok_or(LoxParserError::EndOfFile)?;
+        if peek.kind != TokenKind::Structure(Structure::RightParen) {
+            loop {
+                match peek.kind {
+                    TokenKind::Identifier(name) => params.push(name),
+                    _ => return Err("expected a parameter name".into()),
+                }
+                match self.advance()?.map(|token| token.kind) {
+                    Some(TokenKind::Structure(Structure::Comma)) => {}
+                    Some(TokenKind::Structure(Structure::RightParen)) => break,
+                    _ => return Err("expected ',' or ')' after parameter".into()),
+                }
+                peek = self.advance()?.ok_or(LoxParserError::EndOfFile)?;
+            }
+        }
+        if !self.consume(TokenKind::Structure(Structure::LeftBrace))? {
+            Err("expected '{' before function body")?
+        }
+        self.functions += 1;
+        let body = self.block();
+        self.functions -= 1;
+        Ok(Expr::from_function(params, body?))
+    }
+
+    /// Reads the parameters of `(a, b) => value` back out of the
+    /// parenthesised expression they were parsed as
+    fn arrow_params(expr: Expr, params: &mut Vec<String>) -> LoxParseResult<()> {
+        match expr {
+            Expr::Literal(syntax::Literal::Identifier(name)) => params.push(name),
+            Expr::Comma(comma) => {
+                Self::arrow_params(comma.left, params)?;
+                Self::arrow_params(comma.right, params)?;
+            }
+            _ => Err("expected parameter names before '=>'")?,
+        }
+        Ok(())
+    }
+
+    /// `(params) => value`, from the `=>` on
+    fn arrow(&mut self, params: Vec<String>) -> LoxParseResult<Expr> {
+        if !self.consume(TokenKind::Operator(Operator::Arrow))? {
+            Err("expected '=>' after '()'")?
+        }
+        let peek = self.advance()?.ok_or("'=>' without a value")?;
+        let meta = peek.meta.clone();
+        let value = self.element(peek)?;
+        let body = vec![Stmt::new(StmtKind::Return(Some(value)), meta)];
+        Ok(Expr::from_function(params, body))
+    }
+
+    /// Parses something nested in what is being parsed, anything nested in
+    /// it counts from one deeper until it is done
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> LoxParseResult<T>,
+    ) -> LoxParseResult<T> {
+        let depth = self.depth;
+        self.nest()?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    /// One level deeper, such as each operator of `a + b + c` which nests
+    /// the tree a level further on the left
+    fn nest(&mut self) -> LoxParseResult<()> {
+        self.depth += 1;
+        let too_deep = self
+            .stack_bytes
+            .is_some_and(|limit| self.depth > limit / STACK_PER_LEVEL || self.stack.used() > limit);
+        if too_deep {
+            Err("nesting too deep")?
+        }
+        Ok(())
+    }
+
     fn peek(&mut self) -> Result<Option<&Token>, LexicalError> {
         self.peeked
             .get_or_insert_with(|| self.tokens.next())
@@ -216,10 +856,26 @@ impl<'a> LoxParser<'a> {
     }
 
     fn advance(&mut self) -> Result<Option<Token>, LexicalError> {
-        self.peeked
+        let token = self
+            .peeked
             .take()
             .unwrap_or_else(|| self.tokens.next())
-            .transpose()
+            .transpose()?;
+        if let Some(token) = &token {
+            self.last = token.meta.clone();
+        }
+        Ok(token)
+    }
+
+    /// Advances past the next token only if it is `token_kind`
+    fn matches(&mut self, token_kind: TokenKind) -> Result<bool, LexicalError> {
+        match self.peek()? {
+            Some(token) if token.kind == token_kind => {
+                self.advance()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 
     fn consume(&mut self, token_kind: TokenKind) -> Result<bool, LoxParserError> {
@@ -233,15 +889,63 @@ impl<'a> LoxParser<'a> {
 
 #[cfg(test)]
 mod test {
+    use crate::{
+        error::LoxParserError,
+        syntax::{from_lisp, Stmt},
+    };
+
     use super::LoxParser;
 
+    /// Parses a script that has to parse, checking that its tree reads
+    /// back from the s-expressions it prints as
+    fn parse(input: &str) -> Vec<Stmt> {
+        let syntax = LoxParser::new(input).parse().unwrap();
+        let lisp: String = syntax
+            .iter()
+            .map(|stmt| format!("{}\n", stmt.display_lisp()))
+            .collect();
+        assert_eq!(Ok(&syntax), from_lisp(&lisp).as_ref(), "{lisp}");
+        syntax
+    }
+
+    /// Parses a script, checking that its tree is the one the expected
+    /// s-expressions read as
+    fn assert_parses(input: &str, expected: &str) -> Vec<Stmt> {
+        let expected = from_lisp(expected).unwrap_or_else(|err| panic!("{err}: {expected}"));
+        let syntax = parse(input);
+        assert_eq!(expected, syntax, "{input}");
+        syntax
+    }
+
     #[test]
     fn parse_number() {
         let input = "123.456;";
         let expected = "123.456";
 
-        let syntax = LoxParser::new(input).parse().unwrap();
-        assert_eq!(expected, syntax[0].display_lisp().to_string());
+        assert_parses(input, expected);
+    }
+
+    #[test]
+    fn number_forms_round_trip() {
+        for (input, expected) in [
+            ("0xFF", "255"),
+            ("0b1010", "10"),
+            ("0o755", "493"),
+            ("1e-9", "1e-9"),
+            ("6.02E23", "6.02e23"),
+            ("2.0", "2.0"),
+            ("1_000_000", "1000000"),
+        ] {
+            let syntax = parse(&format!("{input};"));
+            let printed = syntax[0].display_lisp().to_string();
+            assert_eq!(expected, printed, "{input}");
+
+            let reparsed = parse(&format!("{printed};"));
+            assert_eq!(printed, reparsed[0].display_lisp().to_string());
+        }
+
+        let syntax = parse("-0x10 * 1.5e1;");
+        assert_eq!("(* (- 16) 15.0)", syntax[0].display_lisp().to_string());
     }
 
     #[test]
@@ -249,8 +953,57 @@ mod test {
         let input = "true == (123 > 42 == -4 + 6 / (4 - 2));";
         let expected = "(== true (group (== (> 123 42) (+ (- 4) (/ 6 (group (- 4 2)))))))";
 
-        let syntax = LoxParser::new(input).parse().unwrap();
-        assert_eq!(expected, syntax[0].display_lisp().to_string());
+        assert_parses(input, expected);
+    }
+
+    #[test]
+    fn parse_arithmetic_and_bitwise_precedence() {
+        for (input, expected) in [
+            ("-2 ** 2;", "(- (** 2 2))"),
+            ("2 ** 3 ** -1;", "(** 2 (** 3 (- 1)))"),
+            (
+                "a * b ** c % d ~/ e;",
+                "(~/ (% (* `a` (** `b` `c`)) `d`) `e`)",
+            ),
+            ("1 << 2 + 3 & 4;", "(& (<< 1 (+ 2 3)) 4)"),
+            ("a | b ^ c & d;", "(| `a` (^ `b` (& `c` `d`)))"),
+            ("~a | 1 < b >> 2;", "(< (| (~ `a`) 1) (>> `b` 2))"),
+            ("x == y | z;", "(== `x` (| `y` `z`))"),
+        ] {
+            assert_parses(input, expected);
+        }
+    }
+
+    #[test]
+    fn parse_conditional_and_comma() {
+        for (input, expected) in [
+            ("a or b ? c : d;", "(?: (or `a` `b`) `c` `d`)"),
+            ("a ? b : c ? d : e;", "(?: `a` `b` (?: `c` `d` `e`))"),
+            ("a ? b, c : d;", "(?: `a` (, `b` `c`) `d`)"),
+            ("x = a ? 1 : 2;", "(= x (?: `a` 1 2))"),
+            ("a, b = 1, c;", "(, (, `a` (= b 1)) `c`)"),
+            (
+                "f(a, (b, c), d ? e : g);",
+                "(call `f` `a` (group (, `b` `c`)) (?: `d` `e` `g`))",
+            ),
+            ("[a, b][a, 1];", "(index (list `a` `b`) (, `a` 1))"),
+            ("a and b or !c;", "(or (and `a` `b`) (! `c`))"),
+        ] {
+            assert_parses(input, expected);
+        }
+    }
+
+    #[test]
+    fn parse_conditional_without_colon() {
+        let err = LoxParser::new("a ? b;").parse().unwrap_err();
+        assert_eq!(
+            LoxParserError::At(
+                Box::new("expected ':' in conditional expression".into()),
+                1,
+                6
+            ),
+            err
+        );
     }
 
     #[test]
@@ -258,7 +1011,205 @@ mod test {
         let input = "!-123;";
         let expected = "(! (- 123))";
 
-        let syntax = LoxParser::new(input).parse().unwrap();
-        assert_eq!(expected, syntax[0].display_lisp().to_string());
+        assert_parses(input, expected);
+    }
+
+    #[test]
+    fn parse_declarations_and_blocks() {
+        let input = "var a = 1;\n{\n  var b;\n  a = b = 2;\n}";
+        let expected = ["(var a 1)", "(block (var b) (= a (= b 2)))"];
+
+        let syntax = assert_parses(input, &expected.join("\n"));
+        assert_eq!((2, 1), (syntax[1].meta.row, syntax[1].meta.col));
+    }
+
+    #[test]
+    fn parse_lists_calls_and_indexing() {
+        let input = "[];\n[1, [2]][0] = f(a, b)(c)[1];\npush(xs, [1 + 2]);";
+        let expected = [
+            "(list)",
+            "(= (index (list 1 (list 2)) 0) (index (call (call `f` `a` `b`) `c`) 1))",
+            "(call `push` `xs` (list (+ 1 2)))",
+        ];
+
+        assert_parses(input, &expected.join("\n"));
+    }
+
+    #[test]
+    fn parse_maps_apart_from_blocks() {
+        let input = "{ var m = {}; }\nm = {\"a\": 1, 2: [3]};\n{ print {true: nil}[true]; }";
+        let expected = [
+            "(block (var m (map)))",
+            "(= m (map \"a\" 1 2 (list 3)))",
+            "(block (print (index (map true nil) true)))",
+        ];
+
+        assert_parses(input, &expected.join("\n"));
+    }
+
+    #[test]
+    fn parse_interpolation() {
+        let input = r#""n = ${n}, sum = ${a + b}" + "${"${x}"}!";"#;
+        let expected = r#"(+ (interpolate "n = " `n` ", sum = " (+ `a` `b`)) (interpolate (interpolate `x`) "!"))"#;
+
+        assert_parses(input, expected);
+    }
+
+    #[test]
+    fn parse_unclosed_list() {
+        let err = LoxParser::new("[1, 2;").parse().unwrap_err();
+        assert_eq!(
+            LoxParserError::At(Box::new("expected ',' or ']' after element".into()), 1, 6),
+            err
+        );
+    }
+
+    #[test]
+    fn parse_invalid_assignment() {
+        let err = LoxParser::new("1 + a = 2;").parse().unwrap_err();
+        assert_eq!(
+            LoxParserError::At(Box::new("invalid assignment target".into()), 1, 9),
+            err
+        );
+    }
+
+    #[test]
+    fn parse_update_operators() {
+        let input = "x += 1; xs[0] -= 2, y; a *= b /= 2; ++x; --xs[i]; x++ + -x--; ++x ** 2;";
+        let expected = [
+            "(+= x 1)",
+            "(, (-= (index `xs` 0) 2) `y`)",
+            "(*= a (/= b 2))",
+            "(+= x 1)",
+            "(-= (index `xs` `i`) 1)",
+            "(+ (post+= x 1) (- (post-= x 1)))",
+            "(** (+= x 1) 2)",
+        ];
+
+        assert_parses(input, &expected.join("\n"));
+    }
+
+    #[test]
+    fn parse_invalid_update_targets() {
+        for input in [
+            "1 += 2;",
+            "(a) -= 1;",
+            "++1;",
+            "f()++;",
+            "x++ ++;",
+            "++x++;",
+        ] {
+            let err = LoxParser::new(input).parse().unwrap_err();
+            assert!(
+                matches!(&err, LoxParserError::At(inner, ..)
+                    if **inner == "invalid assignment target".into()),
+                "{input}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_functions() {
+        let input = "var f = fun (a, b) { print a; return; };\nf(fun () { return 1; });\n\
+            ((a, b) => a + b, () => 1, (x) => (y) => x);";
+        let expected = [
+            "(var f (fun (a b) (print `a`) (return)))",
+            "(call `f` (fun () (return 1)))",
+            "(group (, (, (fun (a b) (return (+ `a` `b`))) (fun () (return 1))) \
+            (fun (x) (return (fun (y) (return `x`))))))",
+        ];
+
+        assert_parses(input, &expected.join("\n"));
+    }
+
+    #[test]
+    fn parse_invalid_functions() {
+        let cases = [
+            ("return 1;", "can't return from outside a function"),
+            ("fun f() {}", "expected '(' after 'fun'"),
+            ("fun (a b) {};", "expected ',' or ')' after parameter"),
+            ("fun (1) {};", "expected a parameter name"),
+            ("(a + 1) => a;", "expected parameter names before '=>'"),
+            ("() + 1;", "expected '=>' after '()'"),
+        ];
+        for (input, message) in cases {
+            let err = LoxParser::new(input).parse().unwrap_err();
+            assert!(
+                matches!(&err, LoxParserError::At(inner, ..) if **inner == message.into()),
+                "{input}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_exceptions() {
+        let input = "try { throw 1; } catch (e) { print e; }\n\
+            try { f(); } finally { g(); }\n\
+            try {} catch (e) {} finally {}";
+        let expected = [
+            "(try (block (throw 1)) (catch e (block (print `e`))))",
+            "(try (block (call `f`)) (finally (block (call `g`))))",
+            "(try (block) (catch e (block)) (finally (block)))",
+        ];
+
+        assert_parses(input, &expected.join("\n"));
+    }
+
+    #[test]
+    fn parse_invalid_exceptions() {
+        let cases = [
+            ("try {}", "expected 'catch' or 'finally' after try block"),
+            ("try print 1;", "expected '{' after 'try'"),
+            ("try {} catch e {}", "expected '(' after 'catch'"),
+            ("try {} catch (1) {}", "expected a name for what was caught"),
+        ];
+        for (input, message) in cases {
+            let err = LoxParser::new(input).parse().unwrap_err();
+            assert!(
+                matches!(&err, LoxParserError::At(inner, ..) if **inner == message.into()),
+                "{input}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_imports() {
+        let input =
+            "import \"lib/a.lox\" as a;\nfrom \"b.lox\" import x, y;\nvar from = 1;\nfrom + 1;";
+        let expected = [
+            "(import \"lib/a.lox\" a)",
+            "(from \"b.lox\" x y)",
+            "(var from 1)",
+            "(+ `from` 1)",
+        ];
+
+        assert_parses(input, &expected.join("\n"));
+    }
+
+    #[test]
+    fn parse_invalid_imports() {
+        let cases = [
+            ("import a;", "expected a path after 'import'"),
+            ("import \"a.lox\";", "expected 'as' after the imported path"),
+            ("import \"a.lox\" as;", "expected a name for the module"),
+            ("from \"a.lox\" x;", "expected 'import' after the path"),
+            ("from \"a.lox\" import x,;", "expected a name to import"),
+        ];
+        for (input, message) in cases {
+            let err = LoxParser::new(input).parse().unwrap_err();
+            assert!(
+                matches!(&err, LoxParserError::At(inner, ..) if **inner == message.into()),
+                "{input}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_error_is_located() {
+        let input = "print 1;\nprint 2 3;";
+
+        let err = LoxParser::new(input).parse().unwrap_err();
+        assert_eq!(Some((2, 9)), err.position());
+        assert!(matches!(err, LoxParserError::At(..)));
     }
 }
@@ -14,6 +14,8 @@ pub struct Scanner<'a> {
     source: &'a str,
     start: Pos,
     current: Pos,
+    /// For each `${` being scanned, how many braces are open inside it
+    holes: Vec<usize>,
 }
 
 impl<'a> Scanner<'a> {
@@ -30,6 +32,7 @@ impl<'a> Scanner<'a> {
                 row: 1,
                 col: 1,
             },
+            holes: Vec::new(),
         }
     }
 
@@ -43,14 +46,68 @@ impl<'a> Scanner<'a> {
             match c {
                 '(' => break TokenKind::Structure(LeftParen),
                 ')' => break TokenKind::Structure(RightParen),
-                '{' => break TokenKind::Structure(LeftBrace),
-                '}' => break TokenKind::Structure(RightBrace),
+                '{' => {
+                    if let Some(open) = self.holes.last_mut() {
+                        *open += 1;
+                    }
+                    break TokenKind::Structure(LeftBrace);
+                }
+                '}' => match self.holes.last_mut() {
+                    Some(0) => {
+                        self.holes.pop();
+                        return Some(self.string(false));
+                    }
+                    Some(open) => {
+                        *open -= 1;
+                        break TokenKind::Structure(RightBrace);
+                    }
+                    None => break TokenKind::Structure(RightBrace),
+                },
+                '[' => break TokenKind::Structure(LeftBracket),
+                ']' => break TokenKind::Structure(RightBracket),
                 ',' => break TokenKind::Structure(Comma),
+                ':' => break TokenKind::Structure(Colon),
                 '.' => break TokenKind::Structure(Dot),
                 ';' => break TokenKind::Structure(SemiColon),
-                '-' => break TokenKind::Operator(Minus),
-                '+' => break TokenKind::Operator(Plus),
-                '*' => break TokenKind::Operator(Star),
+                '-' => {
+                    break if self.matches('=') {
+                        TokenKind::Operator(MinusEqual)
+                    } else if self.matches('-') {
+                        TokenKind::Operator(MinusMinus)
+                    } else {
+                        TokenKind::Operator(Minus)
+                    }
+                }
+                '+' => {
+                    break if self.matches('=') {
+                        TokenKind::Operator(PlusEqual)
+                    } else if self.matches('+') {
+                        TokenKind::Operator(PlusPlus)
+                    } else {
+                        TokenKind::Operator(Plus)
+                    }
+                }
+                '*' => {
+                    break if self.matches('*') {
+                        TokenKind::Operator(StarStar)
+                    } else if self.matches('=') {
+                        TokenKind::Operator(StarEqual)
+                    } else {
+                        TokenKind::Operator(Star)
+                    }
+                }
+                '%' => break TokenKind::Operator(Percent),
+                '?' => break TokenKind::Operator(Question),
+                '&' => break TokenKind::Operator(Ampersand),
+                '|' => break TokenKind::Operator(Pipe),
+                '^' => break TokenKind::Operator(Caret),
+                '~' => {
+                    break if self.matches('/') {
+                        TokenKind::Operator(TildeSlash)
+                    } else {
+                        TokenKind::Operator(Tilde)
+                    }
+                }
                 '!' => {
                     break if self.matches('=') {
                         TokenKind::Operator(BangEqual)
@@ -61,6 +118,8 @@ impl<'a> Scanner<'a> {
                 '=' => {
                     break if self.matches('=') {
                         TokenKind::Operator(EqualEqual)
+                    } else if self.matches('>') {
+                        TokenKind::Operator(Arrow)
                     } else {
                         TokenKind::Operator(Equal)
                     }
@@ -68,6 +127,8 @@ impl<'a> Scanner<'a> {
                 '<' => {
                     break if self.matches('=') {
                         TokenKind::Operator(LessEqual)
+                    } else if self.matches('<') {
+                        TokenKind::Operator(LessLess)
                     } else {
                         TokenKind::Operator(Less)
                     }
@@ -75,6 +136,8 @@ impl<'a> Scanner<'a> {
                 '>' => {
                     break if self.matches('=') {
                         TokenKind::Operator(GreaterEqual)
+                    } else if self.matches('>') {
+                        TokenKind::Operator(GreaterGreater)
                     } else {
                         TokenKind::Operator(Greater)
                     }
@@ -85,6 +148,8 @@ impl<'a> Scanner<'a> {
                             self.advance();
                         }
                         c = self.restart()?;
+                    } else if self.matches('=') {
+                        break TokenKind::Operator(SlashEqual);
                     } else {
                         break TokenKind::Operator(Slash);
                     }
@@ -92,14 +157,18 @@ impl<'a> Scanner<'a> {
                 ' ' | '\r' | '\t' | '\n' => {
                     c = self.restart()?;
                 }
-                '"' => return Some(self.string()),
-                '0'..='9' => return Some(self.number()),
+                '"' => return Some(self.string(false)),
+                'r' if self.look_ahead() == Some('"') => {
+                    self.advance();
+                    return Some(self.string(true));
+                }
+                '0'..='9' => return Some(self.number(c)),
                 'a'..='z' | 'A'..='Z' | '_' => return Some(self.identifier()),
                 _ => {
                     return Some(Err(LexicalError::UnexpectedChar(
                         c,
-                        self.current.row,
-                        self.current.col,
+                        self.start.row,
+                        self.start.col,
                     )))
                 }
             }
@@ -169,57 +238,188 @@ impl<'a> Scanner<'a> {
         self.rest().chars().nth(n)
     }
 
-    fn string(&mut self) -> Result<Token, LexicalError> {
-        while self.look_ahead().ok_or(LexicalError::UnterminatedString(
-            self.start.row,
-            self.start.col,
-        ))? != '"'
-        {
-            self.advance().ok_or(LexicalError::UnterminatedString(
-                self.start.row,
-                self.start.col,
-            ))?;
+    /// Scans a string up to the closing quote, the opening quote is
+    /// already consumed. Raw strings keep their backslashes as they are.
+    ///
+    /// A `${` ends the token early as an [`TokenKind::Interpolation`], the
+    /// tokens of the expression follow and the `}` closing it carries on
+    /// with the rest of the string.
+    fn string(&mut self, raw: bool) -> Result<Token, LexicalError> {
+        let unterminated = LexicalError::UnterminatedString(self.start.row, self.start.col);
+        let mut value = String::new();
+        // an invalid escape is reported once the whole string is skipped
+        let mut invalid = None;
+        loop {
+            let at = self.current;
+            match self.advance().ok_or(unterminated)? {
+                '"' => break,
+                '$' if !raw && self.matches('{') => {
+                    self.holes.push(0);
+                    if let Some(err) = invalid {
+                        return Err(err);
+                    }
+                    return Ok(Token {
+                        kind: TokenKind::Interpolation(value),
+                        meta: TokenMeta {
+                            row: self.start.row,
+                            col: self.start.col,
+                        },
+                    });
+                }
+                '\\' if !raw => match self.escape() {
+                    Some(c) => value.push(c),
+                    None => {
+                        invalid.get_or_insert(LexicalError::InvalidEscape(at.row, at.col));
+                    }
+                },
+                c => value.push(c),
+            }
+        }
+        if let Some(err) = invalid {
+            return Err(err);
         }
-        self.advance();
 
-        let s = self.sub_str();
-        let token = Token {
-            kind: TokenKind::String((&s[1..s.len() - 1]).into()),
+        Ok(Token {
+            kind: TokenKind::String(value),
             meta: TokenMeta {
                 row: self.start.row,
                 col: self.start.col,
             },
-        };
-        Ok(token)
+        })
+    }
+
+    /// The character an escape sequence stands for, the backslash is
+    /// already consumed
+    fn escape(&mut self) -> Option<char> {
+        match self.advance()? {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            'u' => {
+                if !self.matches('{') {
+                    return None;
+                }
+                let mut code = 0;
+                for digits in 0.. {
+                    match self.look_ahead()? {
+                        '}' if digits > 0 => break,
+                        c if digits < 6 && c.is_ascii_hexdigit() => {
+                            code = code * 16 + c.to_digit(16)?;
+                        }
+                        _ => return None,
+                    }
+                    self.advance();
+                }
+                self.advance();
+                char::from_u32(code)
+            }
+            _ => None,
+        }
     }
 
     fn sub_str(&mut self) -> &'a str {
         &self.source[self.start.index..self.current.index]
     }
 
-    fn number(&mut self) -> Result<Token, LexicalError> {
-        while matches!(self.look_ahead(), Some('0'..='9')) {
-            self.advance();
-        }
+    /// Scans a number, its first digit is already consumed
+    fn number(&mut self, first: char) -> Result<Token, LexicalError> {
+        let radix = match (first, self.look_ahead()) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('o' | 'O')) => 8,
+            ('0', Some('b' | 'B')) => 2,
+            _ => 10,
+        };
 
-        if self.look_ahead() == Some('.') && matches!(self.look_ahead_nth(1), Some('0'..='9')) {
+        let overflow = LexicalError::ParseNumberError(self.start.row, self.start.col);
+        let kind = if radix == 10 {
+            let mut float = false;
+            self.digits(10, true)?;
+            if self.look_ahead() == Some('.')
+                && self.look_ahead_nth(1).is_some_and(|c| c.is_ascii_digit())
+            {
+                float = true;
+                self.advance();
+                self.digits(10, false)?;
+            }
+            if matches!(self.look_ahead(), Some('e' | 'E')) {
+                float = true;
+                self.advance();
+                if matches!(self.look_ahead(), Some('+' | '-')) {
+                    self.advance();
+                }
+                self.required_digits(10)?;
+            }
+            let text: String = self.sub_str().chars().filter(|&c| c != '_').collect();
+            if float {
+                TokenKind::Number(text.parse().map_err(|_| overflow)?)
+            } else {
+                TokenKind::Int(text.parse().map_err(|_| overflow)?)
+            }
+        } else {
             self.advance();
+            self.required_digits(radix)?;
+            let n = self.sub_str()[2..]
+                .chars()
+                .filter_map(|c| c.to_digit(radix))
+                .try_fold(0i64, |n, digit| {
+                    n.checked_mul(radix.into())?.checked_add(digit.into())
+                })
+                .ok_or(overflow)?;
+            TokenKind::Int(n)
+        };
 
-            while matches!(self.look_ahead(), Some('0'..='9')) {
-                self.advance();
+        Ok(Token {
+            kind,
+            meta: TokenMeta {
+                row: self.start.row,
+                col: self.start.col,
+            },
+        })
+    }
+
+    /// Consumes digits in `radix` and the `_` separating them, and returns
+    /// how many digits there were. `after_digit` is whether the character
+    /// before them was a digit, so that they may start with a separator.
+    fn digits(&mut self, radix: u32, mut after_digit: bool) -> Result<usize, LexicalError> {
+        let mut count = 0;
+        loop {
+            let at = self.current;
+            match self.look_ahead() {
+                Some('_') => {
+                    self.advance();
+                    let followed = self
+                        .look_ahead()
+                        .is_some_and(|c| c == '_' || c.is_digit(radix));
+                    if !after_digit || !followed {
+                        return Err(LexicalError::ParseNumberError(at.row, at.col));
+                    }
+                    after_digit = false;
+                }
+                Some(c) if c.is_digit(radix) => {
+                    self.advance();
+                    count += 1;
+                    after_digit = true;
+                }
+                // such as the 2 in 0b102
+                Some(c) if c.is_ascii_digit() => {
+                    return Err(LexicalError::ParseNumberError(at.row, at.col))
+                }
+                _ => return Ok(count),
             }
         }
+    }
 
-        self.sub_str()
-            .parse()
-            .map_err(|_| LexicalError::ParseNumberError(self.start.row, self.start.col))
-            .map(|n| Token {
-                kind: TokenKind::Number(n),
-                meta: TokenMeta {
-                    row: self.start.row,
-                    col: self.start.col,
-                },
-            })
+    /// Like [`Self::digits`] when there has to be at least one digit, the
+    /// error is where the first one was expected
+    fn required_digits(&mut self, radix: u32) -> Result<(), LexicalError> {
+        let at = self.current;
+        if self.digits(radix, false)? == 0 {
+            Err(LexicalError::ParseNumberError(at.row, at.col))
+        } else {
+            Ok(())
+        }
     }
 
     fn identifier(&mut self) -> Result<Token, LexicalError> {
@@ -238,16 +438,21 @@ impl<'a> Scanner<'a> {
             "and" => TokenKind::Operator(And),
             "or" => TokenKind::Operator(Or),
             "class" => TokenKind::Keyword(Class),
+            "catch" => TokenKind::Keyword(Catch),
             "else" => TokenKind::Keyword(Else),
             "false" => TokenKind::Literal(Literal::False),
+            "finally" => TokenKind::Keyword(Finally),
             "fun" => TokenKind::Keyword(Fun),
             "for" => TokenKind::Keyword(For),
             "if" => TokenKind::Keyword(If),
+            "import" => TokenKind::Keyword(Import),
             "nil" => TokenKind::Literal(Literal::Nil),
             "print" => TokenKind::Keyword(Print),
             "return" => TokenKind::Keyword(Return),
             "super" => TokenKind::Keyword(Super),
             "this" => TokenKind::Keyword(This),
+            "throw" => TokenKind::Keyword(Throw),
+            "try" => TokenKind::Keyword(Try),
             "true" => TokenKind::Literal(Literal::True),
             "var" => TokenKind::Keyword(Var),
             "while" => TokenKind::Keyword(While),
@@ -291,14 +496,14 @@ mod test {
         error::LexicalError,
         token::{Operator::*, Structure::*, TokenKind},
     };
-    use TokenKind::{Number, String};
+    use TokenKind::{Int, Number, String};
     // use TokenKind::LangToken;
 
     use super::Scanner;
 
     #[test]
     fn tokenise_symbols() {
-        let input = "{ } ( ) , . - + ; *";
+        let input = "{ } ( ) [ ] , : . - + ; *";
         let scanner = Scanner::new(input);
         let tokens: Vec<_> = scanner.map(|token| token.unwrap().kind).collect();
 
@@ -307,7 +512,10 @@ mod test {
             TokenKind::Structure(RightBrace),
             TokenKind::Structure(LeftParen),
             TokenKind::Structure(RightParen),
+            TokenKind::Structure(LeftBracket),
+            TokenKind::Structure(RightBracket),
             TokenKind::Structure(Comma),
+            TokenKind::Structure(Colon),
             TokenKind::Structure(Dot),
             TokenKind::Operator(Minus),
             TokenKind::Operator(Plus),
@@ -320,11 +528,47 @@ mod test {
 
     #[test]
     fn tokenise_compound_symbols() {
-        let scanner = Scanner::new("== != <= >=");
+        let scanner = Scanner::new("== != <= >= ** ~/ << >> % & | ^ ~ ~~/ ? => ==>");
+
+        let tokens: Vec<_> = scanner.map(|token| token.unwrap().kind).collect();
+
+        let expected = [
+            EqualEqual,
+            BangEqual,
+            LessEqual,
+            GreaterEqual,
+            StarStar,
+            TildeSlash,
+            LessLess,
+            GreaterGreater,
+            Percent,
+            Ampersand,
+            Pipe,
+            Caret,
+            Tilde,
+            Tilde,
+            TildeSlash,
+            Question,
+            Arrow,
+            EqualEqual,
+            Greater,
+        ]
+        .map(TokenKind::Operator);
+
+        assert_eq!(&expected[..], &tokens[..]);
+    }
+
+    #[test]
+    fn tokenise_update_operators() {
+        let scanner = Scanner::new("+= -= *= /= ++ -- +++ **= - -= // /=");
 
         let tokens: Vec<_> = scanner.map(|token| token.unwrap().kind).collect();
 
-        let expected = [EqualEqual, BangEqual, LessEqual, GreaterEqual].map(TokenKind::Operator);
+        let expected = [
+            PlusEqual, MinusEqual, StarEqual, SlashEqual, PlusPlus, MinusMinus, PlusPlus, Plus,
+            StarStar, Equal, Minus, MinusEqual,
+        ]
+        .map(TokenKind::Operator);
 
         assert_eq!(&expected[..], &tokens[..]);
     }
@@ -370,12 +614,111 @@ mod test {
         assert_eq!(&expected[..], &tokens[..]);
     }
 
+    #[test]
+    fn parse_escapes() {
+        let scanner = Scanner::new(r#""a\nb\t\\" "say \"hi\"" "\u{1F600}\u{e9}""#);
+
+        let tokens: Vec<_> = scanner.map(|token| token.unwrap().kind).collect();
+
+        let expected = ["a\nb\t\\", "say \"hi\"", "\u{1F600}\u{e9}"].map(|s| String(s.into()));
+
+        assert_eq!(&expected[..], &tokens[..]);
+    }
+
+    #[test]
+    fn parse_raw_strings() {
+        let scanner = Scanner::new(r#"r"C:\dir\n" r + "r""#);
+
+        let tokens: Vec<_> = scanner.map(|token| token.unwrap().kind).collect();
+
+        let expected = [
+            String(r"C:\dir\n".into()),
+            TokenKind::Identifier("r".into()),
+            TokenKind::Operator(Plus),
+            String("r".into()),
+        ];
+
+        assert_eq!(&expected[..], &tokens[..]);
+    }
+
+    #[test]
+    fn split_interpolated_strings() {
+        let scanner = Scanner::new(r#""a ${b} c ${ {"d": "${e}"}["d"] }\${f}""#);
+
+        let tokens: Vec<_> = scanner.map(|token| token.unwrap().kind).collect();
+
+        let expected = [
+            TokenKind::Interpolation("a ".into()),
+            TokenKind::Identifier("b".into()),
+            TokenKind::Interpolation(" c ".into()),
+            TokenKind::Structure(LeftBrace),
+            String("d".into()),
+            TokenKind::Structure(Colon),
+            TokenKind::Interpolation("".into()),
+            TokenKind::Identifier("e".into()),
+            String("".into()),
+            TokenKind::Structure(RightBrace),
+            TokenKind::Structure(LeftBracket),
+            String("d".into()),
+            TokenKind::Structure(RightBracket),
+            String("${f}".into()),
+        ];
+
+        assert_eq!(&expected[..], &tokens[..]);
+    }
+
+    #[test]
+    fn invalid_escape_position() {
+        for (input, col) in [
+            (r#"1 "ok\q""#, 6),
+            (r#"1 "\u{110000}""#, 4),
+            (r#"1 "\u{}""#, 4),
+            (r#"1 "\u12""#, 4),
+        ] {
+            let scanner = Scanner::new(input);
+
+            let tokens: Vec<_> = scanner.map(|r| r.map(|t| t.kind)).collect();
+
+            let expected = [Ok(Int(1)), Err(LexicalError::InvalidEscape(1, col))];
+
+            assert_eq!(&expected[..], &tokens[..], "{input}");
+        }
+    }
+
+    #[test]
+    fn positions_after_multi_line_string() {
+        let scanner = Scanner::new("\"one\ntwo\\n\"\n  #");
+
+        let tokens: Vec<_> = scanner.map(|r| r.map(|t| t.kind)).collect();
+
+        let expected = [
+            Ok(String("one\ntwo\n".into())),
+            Err(LexicalError::UnexpectedChar('#', 3, 3)),
+        ];
+
+        assert_eq!(&expected[..], &tokens[..]);
+    }
+
+    #[test]
+    fn unexpected_char_position() {
+        let scanner = Scanner::new("+\n  #");
+
+        let tokens: Vec<_> = scanner.map(|r| r.map(|t| t.kind)).collect();
+
+        let expected = [
+            Ok(TokenKind::Operator(Plus)),
+            Err(LexicalError::UnexpectedChar('#', 2, 3)),
+        ];
+
+        assert_eq!(&expected[..], &tokens[..]);
+    }
+
     #[test]
     fn parse_int() {
         let scanner = Scanner::new("123");
         let tokens: Vec<_> = scanner.map(|t| t.unwrap().kind).collect();
 
-        let expected = [Number(123.)];
+        let expected = [Int(123)];
 
         assert_eq!(tokens[..], expected[..])
     }
@@ -390,6 +733,49 @@ mod test {
         assert_eq!(tokens[..], expected[..])
     }
 
+    #[test]
+    fn parse_number_forms() {
+        let scanner =
+            Scanner::new("0xFF 0b1010 0o755 1e-9 6.02E23 1_000_000 0x_ 2.5e+3 0xdead_BEEF");
+        let tokens: Vec<_> = scanner.map(|t| t.map(|t| t.kind)).collect();
+
+        let expected = [
+            Ok(Int(255)),
+            Ok(Int(10)),
+            Ok(Int(493)),
+            Ok(Number(1e-9)),
+            Ok(Number(6.02e23)),
+            Ok(Int(1_000_000)),
+            Err(LexicalError::ParseNumberError(1, 44)),
+            Ok(Number(2500.)),
+            Ok(Int(3_735_928_559)),
+        ];
+
+        assert_eq!(tokens[..], expected[..])
+    }
+
+    #[test]
+    fn malformed_number_positions() {
+        for (input, col) in [
+            ("0x", 3),
+            ("0x;", 3),
+            ("1e", 3),
+            ("1e+", 4),
+            ("1__0", 3),
+            ("1_", 2),
+            ("0b102", 5),
+            ("0o8", 3),
+            ("  12_.5", 5),
+            ("9223372036854775808", 1),
+            ("0x1_0000_0000_0000_0000", 1),
+        ] {
+            let mut scanner = Scanner::new(input);
+            let err = scanner.next().unwrap().unwrap_err();
+
+            assert_eq!(LexicalError::ParseNumberError(1, col), err, "{input}");
+        }
+    }
+
     #[test]
     fn lex_some_complex_code() {
         let input = r#"
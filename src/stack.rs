@@ -0,0 +1,27 @@
+//! How much of the Rust stack recursion has used, so that parsing and
+//! running fail with "nesting too deep" rather than overflow it
+
+use std::hint::black_box;
+
+/// The stack a parse or run may use unless its [`Limits`](crate::Limits)
+/// say otherwise, half the 2 MiB a spawned thread gets so that what the
+/// host already used and the frames between checks fit in the rest
+pub const DEFAULT_STACK_BYTES: usize = 1024 * 1024;
+
+/// Where recursion started, to measure the stack used since
+#[derive(Debug, Clone, Copy)]
+pub struct StackBase(usize);
+
+impl StackBase {
+    /// The stack as it is in the caller
+    #[inline(never)]
+    pub fn here() -> Self {
+        let marker = 0u8;
+        Self(black_box(&marker) as *const u8 as usize)
+    }
+
+    /// The bytes used since the base, whichever way the stack grows
+    pub fn used(self) -> usize {
+        Self::here().0.abs_diff(self.0)
+    }
+}
@@ -1,25 +1,97 @@
-use std::fmt::{Display, Write};
+//! The syntax tree a script parses to, and its JSON, s-expression and
+//! Graphviz forms
 
-#[derive(Debug, Clone, Copy)]
+use std::{
+    fmt::{Display, Write},
+    rc::Rc,
+};
+
+use crate::{error::AstError, json::Json};
+
+pub use crate::token::TokenMeta;
+
+pub use self::printer::Lisp;
+
+/// The operator of a [`Binary`] or an [`Update`], displayed as written
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinOp {
+    /// `==`
     Eq,
+    /// `!=`
     Ne,
+    /// `<`
     Lt,
+    /// `>`
     Gt,
+    /// `<=`
     Le,
+    /// `>=`
     Ge,
+    /// `+`, which also joins strings
     Add,
+    /// `-`
     Sub,
+    /// `*`
     Mul,
+    /// `/`, which makes a float even from ints
     Div,
+    /// `~/`, division that truncates to an int
+    IntDiv,
+    /// `%`
+    Rem,
+    /// `**`
+    Pow,
+    /// `&`
+    BitAnd,
+    /// `|`
+    BitOr,
+    /// `^`
+    BitXor,
+    /// `<<`
+    Shl,
+    /// `>>`
+    Shr,
+    /// `and`, which only evaluates the right when the left is true
     And,
+    /// `or`, which only evaluates the right when the left is false
     Or,
 }
 
-#[derive(Debug, Copy, Clone)]
+/// The operator of a [`Unary`], displayed as written
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum UnOp {
+    /// `-`
     Neg,
+    /// `!`
     Not,
+    /// `~`
+    BitNot,
+}
+
+impl BinOp {
+    /// Every operator, in the order they are declared
+    pub const ALL: [BinOp; 20] = [
+        BinOp::Eq,
+        BinOp::Ne,
+        BinOp::Lt,
+        BinOp::Gt,
+        BinOp::Le,
+        BinOp::Ge,
+        BinOp::Add,
+        BinOp::Sub,
+        BinOp::Mul,
+        BinOp::Div,
+        BinOp::IntDiv,
+        BinOp::Rem,
+        BinOp::Pow,
+        BinOp::BitAnd,
+        BinOp::BitOr,
+        BinOp::BitXor,
+        BinOp::Shl,
+        BinOp::Shr,
+        BinOp::And,
+        BinOp::Or,
+    ];
 }
 
 impl Display for BinOp {
@@ -35,6 +107,14 @@ impl Display for BinOp {
             BinOp::Sub => f.write_char('-'),
             BinOp::Mul => f.write_char('*'),
             BinOp::Div => f.write_char('/'),
+            BinOp::IntDiv => f.write_str("~/"),
+            BinOp::Rem => f.write_char('%'),
+            BinOp::Pow => f.write_str("**"),
+            BinOp::BitAnd => f.write_char('&'),
+            BinOp::BitOr => f.write_char('|'),
+            BinOp::BitXor => f.write_char('^'),
+            BinOp::Shl => f.write_str("<<"),
+            BinOp::Shr => f.write_str(">>"),
             BinOp::And => f.write_str("and"),
             BinOp::Or => f.write_str("or"),
         }
@@ -46,61 +126,283 @@ impl Display for UnOp {
         match self {
             UnOp::Neg => f.write_char('-'),
             UnOp::Not => f.write_char('!'),
+            UnOp::BitNot => f.write_char('~'),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// `left operator right`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Binary {
+    /// The operand on the left
     pub left: Expr,
+    /// The operator between them
     pub operator: BinOp,
+    /// The operand on the right
     pub right: Expr,
 }
 
-#[derive(Debug, Clone)]
+/// `(expression)`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Grouping {
+    /// What is in the parentheses
     pub expression: Expr,
 }
 
-#[derive(Debug, Clone)]
+/// A single token that is an expression of its own
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
+    /// A string without holes, with its escapes already replaced
     String(String),
+    /// A variable read
     Identifier(String),
+    /// A number written without a fraction or exponent
+    Int(i64),
+    /// A number written with a fraction or exponent
     Number(f64),
+    /// `true`
     True,
+    /// `false`
     False,
+    /// `nil`
     Nil,
 }
 
-#[derive(Debug, Clone)]
+/// `name = value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assign {
+    /// The variable assigned
+    pub name: String,
+    /// What it is assigned
+    pub value: Expr,
+}
+
+/// `condition ? then_branch : else_branch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conditional {
+    /// Decides which branch is evaluated
+    pub condition: Expr,
+    /// Evaluated when the condition is true
+    pub then_branch: Expr,
+    /// Evaluated when the condition is false
+    pub else_branch: Expr,
+}
+
+/// `left, right` evaluates both and results in `right`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comma {
+    /// Evaluated first, only for what it does
+    pub left: Expr,
+    /// Evaluated second, for the result
+    pub right: Expr,
+}
+
+/// `callee(arguments)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// The function called
+    pub callee: Expr,
+    /// What it is called with, evaluated in order after the callee
+    pub arguments: Vec<Expr>,
+}
+
+/// `fun (params) { body }`, the body of `(params) => value` is a single
+/// `return`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// The names the arguments are bound to
+    pub params: Vec<String>,
+    /// What a call runs
+    pub body: Vec<Stmt>,
+}
+
+/// `object[index]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    /// The list, map or string indexed
+    pub object: Expr,
+    /// The position in a list or string, or the key in a map
+    pub index: Expr,
+}
+
+/// `object[index] = value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetIndex {
+    /// The list or map changed
+    pub object: Expr,
+    /// The position in a list or the key in a map
+    pub index: Expr,
+    /// What is stored there
+    pub value: Expr,
+}
+
+/// What an [`Update`] reads from and writes back to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// A variable by name
+    Variable(String),
+    /// An element of a list or map
+    Index(Index),
+}
+
+/// `target op= value`, `++target` and `target++`, the parts of the target
+/// are only evaluated once
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    /// What is read and written back
+    pub target: Target,
+    /// Combines what was read with the value, `++` and `--` add and
+    /// subtract
+    pub operator: BinOp,
+    /// The right operand, `1` for `++` and `--`
+    pub value: Expr,
+    /// Results in the value from before the update
+    pub postfix: bool,
+}
+
+/// `operator expression`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Unary {
+    /// The operator before the operand
     pub operator: UnOp,
+    /// The operand
     pub expression: Expr,
 }
 
-#[derive(Debug, Clone)]
-pub enum Stmt {
+/// `try { body } catch (name) { body } finally { body }`, with at least
+/// one of `catch` and `finally`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Try {
+    /// What is tried
+    pub body: Vec<Stmt>,
+    /// Run if the body fails
+    pub catch: Option<Catch>,
+    /// Run after the body and the catch, however they end
+    pub finally: Option<Vec<Stmt>>,
+}
+
+/// Binds what was thrown to `name` in its body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+    /// The variable what was thrown is bound to
+    pub name: String,
+    /// What is run with it
+    pub body: Vec<Stmt>,
+}
+
+/// `import "path" as name;` or `from "path" import names;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// The module's file as written, relative to the importing file
+    pub path: String,
+    /// The names it binds
+    pub names: Imported,
+}
+
+/// What an [`Import`] binds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Imported {
+    /// The whole module as a map of its exports
+    Module(String),
+    /// Some of its exports as variables of their own
+    Names(Vec<String>),
+}
+
+/// What a [`Stmt`] is
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum StmtKind {
+    /// `expression;`, evaluated for what it does
     Expr(Expr),
+    /// `print expression;`
     Print(Expr),
-    #[allow(dead_code)]
-    Var(&'static str, Expr),
+    /// `var name = initializer;`, a variable without one is `nil`
+    Var(String, Option<Expr>),
+    /// `{ statements }`, with a scope of its own
+    Block(Vec<Stmt>),
+    /// `return value;`, `nil` without one
+    Return(Option<Expr>),
+    /// `throw value;`
+    Throw(Expr),
+    /// `try`, see [`Try`]
+    Try(Box<Try>),
+    /// `import` and `from`, see [`Import`]
+    Import(Import),
+}
+
+/// A statement and where it starts in the source
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    /// What the statement is
+    pub kind: StmtKind,
+    /// Where it starts, runtime errors in it are reported there
+    pub meta: TokenMeta,
+}
+
+/// Statements are equal when their trees are, wherever they start
+impl PartialEq for Stmt {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
 }
 
 impl Stmt {
-    pub fn display_lisp(&self) -> printer::Lisp<'_> {
+    /// A statement of `kind` starting at `meta`
+    pub fn new(kind: StmtKind, meta: TokenMeta) -> Self {
+        Self { kind, meta }
+    }
+
+    /// Displays the statement as an s-expression, which [`from_lisp`]
+    /// reads back
+    pub fn display_lisp(&self) -> Lisp<'_> {
         printer::Lisp::new(self)
     }
 }
 
-#[derive(Debug, Clone)]
+/// An expression, each kind of node has a constructor such as
+/// [`Expr::from_binary`] that boxes it
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Expr {
+    /// See [`Assign`]
+    Assign(Box<Assign>),
+    /// See [`Binary`]
     Binary(Box<Binary>),
+    /// See [`Call`]
+    Call(Box<Call>),
+    /// See [`Comma`]
+    Comma(Box<Comma>),
+    /// See [`Conditional`]
+    Conditional(Box<Conditional>),
+    /// Shared with the closures made from it
+    Function(Rc<Function>),
+    /// See [`Grouping`]
     Grouping(Box<Grouping>),
+    /// See [`Index`]
+    Index(Box<Index>),
+    /// The parts of an interpolated string, the literal text between the
+    /// holes is string literals
+    Interpolation(Vec<Expr>),
+    /// `[elements]`
+    List(Vec<Expr>),
+    /// See [`Literal`]
     Literal(Literal),
+    /// The keys and values of a map literal
+    Map(Vec<(Expr, Expr)>),
+    /// See [`SetIndex`]
+    SetIndex(Box<SetIndex>),
+    /// See [`Unary`]
     Unary(Box<Unary>),
+    /// See [`Update`]
+    Update(Box<Update>),
 }
 
 impl Expr {
+    /// `name = value`
+    pub fn from_assign(name: String, value: Self) -> Self {
+        Expr::Assign(Box::new(Assign { name, value }))
+    }
+    /// `left operator right`
     pub fn from_binary(left: Self, operator: BinOp, right: Self) -> Self {
         Expr::Binary(Box::new(Binary {
             left,
@@ -108,52 +410,166 @@ impl Expr {
             right,
         }))
     }
+    /// `callee(arguments)`
+    pub fn from_call(callee: Self, arguments: Vec<Self>) -> Self {
+        Self::Call(Box::new(Call { callee, arguments }))
+    }
+    /// `left, right`
+    pub fn from_comma(left: Self, right: Self) -> Self {
+        Self::Comma(Box::new(Comma { left, right }))
+    }
+    /// `condition ? then_branch : else_branch`
+    pub fn from_conditional(condition: Self, then_branch: Self, else_branch: Self) -> Self {
+        Self::Conditional(Box::new(Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+    /// `fun (params) { body }`
+    pub fn from_function(params: Vec<String>, body: Vec<Stmt>) -> Self {
+        Self::Function(Rc::new(Function { params, body }))
+    }
+    /// `object[index]`
+    pub fn from_index(object: Self, index: Self) -> Self {
+        Self::Index(Box::new(Index { object, index }))
+    }
+    /// `object[index] = value`
+    pub fn from_set_index(object: Self, index: Self, value: Self) -> Self {
+        Self::SetIndex(Box::new(SetIndex {
+            object,
+            index,
+            value,
+        }))
+    }
+    /// `(expression)`
     pub fn from_grouping(expression: Self) -> Self {
         Self::Grouping(Box::new(Grouping { expression }))
     }
+    /// `operator expression`
     pub fn from_unary(operator: UnOp, expression: Self) -> Self {
         Self::Unary(Box::new(Unary {
             operator,
             expression,
         }))
     }
+    /// `target op= value`, or `++` and `--` with a `value` of `1`
+    pub fn from_update(target: Target, operator: BinOp, value: Self, postfix: bool) -> Self {
+        Self::Update(Box::new(Update {
+            target,
+            operator,
+            value,
+            postfix,
+        }))
+    }
+    /// An int literal
+    pub fn from_int(n: i64) -> Self {
+        Self::Literal(Literal::Int(n))
+    }
+    /// A float literal
     pub fn from_number(n: f64) -> Self {
         Self::Literal(Literal::Number(n))
     }
+    /// A string literal without holes
     pub fn from_string(s: String) -> Self {
         Self::Literal(Literal::String(s))
     }
+    /// A variable read
     pub fn from_ident(id: String) -> Self {
         Self::Literal(Literal::Identifier(id))
     }
+    /// `true` or `false`
     pub fn from_bool(b: bool) -> Self {
         Self::Literal(if b { Literal::True } else { Literal::False })
     }
+    /// `nil`
     pub fn from_nil() -> Self {
         Self::Literal(Literal::Nil)
     }
+
+    /// Reads an expression from the s-expression it displays as, see
+    /// [`from_lisp`]
+    pub fn from_lisp(input: &str) -> Result<Self, AstError> {
+        reader::expression(input)
+    }
+}
+
+/// How deep a tree read from JSON or s-expressions can be, the parser
+/// counts its nesting more coarsely but no tree it makes is deeper
+const MAX_READ_DEPTH: usize = 1024;
+
+/// The statements as a JSON array of nodes, see [`from_json`]
+pub fn to_json(statements: &[Stmt]) -> String {
+    json::JsonAstPrinter.statements(statements).to_string()
+}
+
+/// Reads statements from what [`to_json`] writes
+///
+/// Each node is an object with a `"kind"` and the fields of that kind,
+/// statements may also have a `"span"` with the `"line"` and `"column"`
+/// they start at.
+pub fn from_json(input: &str) -> Result<Vec<Stmt>, AstError> {
+    json::JsonAstReader::new().statements(&Json::parse(input)?, "")
+}
+
+/// Reads statements from the s-expressions [`Stmt::display_lisp`] writes,
+/// one after another
+///
+/// The s-expressions have no spans, so every statement starts at 1:1.
+pub fn from_lisp(input: &str) -> Result<Vec<Stmt>, AstError> {
+    reader::statements(input)
+}
+
+/// The statements as a Graphviz `digraph`
+pub fn to_dot(statements: &[Stmt]) -> String {
+    dot::DotAstPrinter::new().program(statements)
 }
 
 pub mod visit;
 
+mod dot;
+mod json;
 mod printer;
+mod reader;
 
 #[cfg(test)]
 mod test {
-    use crate::syntax::Stmt;
+    use crate::{
+        syntax::{Stmt, StmtKind},
+        token::TokenMeta,
+    };
 
     use super::{printer::Lisp, BinOp, Expr, UnOp};
 
     #[test]
     fn debug_expression_tree() {
-        let e1 = Expr::from_unary(UnOp::Neg, Expr::from_number(123.));
+        let e1 = Expr::from_unary(UnOp::Neg, Expr::from_int(123));
         let e2 = Expr::from_grouping(Expr::from_number(45.67));
         let expr = Expr::from_binary(e1, BinOp::Mul, e2);
-        let stmt = Stmt::Expr(expr);
+        let stmt = Stmt::new(StmtKind::Expr(expr), TokenMeta { row: 1, col: 1 });
         let s_expr = Lisp::new(&stmt);
 
         let expected = "(* (- 123) (group 45.67))";
 
         assert_eq!(format!("{s_expr}"), expected);
     }
+
+    #[test]
+    fn debug_block_tree() {
+        let meta = TokenMeta { row: 1, col: 1 };
+        let assign = Expr::from_assign("a".into(), Expr::from_int(2));
+        let block = StmtKind::Block(vec![
+            Stmt::new(
+                StmtKind::Var("a".into(), Some(Expr::from_int(1))),
+                meta.clone(),
+            ),
+            Stmt::new(StmtKind::Var("b".into(), None), meta.clone()),
+            Stmt::new(StmtKind::Print(assign), meta.clone()),
+        ]);
+        let stmt = Stmt::new(block, meta);
+
+        let expected = "(block (var a 1) (var b) (print (= a 2)))";
+
+        assert_eq!(stmt.display_lisp().to_string(), expected);
+    }
 }
@@ -0,0 +1,254 @@
+//! The syntax tree as a Graphviz graph, to look at rather than read back
+//!
+//! Every node is labelled with its kind and anything that isn't a child,
+//! and every edge with the field the child is in.
+
+use std::{fmt::Write, rc::Rc};
+
+use super::{
+    visit::{ExprVisitor, StmtVisitor},
+    Assign, Binary, Call, Comma, Conditional, Expr, Function, Grouping, Import, Imported, Index,
+    Literal, SetIndex, Stmt, Target, Try, Unary, Update,
+};
+
+/// Writes the graph as it goes, each visit returns the id of its node
+pub struct DotAstPrinter {
+    out: String,
+    nodes: usize,
+}
+
+impl DotAstPrinter {
+    pub fn new() -> Self {
+        Self {
+            out: String::from("digraph ast {\n    node [shape=box];\n"),
+            nodes: 0,
+        }
+    }
+
+    /// The graph of a script, under a node for the whole of it
+    pub fn program(mut self, statements: &[Stmt]) -> String {
+        let program = self.node("program");
+        self.statements(program, statements);
+        self.out.push_str("}\n");
+        self.out
+    }
+
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let label = label
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        writeln!(self.out, "    n{id} [label=\"{label}\"];").unwrap();
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str) {
+        writeln!(self.out, "    n{from} -> n{to} [label=\"{label}\"];").unwrap();
+    }
+
+    fn child(&mut self, parent: usize, expr: &Expr, label: &str) {
+        let child = expr.accept(self);
+        self.edge(parent, child, label);
+    }
+
+    fn children(&mut self, parent: usize, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            self.child(parent, expr, &i.to_string());
+        }
+    }
+
+    fn statements(&mut self, parent: usize, statements: &[Stmt]) {
+        for (i, stmt) in statements.iter().enumerate() {
+            let child = stmt.accept(self);
+            self.edge(parent, child, &i.to_string());
+        }
+    }
+
+    /// A node for a list of statements that isn't a node of its own in the
+    /// tree, such as the body of a `try`
+    fn body(&mut self, parent: usize, statements: &[Stmt], label: &str) {
+        let body = self.node(label);
+        self.edge(parent, body, label);
+        self.statements(body, statements);
+    }
+}
+
+impl ExprVisitor<usize> for DotAstPrinter {
+    fn visit_assign(&mut self, assign: &Assign) -> usize {
+        let node = self.node(&format!("= {}", assign.name));
+        self.child(node, &assign.value, "value");
+        node
+    }
+
+    fn visit_binary(&mut self, binary: &Binary) -> usize {
+        let node = self.node(&binary.operator.to_string());
+        self.child(node, &binary.left, "left");
+        self.child(node, &binary.right, "right");
+        node
+    }
+
+    fn visit_call(&mut self, call: &Call) -> usize {
+        let node = self.node("call");
+        self.child(node, &call.callee, "callee");
+        self.children(node, &call.arguments);
+        node
+    }
+
+    fn visit_comma(&mut self, comma: &Comma) -> usize {
+        let node = self.node(",");
+        self.child(node, &comma.left, "left");
+        self.child(node, &comma.right, "right");
+        node
+    }
+
+    fn visit_conditional(&mut self, conditional: &Conditional) -> usize {
+        let node = self.node("?:");
+        self.child(node, &conditional.condition, "condition");
+        self.child(node, &conditional.then_branch, "then");
+        self.child(node, &conditional.else_branch, "else");
+        node
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) -> usize {
+        let node = self.node(&format!("fun ({})", function.params.join(", ")));
+        self.statements(node, &function.body);
+        node
+    }
+
+    fn visit_group(&mut self, group: &Grouping) -> usize {
+        let node = self.node("group");
+        self.child(node, &group.expression, "expression");
+        node
+    }
+
+    fn visit_index(&mut self, index: &Index) -> usize {
+        let node = self.node("index");
+        self.child(node, &index.object, "object");
+        self.child(node, &index.index, "index");
+        node
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> usize {
+        let node = self.node("interpolation");
+        self.children(node, parts);
+        node
+    }
+
+    fn visit_list(&mut self, elements: &[Expr]) -> usize {
+        let node = self.node("list");
+        self.children(node, elements);
+        node
+    }
+
+    fn visit_literal(&mut self, lit: &Literal) -> usize {
+        let label = match lit {
+            Literal::String(str) => format!("{str:?}"),
+            Literal::Identifier(name) => name.clone(),
+            Literal::Int(n) => n.to_string(),
+            Literal::Number(n) => format!("{n:?}"),
+            Literal::True => "true".into(),
+            Literal::False => "false".into(),
+            Literal::Nil => "nil".into(),
+        };
+        self.node(&label)
+    }
+
+    fn visit_map(&mut self, entries: &[(Expr, Expr)]) -> usize {
+        let node = self.node("map");
+        for (i, (key, value)) in entries.iter().enumerate() {
+            self.child(node, key, &format!("key {i}"));
+            self.child(node, value, &format!("value {i}"));
+        }
+        node
+    }
+
+    fn visit_set_index(&mut self, set: &SetIndex) -> usize {
+        let node = self.node("set index");
+        self.child(node, &set.object, "object");
+        self.child(node, &set.index, "index");
+        self.child(node, &set.value, "value");
+        node
+    }
+
+    fn visit_unary(&mut self, unary: &Unary) -> usize {
+        let node = self.node(&unary.operator.to_string());
+        self.child(node, &unary.expression, "expression");
+        node
+    }
+
+    fn visit_update(&mut self, update: &Update) -> usize {
+        let fix = if update.postfix { "postfix" } else { "prefix" };
+        let node = self.node(&format!("{}= {fix}", update.operator));
+        let target = match &update.target {
+            Target::Variable(name) => self.node(name),
+            Target::Index(index) => self.visit_index(index),
+        };
+        self.edge(node, target, "target");
+        self.child(node, &update.value, "value");
+        node
+    }
+}
+
+impl StmtVisitor<usize> for DotAstPrinter {
+    fn visit_expr(&mut self, expr: &Expr) -> usize {
+        let node = self.node("expression");
+        self.child(node, expr, "expression");
+        node
+    }
+
+    fn visit_print(&mut self, expr: &Expr) -> usize {
+        let node = self.node("print");
+        self.child(node, expr, "expression");
+        node
+    }
+
+    fn visit_var(&mut self, name: &str, initializer: Option<&Expr>) -> usize {
+        let node = self.node(&format!("var {name}"));
+        if let Some(initializer) = initializer {
+            self.child(node, initializer, "initializer");
+        }
+        node
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> usize {
+        let node = self.node("block");
+        self.statements(node, statements);
+        node
+    }
+
+    fn visit_return(&mut self, value: Option<&Expr>) -> usize {
+        let node = self.node("return");
+        if let Some(value) = value {
+            self.child(node, value, "value");
+        }
+        node
+    }
+
+    fn visit_throw(&mut self, value: &Expr) -> usize {
+        let node = self.node("throw");
+        self.child(node, value, "value");
+        node
+    }
+
+    fn visit_try(&mut self, statement: &Try) -> usize {
+        let node = self.node("try");
+        self.body(node, &statement.body, "body");
+        if let Some(catch) = &statement.catch {
+            self.body(node, &catch.body, &format!("catch ({})", catch.name));
+        }
+        if let Some(finally) = &statement.finally {
+            self.body(node, finally, "finally");
+        }
+        node
+    }
+
+    fn visit_import(&mut self, import: &Import) -> usize {
+        let label = match &import.names {
+            Imported::Module(name) => format!("import {:?} as {name}", import.path),
+            Imported::Names(names) => format!("from {:?} import {}", import.path, names.join(", ")),
+        };
+        self.node(&label)
+    }
+}
@@ -0,0 +1,776 @@
+//! The syntax tree as JSON, and back
+//!
+//! Every node is an object with its `kind` first, statements have a `span`
+//! with the line and column they start at, and the fields of each kind
+//! are named as in the tree. Operators are written as in the source. Ints
+//! too big for a JSON number to hold exactly are written as strings.
+
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    error::AstError,
+    json::Json,
+    stack::{StackBase, DEFAULT_STACK_BYTES},
+    token::TokenMeta,
+};
+
+use super::{
+    visit::{ExprVisitor, StmtVisitor},
+    Assign, BinOp, Binary, Call, Catch, Comma, Conditional, Expr, Function, Grouping, Import,
+    Imported, Index, Literal, SetIndex, Stmt, StmtKind, Target, Try, UnOp, Unary, Update,
+    MAX_READ_DEPTH,
+};
+
+/// The largest magnitude every int up to can be written as a JSON number
+const EXACT_INTS: i64 = 1 << 53;
+
+pub struct JsonAstPrinter;
+
+impl JsonAstPrinter {
+    pub fn statements(&mut self, statements: &[Stmt]) -> Json {
+        Json::Array(statements.iter().map(|stmt| self.statement(stmt)).collect())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Json {
+        let mut json = stmt.accept(self);
+        if let Json::Object(fields) = &mut json {
+            let span = Json::object([
+                ("line", stmt.meta.row.into()),
+                ("column", stmt.meta.col.into()),
+            ]);
+            fields.insert(1, ("span".into(), span));
+        }
+        json
+    }
+
+    fn expressions(&mut self, expressions: &[Expr]) -> Json {
+        Json::Array(expressions.iter().map(|expr| expr.accept(self)).collect())
+    }
+
+    fn optional(&mut self, expr: Option<&Expr>) -> Json {
+        expr.map_or(Json::Null, |expr| expr.accept(self))
+    }
+}
+
+fn names(names: &[String]) -> Json {
+    Json::Array(names.iter().map(|name| name.as_str().into()).collect())
+}
+
+impl ExprVisitor<Json> for JsonAstPrinter {
+    fn visit_assign(&mut self, assign: &Assign) -> Json {
+        Json::object([
+            ("kind", "Assign".into()),
+            ("name", assign.name.as_str().into()),
+            ("value", assign.value.accept(self)),
+        ])
+    }
+
+    fn visit_binary(&mut self, binary: &Binary) -> Json {
+        Json::object([
+            ("kind", "Binary".into()),
+            ("operator", binary.operator.to_string().into()),
+            ("left", binary.left.accept(self)),
+            ("right", binary.right.accept(self)),
+        ])
+    }
+
+    fn visit_call(&mut self, call: &Call) -> Json {
+        Json::object([
+            ("kind", "Call".into()),
+            ("callee", call.callee.accept(self)),
+            ("arguments", self.expressions(&call.arguments)),
+        ])
+    }
+
+    fn visit_comma(&mut self, comma: &Comma) -> Json {
+        Json::object([
+            ("kind", "Comma".into()),
+            ("left", comma.left.accept(self)),
+            ("right", comma.right.accept(self)),
+        ])
+    }
+
+    fn visit_conditional(&mut self, conditional: &Conditional) -> Json {
+        Json::object([
+            ("kind", "Conditional".into()),
+            ("condition", conditional.condition.accept(self)),
+            ("then_branch", conditional.then_branch.accept(self)),
+            ("else_branch", conditional.else_branch.accept(self)),
+        ])
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) -> Json {
+        Json::object([
+            ("kind", "Function".into()),
+            ("params", names(&function.params)),
+            ("body", self.statements(&function.body)),
+        ])
+    }
+
+    fn visit_group(&mut self, group: &Grouping) -> Json {
+        Json::object([
+            ("kind", "Grouping".into()),
+            ("expression", group.expression.accept(self)),
+        ])
+    }
+
+    fn visit_index(&mut self, index: &Index) -> Json {
+        Json::object([
+            ("kind", "Index".into()),
+            ("object", index.object.accept(self)),
+            ("index", index.index.accept(self)),
+        ])
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> Json {
+        Json::object([
+            ("kind", "Interpolation".into()),
+            ("parts", self.expressions(parts)),
+        ])
+    }
+
+    fn visit_list(&mut self, elements: &[Expr]) -> Json {
+        Json::object([
+            ("kind", "List".into()),
+            ("elements", self.expressions(elements)),
+        ])
+    }
+
+    fn visit_literal(&mut self, lit: &Literal) -> Json {
+        let (kind, value) = match lit {
+            Literal::String(str) => ("String", str.as_str().into()),
+            Literal::Identifier(name) => {
+                return Json::object([("kind", "Variable".into()), ("name", name.as_str().into())])
+            }
+            Literal::Int(n) if (-EXACT_INTS..=EXACT_INTS).contains(n) => {
+                ("Int", Json::Number(*n as f64))
+            }
+            Literal::Int(n) => ("Int", n.to_string().into()),
+            Literal::Number(n) if n.is_finite() => ("Number", Json::Number(*n)),
+            Literal::Number(n) => ("Number", n.to_string().into()),
+            Literal::True => ("Bool", true.into()),
+            Literal::False => ("Bool", false.into()),
+            Literal::Nil => return Json::object([("kind", "Nil".into())]),
+        };
+        Json::object([("kind", kind.into()), ("value", value)])
+    }
+
+    fn visit_map(&mut self, entries: &[(Expr, Expr)]) -> Json {
+        let entries = entries
+            .iter()
+            .map(|(key, value)| {
+                Json::object([("key", key.accept(self)), ("value", value.accept(self))])
+            })
+            .collect();
+        Json::object([("kind", "Map".into()), ("entries", Json::Array(entries))])
+    }
+
+    fn visit_set_index(&mut self, set: &SetIndex) -> Json {
+        Json::object([
+            ("kind", "SetIndex".into()),
+            ("object", set.object.accept(self)),
+            ("index", set.index.accept(self)),
+            ("value", set.value.accept(self)),
+        ])
+    }
+
+    fn visit_unary(&mut self, unary: &Unary) -> Json {
+        Json::object([
+            ("kind", "Unary".into()),
+            ("operator", unary.operator.to_string().into()),
+            ("expression", unary.expression.accept(self)),
+        ])
+    }
+
+    fn visit_update(&mut self, update: &Update) -> Json {
+        let target = match &update.target {
+            Target::Variable(name) => {
+                Json::object([("kind", "Variable".into()), ("name", name.as_str().into())])
+            }
+            Target::Index(index) => self.visit_index(index),
+        };
+        Json::object([
+            ("kind", "Update".into()),
+            ("target", target),
+            ("operator", update.operator.to_string().into()),
+            ("value", update.value.accept(self)),
+            ("postfix", update.postfix.into()),
+        ])
+    }
+}
+
+impl StmtVisitor<Json> for JsonAstPrinter {
+    fn visit_expr(&mut self, expr: &Expr) -> Json {
+        Json::object([
+            ("kind", "Expression".into()),
+            ("expression", expr.accept(self)),
+        ])
+    }
+
+    fn visit_print(&mut self, expr: &Expr) -> Json {
+        Json::object([("kind", "Print".into()), ("expression", expr.accept(self))])
+    }
+
+    fn visit_var(&mut self, name: &str, initializer: Option<&Expr>) -> Json {
+        Json::object([
+            ("kind", "Var".into()),
+            ("name", name.into()),
+            ("initializer", self.optional(initializer)),
+        ])
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> Json {
+        Json::object([
+            ("kind", "Block".into()),
+            ("statements", self.statements(statements)),
+        ])
+    }
+
+    fn visit_return(&mut self, value: Option<&Expr>) -> Json {
+        Json::object([("kind", "Return".into()), ("value", self.optional(value))])
+    }
+
+    fn visit_throw(&mut self, value: &Expr) -> Json {
+        Json::object([("kind", "Throw".into()), ("value", value.accept(self))])
+    }
+
+    fn visit_try(&mut self, statement: &Try) -> Json {
+        let catch = statement.catch.as_ref().map_or(Json::Null, |catch| {
+            Json::object([
+                ("name", catch.name.as_str().into()),
+                ("body", self.statements(&catch.body)),
+            ])
+        });
+        let finally = statement
+            .finally
+            .as_ref()
+            .map_or(Json::Null, |finally| self.statements(finally));
+        Json::object([
+            ("kind", "Try".into()),
+            ("body", self.statements(&statement.body)),
+            ("catch", catch),
+            ("finally", finally),
+        ])
+    }
+
+    fn visit_import(&mut self, import: &Import) -> Json {
+        let names = match &import.names {
+            Imported::Module(name) => ("module", name.as_str().into()),
+            Imported::Names(imported) => ("names", names(imported)),
+        };
+        Json::object([
+            ("kind", "Import".into()),
+            ("path", import.path.as_str().into()),
+            names,
+        ])
+    }
+}
+
+/// Rebuilds the tree from what [`JsonAstPrinter`] writes, errors say where
+/// in the document they are as a path such as `[0].expression.left`
+pub struct JsonAstReader {
+    /// How many statements and expressions the one being read is inside
+    depth: Cell<usize>,
+    /// Where reading started on the stack
+    stack: StackBase,
+}
+
+impl JsonAstReader {
+    pub fn new() -> Self {
+        Self {
+            depth: Cell::new(0),
+            stack: StackBase::here(),
+        }
+    }
+
+    pub fn statements(&self, json: &Json, path: &str) -> Result<Vec<Stmt>, AstError> {
+        let statements = json
+            .as_array()
+            .ok_or_else(|| AstError::expected(path, "an array of statements"))?;
+        statements
+            .iter()
+            .enumerate()
+            .map(|(i, stmt)| self.statement(stmt, &format!("{path}[{i}]")))
+            .collect()
+    }
+
+    /// Reads a statement or expression, failing if the tree gets deeper
+    /// than one the parser could have made or than the stack allows
+    fn nested<T>(
+        &self,
+        path: &str,
+        read: impl FnOnce() -> Result<T, AstError>,
+    ) -> Result<T, AstError> {
+        let depth = self.depth.get();
+        if depth >= MAX_READ_DEPTH || self.stack.used() > DEFAULT_STACK_BYTES {
+            return Err(AstError::NestingTooDeep(path.into()));
+        }
+        self.depth.set(depth + 1);
+        let result = read();
+        self.depth.set(depth);
+        result
+    }
+
+    fn statement(&self, json: &Json, path: &str) -> Result<Stmt, AstError> {
+        self.nested(path, || self.read_statement(json, path))
+    }
+
+    fn read_statement(&self, json: &Json, path: &str) -> Result<Stmt, AstError> {
+        // a tree built by another tool may not know where its statements are
+        let meta = match json.get("span") {
+            None => TokenMeta { row: 1, col: 1 },
+            Some(span) => TokenMeta {
+                row: usize_field(span, &format!("{path}.span"), "line")?,
+                col: usize_field(span, &format!("{path}.span"), "column")?,
+            },
+        };
+        // each kind is read by its own method to keep the frames the
+        // recursion goes through small
+        let kind = match kind(json, path)? {
+            "Expression" => StmtKind::Expr(self.expr_field(json, path, "expression")?),
+            "Print" => StmtKind::Print(self.expr_field(json, path, "expression")?),
+            "Var" => self.var(json, path)?,
+            "Block" => StmtKind::Block(self.statements_field(json, path, "statements")?),
+            "Return" => StmtKind::Return(self.optional(json, path, "value")?),
+            "Throw" => StmtKind::Throw(self.expr_field(json, path, "value")?),
+            "Try" => self.try_statement(json, path)?,
+            "Import" => import(json, path)?,
+            kind => return Err(AstError::UnknownKind(path.into(), kind.into())),
+        };
+        Ok(Stmt::new(kind, meta))
+    }
+
+    fn var(&self, json: &Json, path: &str) -> Result<StmtKind, AstError> {
+        Ok(StmtKind::Var(
+            str_field(json, path, "name")?,
+            self.optional(json, path, "initializer")?,
+        ))
+    }
+
+    fn try_statement(&self, json: &Json, path: &str) -> Result<StmtKind, AstError> {
+        let catch = match field(json, path, "catch")? {
+            Json::Null => None,
+            catch => {
+                let path = format!("{path}.catch");
+                Some(Catch {
+                    name: str_field(catch, &path, "name")?,
+                    body: self.statements_field(catch, &path, "body")?,
+                })
+            }
+        };
+        let finally = match field(json, path, "finally")? {
+            Json::Null => None,
+            finally => Some(self.statements(finally, &format!("{path}.finally"))?),
+        };
+        Ok(StmtKind::Try(Box::new(Try {
+            body: self.statements_field(json, path, "body")?,
+            catch,
+            finally,
+        })))
+    }
+
+    fn expr(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        self.nested(path, || self.read_expr(json, path))
+    }
+
+    fn read_expr(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        match kind(json, path)? {
+            "Assign" => self.assign(json, path),
+            "Binary" => self.binary(json, path),
+            "Call" => self.call(json, path),
+            "Comma" => self.comma(json, path),
+            "Conditional" => self.conditional(json, path),
+            "Function" => self.function(json, path),
+            "Grouping" => self.grouping(json, path),
+            "Index" => self.index(json, path),
+            "Interpolation" => self
+                .exprs_field(json, path, "parts")
+                .map(Expr::Interpolation),
+            "List" => self.exprs_field(json, path, "elements").map(Expr::List),
+            "Map" => self.map(json, path),
+            "SetIndex" => self.set_index(json, path),
+            "Unary" => self.unary(json, path),
+            "Update" => self.update(json, path),
+            "Variable" => str_field(json, path, "name").map(Expr::from_ident),
+            "String" => str_field(json, path, "value").map(Expr::from_string),
+            "Int" => int(json, path),
+            "Number" => number(json, path),
+            "Bool" => bool(json, path),
+            "Nil" => Ok(Expr::from_nil()),
+            kind => Err(AstError::UnknownKind(path.into(), kind.into())),
+        }
+    }
+
+    fn assign(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_assign(
+            str_field(json, path, "name")?,
+            self.expr_field(json, path, "value")?,
+        ))
+    }
+
+    fn binary(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_binary(
+            self.expr_field(json, path, "left")?,
+            binary_operator(json, path)?,
+            self.expr_field(json, path, "right")?,
+        ))
+    }
+
+    fn call(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_call(
+            self.expr_field(json, path, "callee")?,
+            self.exprs_field(json, path, "arguments")?,
+        ))
+    }
+
+    fn comma(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_comma(
+            self.expr_field(json, path, "left")?,
+            self.expr_field(json, path, "right")?,
+        ))
+    }
+
+    fn conditional(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_conditional(
+            self.expr_field(json, path, "condition")?,
+            self.expr_field(json, path, "then_branch")?,
+            self.expr_field(json, path, "else_branch")?,
+        ))
+    }
+
+    fn function(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_function(
+            names_field(json, path, "params")?,
+            self.statements_field(json, path, "body")?,
+        ))
+    }
+
+    fn grouping(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        self.expr_field(json, path, "expression")
+            .map(Expr::from_grouping)
+    }
+
+    fn index(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_index(
+            self.expr_field(json, path, "object")?,
+            self.expr_field(json, path, "index")?,
+        ))
+    }
+
+    fn map(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        let entries = field(json, path, "entries")?
+            .as_array()
+            .ok_or_else(|| AstError::expected(&format!("{path}.entries"), "an array"))?;
+        let entries = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let path = format!("{path}.entries[{i}]");
+                Ok((
+                    self.expr_field(entry, &path, "key")?,
+                    self.expr_field(entry, &path, "value")?,
+                ))
+            })
+            .collect::<Result<_, AstError>>()?;
+        Ok(Expr::Map(entries))
+    }
+
+    fn set_index(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        Ok(Expr::from_set_index(
+            self.expr_field(json, path, "object")?,
+            self.expr_field(json, path, "index")?,
+            self.expr_field(json, path, "value")?,
+        ))
+    }
+
+    fn unary(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        let operator = match str_field(json, path, "operator")?.as_str() {
+            "-" => UnOp::Neg,
+            "!" => UnOp::Not,
+            "~" => UnOp::BitNot,
+            _ => {
+                let path = format!("{path}.operator");
+                return Err(AstError::expected(&path, "a unary operator"));
+            }
+        };
+        Ok(Expr::from_unary(
+            operator,
+            self.expr_field(json, path, "expression")?,
+        ))
+    }
+
+    fn update(&self, json: &Json, path: &str) -> Result<Expr, AstError> {
+        let target_path = format!("{path}.target");
+        let target = match self.expr_field(json, path, "target")? {
+            Expr::Literal(Literal::Identifier(name)) => Target::Variable(name),
+            Expr::Index(index) => Target::Index(*index),
+            _ => return Err(AstError::expected(&target_path, "a Variable or Index")),
+        };
+        let postfix = field(json, path, "postfix")?
+            .as_bool()
+            .ok_or_else(|| AstError::expected(&format!("{path}.postfix"), "a boolean"))?;
+        Ok(Expr::from_update(
+            target,
+            binary_operator(json, path)?,
+            self.expr_field(json, path, "value")?,
+            postfix,
+        ))
+    }
+
+    fn expr_field(&self, json: &Json, path: &str, name: &str) -> Result<Expr, AstError> {
+        self.expr(field(json, path, name)?, &format!("{path}.{name}"))
+    }
+
+    fn optional(&self, json: &Json, path: &str, name: &str) -> Result<Option<Expr>, AstError> {
+        match json.get(name) {
+            None | Some(Json::Null) => Ok(None),
+            Some(expr) => self.expr(expr, &format!("{path}.{name}")).map(Some),
+        }
+    }
+
+    fn exprs_field(&self, json: &Json, path: &str, name: &str) -> Result<Vec<Expr>, AstError> {
+        let exprs = field(json, path, name)?.as_array().ok_or_else(|| {
+            AstError::expected(&format!("{path}.{name}"), "an array of expressions")
+        })?;
+        exprs
+            .iter()
+            .enumerate()
+            .map(|(i, expr)| self.expr(expr, &format!("{path}.{name}[{i}]")))
+            .collect()
+    }
+
+    fn statements_field(&self, json: &Json, path: &str, name: &str) -> Result<Vec<Stmt>, AstError> {
+        self.statements(field(json, path, name)?, &format!("{path}.{name}"))
+    }
+}
+
+fn import(json: &Json, path: &str) -> Result<StmtKind, AstError> {
+    let names = match json.get("module") {
+        Some(_) => Imported::Module(str_field(json, path, "module")?),
+        None => Imported::Names(names_field(json, path, "names")?),
+    };
+    Ok(StmtKind::Import(Import {
+        path: str_field(json, path, "path")?,
+        names,
+    }))
+}
+
+fn int(json: &Json, path: &str) -> Result<Expr, AstError> {
+    let n = match field(json, path, "value")? {
+        Json::Number(n) if n.fract() == 0.0 && n.abs() <= EXACT_INTS as f64 => Some(*n as i64),
+        Json::String(n) => n.parse().ok(),
+        _ => None,
+    };
+    n.map(Expr::from_int)
+        .ok_or_else(|| AstError::expected(&format!("{path}.value"), "an int"))
+}
+
+fn number(json: &Json, path: &str) -> Result<Expr, AstError> {
+    let n = match field(json, path, "value")? {
+        Json::Number(n) => Some(*n),
+        Json::String(n) => n.parse().ok(),
+        _ => None,
+    };
+    n.map(Expr::from_number)
+        .ok_or_else(|| AstError::expected(&format!("{path}.value"), "a number"))
+}
+
+fn bool(json: &Json, path: &str) -> Result<Expr, AstError> {
+    field(json, path, "value")?
+        .as_bool()
+        .map(Expr::from_bool)
+        .ok_or_else(|| AstError::expected(&format!("{path}.value"), "a boolean"))
+}
+
+fn field<'j>(json: &'j Json, path: &str, name: &str) -> Result<&'j Json, AstError> {
+    json.get(name)
+        .ok_or_else(|| AstError::expected(path, &format!("a field {name:?}")))
+}
+
+fn kind<'j>(json: &'j Json, path: &str) -> Result<&'j str, AstError> {
+    json.get("kind")
+        .and_then(Json::as_str)
+        .ok_or_else(|| AstError::expected(path, "a node with a \"kind\""))
+}
+
+fn str_field(json: &Json, path: &str, name: &str) -> Result<String, AstError> {
+    field(json, path, name)?
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| AstError::expected(&format!("{path}.{name}"), "a string"))
+}
+
+fn usize_field(json: &Json, path: &str, name: &str) -> Result<usize, AstError> {
+    field(json, path, name)?
+        .as_usize()
+        .ok_or_else(|| AstError::expected(&format!("{path}.{name}"), "a whole number"))
+}
+
+fn names_field(json: &Json, path: &str, name: &str) -> Result<Vec<String>, AstError> {
+    let names = field(json, path, name)?;
+    let path = format!("{path}.{name}");
+    let names = names
+        .as_array()
+        .ok_or_else(|| AstError::expected(&path, "an array of names"))?;
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            name.as_str()
+                .map(String::from)
+                .ok_or_else(|| AstError::expected(&format!("{path}[{i}]"), "a string"))
+        })
+        .collect()
+}
+
+fn binary_operator(json: &Json, path: &str) -> Result<BinOp, AstError> {
+    let operator = str_field(json, path, "operator")?;
+    BinOp::ALL
+        .into_iter()
+        .find(|op| op.to_string() == operator)
+        .ok_or_else(|| AstError::expected(&format!("{path}.operator"), "a binary operator"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path, thread};
+
+    use crate::{
+        error::AstError,
+        parser::LoxParser,
+        syntax::{from_json, to_json, Stmt},
+    };
+
+    fn lisp(statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|stmt| format!("{}\n", stmt.display_lisp()))
+            .collect()
+    }
+
+    fn round_trip(source: &str) {
+        let statements = LoxParser::new(source).parse().unwrap();
+        let json = to_json(&statements);
+        let read = from_json(&json).unwrap_or_else(|err| panic!("{err}: {json}"));
+        assert_eq!(statements, read);
+        assert_eq!(json, to_json(&read));
+        for (stmt, read) in statements.iter().zip(&read) {
+            assert_eq!(
+                (stmt.meta.row, stmt.meta.col),
+                (read.meta.row, read.meta.col)
+            );
+        }
+    }
+
+    fn examples(dir: &Path, scripts: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                examples(&path, scripts);
+            } else if path.extension().is_some_and(|extension| extension == "lox") {
+                scripts.push(fs::read_to_string(path).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn examples_round_trip() {
+        let mut scripts = vec![];
+        examples(Path::new("examples"), &mut scripts);
+        // the deepest example needs more stack than a test thread has
+        let parsed = thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || {
+                scripts
+                    .iter()
+                    .filter(|script| LoxParser::new(script).parse().is_ok())
+                    .inspect(|script| round_trip(script))
+                    .count()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(parsed > 40, "{parsed}");
+    }
+
+    #[test]
+    fn literals_round_trip() {
+        round_trip(
+            "print 9223372036854775807; print -9007199254740993; print 1e400; \
+             print 0.1; print \"a \\\"quoted\\\"\\n line\"; print \"{1} and {nil}\"; \
+             print [true, false, nil]; print {\"a\": 1, 2: [3]};",
+        );
+    }
+
+    #[test]
+    fn everything_round_trips() {
+        round_trip(
+            "var a = 1; a += 2; a--; ++a; var l = [1]; l[0] *= 2; l[0]++;
+             var f = fun (x, y) { return x ~/ y, x % y; };
+             print f(7, 2) == 1 or !(a < 3 and a >= -1) ? ~a : a << 1;
+             { try { throw \"no\"; } catch (e) { print e; } finally { print 1; } }
+             f = fun () { try { print 1; } finally { return; } };
+             import \"a.lox\" as a; from \"b.lox\" import b, c;",
+        );
+    }
+
+    #[test]
+    fn spans_are_optional() {
+        let statements =
+            from_json(r#"[{"kind":"Print","expression":{"kind":"Int","value":1}}]"#).unwrap();
+        assert_eq!("(print 1)\n", lisp(&statements));
+        assert_eq!((1, 1), (statements[0].meta.row, statements[0].meta.col));
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let cases = [
+            ("[", "Unexpected end of JSON input"),
+            ("{}", "Expected an array of statements at the top level"),
+            ("[{}]", "Expected a node with a \"kind\" at [0]"),
+            (
+                r#"[{"kind":"Print"}]"#,
+                "Expected a field \"expression\" at [0]",
+            ),
+            (
+                r#"[{"kind":"Print","expression":{"kind":"Binary","operator":"+",
+                    "left":{"kind":"Int","value":1},"right":{"kind":"Loop"}}}]"#,
+                "Unknown kind \"Loop\" at [0].expression.right",
+            ),
+            (
+                r#"[{"kind":"Expression","expression":{"kind":"Binary","operator":"=",
+                    "left":{"kind":"Nil"},"right":{"kind":"Nil"}}}]"#,
+                "Expected a binary operator at [0].expression.operator",
+            ),
+            (
+                r#"[{"kind":"Print","expression":{"kind":"Int","value":1.5}}]"#,
+                "Expected an int at [0].expression.value",
+            ),
+            (
+                r#"[{"kind":"Block","statements":[{"kind":"Var","name":1}]}]"#,
+                "Expected a string at [0].statements[0].name",
+            ),
+            (
+                r#"[{"kind":"Expression","expression":{"kind":"Update","operator":"+",
+                    "target":{"kind":"Nil"},"value":{"kind":"Int","value":1},"postfix":true}}]"#,
+                "Expected a Variable or Index at [0].expression.target",
+            ),
+        ];
+        for (json, message) in cases {
+            let err: AstError = from_json(json).unwrap_err();
+            assert_eq!(message, err.to_string(), "{json}");
+        }
+    }
+
+    #[test]
+    fn deep_trees_are_errors() {
+        let depth = 100_000;
+        let json = format!(
+            r#"[{{"kind":"Print","expression":{}{{"kind":"Nil"}}{}}}]"#,
+            r#"{"kind":"Grouping","expression":"#.repeat(depth),
+            "}".repeat(depth)
+        );
+        let err = from_json(&json).unwrap_err().to_string();
+        assert!(err.starts_with("Nesting too deep at "), "{err}");
+    }
+}
@@ -1,8 +1,12 @@
-use std::fmt::{self, Display, Formatter, Write};
+use std::{
+    fmt::{self, Debug, Display, Formatter, Write},
+    rc::Rc,
+};
 
 use super::{
     visit::{ExprVisitor, StmtVisitor},
-    Binary, Expr, Grouping, Literal, Stmt, Unary,
+    Assign, Binary, Call, Comma, Conditional, Expr, Function, Grouping, Import, Imported, Index,
+    Literal, SetIndex, Stmt, Target, Try, Unary, Update,
 };
 
 pub struct LispAstPrinter<'a, 'b> {
@@ -10,6 +14,14 @@ pub struct LispAstPrinter<'a, 'b> {
 }
 
 impl<'b> ExprVisitor<fmt::Result> for LispAstPrinter<'_, 'b> {
+    fn visit_assign(&mut self, assign: &Assign) -> fmt::Result {
+        self.f.write_str("(= ")?;
+        self.f.write_str(&assign.name)?;
+        self.f.write_char(' ')?;
+        assign.value.accept(&mut *self)?;
+        self.f.write_char(')')
+    }
+
     fn visit_binary(&mut self, binary: &Binary) -> fmt::Result {
         self.f.write_char('(')?;
         Display::fmt(&binary.operator, self.f)?;
@@ -20,6 +32,45 @@ impl<'b> ExprVisitor<fmt::Result> for LispAstPrinter<'_, 'b> {
         self.f.write_char(')')
     }
 
+    fn visit_call(&mut self, call: &Call) -> fmt::Result {
+        self.f.write_str("(call ")?;
+        call.callee.accept(&mut *self)?;
+        for argument in &call.arguments {
+            self.f.write_char(' ')?;
+            argument.accept(&mut *self)?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_comma(&mut self, comma: &Comma) -> fmt::Result {
+        self.f.write_str("(, ")?;
+        comma.left.accept(&mut *self)?;
+        self.f.write_char(' ')?;
+        comma.right.accept(&mut *self)?;
+        self.f.write_char(')')
+    }
+
+    fn visit_conditional(&mut self, conditional: &Conditional) -> fmt::Result {
+        self.f.write_str("(?: ")?;
+        conditional.condition.accept(&mut *self)?;
+        self.f.write_char(' ')?;
+        conditional.then_branch.accept(&mut *self)?;
+        self.f.write_char(' ')?;
+        conditional.else_branch.accept(&mut *self)?;
+        self.f.write_char(')')
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) -> fmt::Result {
+        self.f.write_str("(fun (")?;
+        self.f.write_str(&function.params.join(" "))?;
+        self.f.write_char(')')?;
+        for stmt in &function.body {
+            self.f.write_char(' ')?;
+            stmt.accept(&mut *self)?;
+        }
+        self.f.write_char(')')
+    }
+
     fn visit_group(&mut self, group: &Grouping) -> fmt::Result {
         self.f.write_char('(')?;
         self.f.write_str("group ")?;
@@ -27,17 +78,66 @@ impl<'b> ExprVisitor<fmt::Result> for LispAstPrinter<'_, 'b> {
         self.f.write_char(')')
     }
 
+    fn visit_index(&mut self, index: &Index) -> fmt::Result {
+        self.f.write_str("(index ")?;
+        index.object.accept(&mut *self)?;
+        self.f.write_char(' ')?;
+        index.index.accept(&mut *self)?;
+        self.f.write_char(')')
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> fmt::Result {
+        self.f.write_str("(interpolate")?;
+        for part in parts {
+            self.f.write_char(' ')?;
+            part.accept(&mut *self)?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_list(&mut self, elements: &[Expr]) -> fmt::Result {
+        self.f.write_str("(list")?;
+        for element in elements {
+            self.f.write_char(' ')?;
+            element.accept(&mut *self)?;
+        }
+        self.f.write_char(')')
+    }
+
     fn visit_literal(&mut self, lit: &Literal) -> fmt::Result {
         match lit {
             Literal::String(str) => self.f.write_fmt(format_args!("{str:?}")),
             Literal::Identifier(id) => self.f.write_fmt(format_args!("`{id}`")),
-            Literal::Number(n) => Display::fmt(n, self.f),
+            Literal::Int(n) => Display::fmt(n, self.f),
+            // always with a fraction or exponent, to read back as a float
+            Literal::Number(n) => Debug::fmt(n, self.f),
             Literal::True => self.f.write_str("true"),
             Literal::False => self.f.write_str("false"),
             Literal::Nil => self.f.write_str("nil"),
         }
     }
 
+    fn visit_map(&mut self, entries: &[(Expr, Expr)]) -> fmt::Result {
+        self.f.write_str("(map")?;
+        for (key, value) in entries {
+            self.f.write_char(' ')?;
+            key.accept(&mut *self)?;
+            self.f.write_char(' ')?;
+            value.accept(&mut *self)?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_set_index(&mut self, set: &SetIndex) -> fmt::Result {
+        self.f.write_str("(= (index ")?;
+        set.object.accept(&mut *self)?;
+        self.f.write_char(' ')?;
+        set.index.accept(&mut *self)?;
+        self.f.write_str(") ")?;
+        set.value.accept(&mut *self)?;
+        self.f.write_char(')')
+    }
+
     fn visit_unary(&mut self, unary: &Unary) -> fmt::Result {
         self.f.write_char('(')?;
         Display::fmt(&unary.operator, self.f)?;
@@ -45,6 +145,21 @@ impl<'b> ExprVisitor<fmt::Result> for LispAstPrinter<'_, 'b> {
         unary.expression.accept(&mut *self)?;
         self.f.write_char(')')
     }
+
+    fn visit_update(&mut self, update: &Update) -> fmt::Result {
+        // `++x` is `(+= x 1)` while `x++` is `(post+= x 1)`
+        self.f
+            .write_str(if update.postfix { "(post" } else { "(" })?;
+        Display::fmt(&update.operator, self.f)?;
+        self.f.write_str("= ")?;
+        match &update.target {
+            Target::Variable(name) => self.f.write_str(name)?,
+            Target::Index(index) => self.visit_index(index)?,
+        }
+        self.f.write_char(' ')?;
+        update.value.accept(&mut *self)?;
+        self.f.write_char(')')
+    }
 }
 
 impl<'b> StmtVisitor<fmt::Result> for LispAstPrinter<'_, 'b> {
@@ -58,18 +173,81 @@ impl<'b> StmtVisitor<fmt::Result> for LispAstPrinter<'_, 'b> {
         self.f.write_char(')')
     }
 
-    fn visit_var(&mut self, name: &str, expr: &Expr) -> fmt::Result {
+    fn visit_var(&mut self, name: &str, initializer: Option<&Expr>) -> fmt::Result {
         self.f.write_str("(var ")?;
         self.f.write_str(name)?;
-        expr.accept(self)?;
+        if let Some(expr) = initializer {
+            self.f.write_char(' ')?;
+            expr.accept(self)?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> fmt::Result {
+        self.f.write_str("(block")?;
+        for stmt in statements {
+            self.f.write_char(' ')?;
+            stmt.accept(self)?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_return(&mut self, value: Option<&Expr>) -> fmt::Result {
+        self.f.write_str("(return")?;
+        if let Some(expr) = value {
+            self.f.write_char(' ')?;
+            expr.accept(self)?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_throw(&mut self, value: &Expr) -> fmt::Result {
+        self.f.write_str("(throw ")?;
+        value.accept(self)?;
         self.f.write_char(')')
     }
+
+    fn visit_try(&mut self, statement: &Try) -> fmt::Result {
+        self.f.write_str("(try ")?;
+        self.visit_block(&statement.body)?;
+        if let Some(catch) = &statement.catch {
+            self.f.write_str(" (catch ")?;
+            self.f.write_str(&catch.name)?;
+            self.f.write_char(' ')?;
+            self.visit_block(&catch.body)?;
+            self.f.write_char(')')?;
+        }
+        if let Some(finally) = &statement.finally {
+            self.f.write_str(" (finally ")?;
+            self.visit_block(finally)?;
+            self.f.write_char(')')?;
+        }
+        self.f.write_char(')')
+    }
+
+    fn visit_import(&mut self, import: &Import) -> fmt::Result {
+        match &import.names {
+            Imported::Module(name) => self
+                .f
+                .write_fmt(format_args!("(import {:?} {name})", import.path)),
+            Imported::Names(names) => {
+                self.f.write_fmt(format_args!("(from {:?}", import.path))?;
+                for name in names {
+                    self.f.write_char(' ')?;
+                    self.f.write_str(name)?;
+                }
+                self.f.write_char(')')
+            }
+        }
+    }
 }
 
+/// Displays a statement as the s-expression [`super::from_lisp`] reads
 #[derive(Debug, Clone, Copy)]
 pub struct Lisp<'b>(&'b Stmt);
 
 impl<'b> Lisp<'b> {
+    /// Displays `stmt`, as [`Stmt::display_lisp`] does
     pub fn new(stmt: &'b Stmt) -> Self {
         Self(stmt)
     }
@@ -0,0 +1,651 @@
+//! Reads the s-expressions [`LispAstPrinter`](super::printer) writes back
+//! into a tree
+//!
+//! The printer doesn't write spans, so every statement starts at 1:1. It
+//! writes an update with `<` or `>` of an index the same as a comparison,
+//! which is what it reads as, but the parser never makes those.
+
+use crate::{
+    error::AstError,
+    stack::{StackBase, DEFAULT_STACK_BYTES},
+    token::TokenMeta,
+};
+
+use super::{
+    BinOp, Catch, Expr, Import, Imported, Index, Stmt, StmtKind, Target, Try, UnOp, MAX_READ_DEPTH,
+};
+
+/// A form as written, before it is known what it is
+enum Sexp {
+    List(Vec<Sexp>, usize),
+    /// A name, number or keyword
+    Atom(String, usize),
+    String(String, usize),
+    /// A variable, written in backticks
+    Identifier(String, usize),
+}
+
+impl Sexp {
+    fn at(&self) -> usize {
+        match self {
+            Sexp::List(_, at)
+            | Sexp::Atom(_, at)
+            | Sexp::String(_, at)
+            | Sexp::Identifier(_, at) => *at,
+        }
+    }
+
+    /// The name at the head of a list
+    fn head(&self) -> Option<&str> {
+        match self {
+            Sexp::List(items, _) => match items.first() {
+                Some(Sexp::Atom(head, _)) => Some(head),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn expected(&self, what: &str) -> AstError {
+        AstError::ExpectedAt(self.at(), what.into())
+    }
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    index: usize,
+    /// How many lists the reader is inside
+    depth: usize,
+    /// Where reading started on the stack
+    stack: StackBase,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            index: 0,
+            depth: 0,
+            stack: StackBase::here(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.index..].chars().next()
+    }
+
+    fn advance(&mut self) -> Result<char, AstError> {
+        let c = self.peek().ok_or(AstError::UnexpectedEnd)?;
+        self.index += c.len_utf8();
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.advance().unwrap();
+        }
+    }
+
+    /// Every form up to the end of the input
+    fn forms(&mut self) -> Result<Vec<Sexp>, AstError> {
+        let mut forms = vec![];
+        self.skip_whitespace();
+        while self.peek().is_some() {
+            forms.push(self.form()?);
+            self.skip_whitespace();
+        }
+        Ok(forms)
+    }
+
+    fn form(&mut self) -> Result<Sexp, AstError> {
+        self.skip_whitespace();
+        let at = self.index;
+        match self.advance()? {
+            '(' => {
+                if self.depth >= MAX_READ_DEPTH || self.stack.used() > DEFAULT_STACK_BYTES {
+                    return Err(AstError::NestingTooDeepAt(at));
+                }
+                self.depth += 1;
+                let items = self.list();
+                self.depth -= 1;
+                Ok(Sexp::List(items?, at))
+            }
+            ')' => Err(AstError::ExpectedAt(at, "a form".into())),
+            '"' => self.string().map(|str| Sexp::String(str, at)),
+            '`' => {
+                let start = self.index;
+                while self.advance()? != '`' {}
+                Ok(Sexp::Identifier(
+                    self.input[start..self.index - 1].into(),
+                    at,
+                ))
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && !"()\"`".contains(c))
+                {
+                    self.advance()?;
+                }
+                Ok(Sexp::Atom(self.input[at..self.index].into(), at))
+            }
+        }
+    }
+
+    /// The forms in a list, after the opening parenthesis
+    fn list(&mut self) -> Result<Vec<Sexp>, AstError> {
+        let mut items = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+                self.advance()?;
+                return Ok(items);
+            }
+            items.push(self.form()?);
+        }
+    }
+
+    /// A string as Rust's `Debug` writes it, after the opening quote
+    fn string(&mut self) -> Result<String, AstError> {
+        let mut str = String::new();
+        loop {
+            match self.advance()? {
+                '"' => return Ok(str),
+                '\\' => {
+                    let at = self.index - 1;
+                    let c = match self.advance()? {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        '0' => '\0',
+                        c @ ('\\' | '"' | '\'') => c,
+                        'u' => self.unicode(at)?,
+                        _ => return Err(AstError::ExpectedAt(at, "an escape".into())),
+                    };
+                    str.push(c);
+                }
+                c => str.push(c),
+            }
+        }
+    }
+
+    /// The `{hex}` of a `\u{hex}` escape
+    fn unicode(&mut self, at: usize) -> Result<char, AstError> {
+        let invalid = || AstError::ExpectedAt(at, "an escape".into());
+        if self.advance()? != '{' {
+            return Err(invalid());
+        }
+        let start = self.index;
+        while self.advance()? != '}' {}
+        u32::from_str_radix(&self.input[start..self.index - 1], 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(invalid)
+    }
+}
+
+/// Reads statements, one form each
+pub fn statements(input: &str) -> Result<Vec<Stmt>, AstError> {
+    let forms = Reader::new(input).forms()?;
+    let tree = Tree::new();
+    forms.iter().map(|form| tree.statement(form)).collect()
+}
+
+/// Reads a single expression
+pub fn expression(input: &str) -> Result<Expr, AstError> {
+    let mut forms = Reader::new(input).forms()?;
+    match forms.len() {
+        0 => Err(AstError::UnexpectedEnd),
+        1 => Tree::new().expr(&forms.remove(0)),
+        _ => Err(forms[1].expected("the end of the input")),
+    }
+}
+
+/// Builds the tree from forms no deeper than the reader allows, failing
+/// before that takes more stack than there is
+struct Tree {
+    stack: StackBase,
+}
+
+impl Tree {
+    fn new() -> Self {
+        Self {
+            stack: StackBase::here(),
+        }
+    }
+
+    fn nested<T>(
+        &self,
+        form: &Sexp,
+        build: impl FnOnce() -> Result<T, AstError>,
+    ) -> Result<T, AstError> {
+        if self.stack.used() > DEFAULT_STACK_BYTES {
+            return Err(AstError::NestingTooDeepAt(form.at()));
+        }
+        build()
+    }
+
+    fn statement(&self, form: &Sexp) -> Result<Stmt, AstError> {
+        self.nested(form, || self.build_statement(form))
+    }
+
+    // each form with more to it than a line is built by its own method to
+    // keep the frames the recursion goes through small
+    fn build_statement(&self, form: &Sexp) -> Result<Stmt, AstError> {
+        let kind = match form.head() {
+            Some("print") => StmtKind::Print(self.expr(&arguments(form, 1)?[0])?),
+            Some("var") => self.var(form)?,
+            Some("block") => StmtKind::Block(self.body(form)?),
+            Some("return") => {
+                let args = arguments_between(form, 0, 1)?;
+                StmtKind::Return(self.optional(args.first())?)
+            }
+            Some("throw") => StmtKind::Throw(self.expr(&arguments(form, 1)?[0])?),
+            Some("try") => StmtKind::Try(Box::new(self.try_statement(form)?)),
+            Some("import" | "from") => import(form)?,
+            _ => StmtKind::Expr(self.expr(form)?),
+        };
+        Ok(Stmt::new(kind, TokenMeta { row: 1, col: 1 }))
+    }
+
+    fn var(&self, form: &Sexp) -> Result<StmtKind, AstError> {
+        let args = arguments_between(form, 1, 2)?;
+        Ok(StmtKind::Var(name(&args[0])?, self.optional(args.get(1))?))
+    }
+
+    /// `(try (block ...) (catch name (block ...)) (finally (block ...)))`
+    fn try_statement(&self, form: &Sexp) -> Result<Try, AstError> {
+        let args = arguments_between(form, 2, 3)?;
+        let mut statement = Try {
+            body: self.block(&args[0])?,
+            catch: None,
+            finally: None,
+        };
+        for (i, clause) in args[1..].iter().enumerate() {
+            match clause.head() {
+                Some("catch") if i == 0 => {
+                    let args = arguments(clause, 2)?;
+                    statement.catch = Some(Catch {
+                        name: name(&args[0])?,
+                        body: self.block(&args[1])?,
+                    });
+                }
+                Some("finally") if statement.finally.is_none() => {
+                    statement.finally = Some(self.block(&arguments(clause, 1)?[0])?);
+                }
+                _ => return Err(clause.expected("a catch or finally clause")),
+            }
+        }
+        Ok(statement)
+    }
+
+    fn expr(&self, form: &Sexp) -> Result<Expr, AstError> {
+        self.nested(form, || self.build_expr(form))
+    }
+
+    fn build_expr(&self, form: &Sexp) -> Result<Expr, AstError> {
+        let items = match form {
+            Sexp::List(items, _) => items,
+            Sexp::Atom(atom, _) => return literal(form, atom),
+            Sexp::String(str, _) => return Ok(Expr::from_string(str.clone())),
+            Sexp::Identifier(name, _) => return Ok(Expr::from_ident(name.clone())),
+        };
+        let Some(head) = form.head() else {
+            return Err(form.expected("an expression"));
+        };
+        let args = &items[1..];
+        match head {
+            "=" => self.assign(form),
+            "call" => self.call(form),
+            "," => self.comma(form),
+            "?:" => self.conditional(form),
+            "fun" => self.function(form),
+            "group" => self.grouping(form),
+            "index" => self.index(form).map(|index| Expr::Index(Box::new(index))),
+            "interpolate" => self.exprs(args).map(Expr::Interpolation),
+            "list" => self.exprs(args).map(Expr::List),
+            "map" => self.map(form, args),
+            _ => self.operation(form, head, args),
+        }
+    }
+
+    fn optional(&self, form: Option<&Sexp>) -> Result<Option<Expr>, AstError> {
+        form.map(|form| self.expr(form)).transpose()
+    }
+
+    fn exprs(&self, forms: &[Sexp]) -> Result<Vec<Expr>, AstError> {
+        forms.iter().map(|form| self.expr(form)).collect()
+    }
+
+    /// `(= name value)` or `(= (index object index) value)`
+    fn assign(&self, form: &Sexp) -> Result<Expr, AstError> {
+        let args = arguments(form, 2)?;
+        match &args[0] {
+            Sexp::Atom(name, _) if is_name(&args[0]) => {
+                Ok(Expr::from_assign(name.clone(), self.expr(&args[1])?))
+            }
+            target => {
+                let index = self.index(target)?;
+                Ok(Expr::from_set_index(
+                    index.object,
+                    index.index,
+                    self.expr(&args[1])?,
+                ))
+            }
+        }
+    }
+
+    fn call(&self, form: &Sexp) -> Result<Expr, AstError> {
+        let args = arguments_between(form, 1, usize::MAX)?;
+        Ok(Expr::from_call(
+            self.expr(&args[0])?,
+            self.exprs(&args[1..])?,
+        ))
+    }
+
+    fn comma(&self, form: &Sexp) -> Result<Expr, AstError> {
+        let args = arguments(form, 2)?;
+        Ok(Expr::from_comma(self.expr(&args[0])?, self.expr(&args[1])?))
+    }
+
+    fn conditional(&self, form: &Sexp) -> Result<Expr, AstError> {
+        let args = arguments(form, 3)?;
+        Ok(Expr::from_conditional(
+            self.expr(&args[0])?,
+            self.expr(&args[1])?,
+            self.expr(&args[2])?,
+        ))
+    }
+
+    /// `(fun (params) statements...)`
+    fn function(&self, form: &Sexp) -> Result<Expr, AstError> {
+        let args = arguments_between(form, 1, usize::MAX)?;
+        let Sexp::List(params, _) = &args[0] else {
+            return Err(args[0].expected("a list of parameters"));
+        };
+        let params = params.iter().map(name).collect::<Result<_, _>>()?;
+        let body = args[1..]
+            .iter()
+            .map(|form| self.statement(form))
+            .collect::<Result<_, _>>()?;
+        Ok(Expr::from_function(params, body))
+    }
+
+    fn grouping(&self, form: &Sexp) -> Result<Expr, AstError> {
+        self.expr(&arguments(form, 1)?[0]).map(Expr::from_grouping)
+    }
+
+    fn map(&self, form: &Sexp, args: &[Sexp]) -> Result<Expr, AstError> {
+        if !args.len().is_multiple_of(2) {
+            return Err(form.expected("a value for every key"));
+        }
+        let entries = args
+            .chunks(2)
+            .map(|entry| Ok((self.expr(&entry[0])?, self.expr(&entry[1])?)))
+            .collect::<Result<_, AstError>>()?;
+        Ok(Expr::Map(entries))
+    }
+
+    /// A unary, binary or update form, the head is its operator
+    fn operation(&self, form: &Sexp, head: &str, args: &[Sexp]) -> Result<Expr, AstError> {
+        let unary = match head {
+            "-" => Some(UnOp::Neg),
+            "!" => Some(UnOp::Not),
+            "~" => Some(UnOp::BitNot),
+            _ => None,
+        };
+        if let (Some(operator), [expression]) = (unary, args) {
+            return Ok(Expr::from_unary(operator, self.expr(expression)?));
+        }
+        // an operand is never a bare name, so `(<= x 1)` is an update of `x`
+        if let (Some(operator), [left, right]) = (binary_operator(head), args) {
+            if !is_name(left) {
+                return Ok(Expr::from_binary(
+                    self.expr(left)?,
+                    operator,
+                    self.expr(right)?,
+                ));
+            }
+        }
+        let (postfix, update) = match head.strip_prefix("post") {
+            Some(update) => (true, update),
+            None => (false, head),
+        };
+        let Some(operator) = update.strip_suffix('=').and_then(binary_operator) else {
+            return Err(AstError::UnknownForm(form.at(), head.into()));
+        };
+        let args = arguments(form, 2)?;
+        let target = match &args[0] {
+            Sexp::Atom(name, _) if is_name(&args[0]) => Target::Variable(name.clone()),
+            target => Target::Index(self.index(target)?),
+        };
+        Ok(Expr::from_update(
+            target,
+            operator,
+            self.expr(&args[1])?,
+            postfix,
+        ))
+    }
+
+    /// `(index object index)`
+    fn index(&self, form: &Sexp) -> Result<Index, AstError> {
+        if form.head() != Some("index") {
+            return Err(form.expected("a name or an index"));
+        }
+        let args = arguments(form, 2)?;
+        Ok(Index {
+            object: self.expr(&args[0])?,
+            index: self.expr(&args[1])?,
+        })
+    }
+
+    /// The statements in a `(block ...)`
+    fn block(&self, form: &Sexp) -> Result<Vec<Stmt>, AstError> {
+        if form.head() != Some("block") {
+            return Err(form.expected("a block"));
+        }
+        self.body(form)
+    }
+
+    /// The statements after the head of a form
+    fn body(&self, form: &Sexp) -> Result<Vec<Stmt>, AstError> {
+        let Sexp::List(items, _) = form else {
+            unreachable!("only lists have heads")
+        };
+        items[1..].iter().map(|form| self.statement(form)).collect()
+    }
+}
+
+/// `(import "path" name)` or `(from "path" names...)`
+fn import(form: &Sexp) -> Result<StmtKind, AstError> {
+    if form.head() == Some("import") {
+        let args = arguments(form, 2)?;
+        return Ok(StmtKind::Import(Import {
+            path: string(&args[0])?,
+            names: Imported::Module(name(&args[1])?),
+        }));
+    }
+    let args = arguments_between(form, 1, usize::MAX)?;
+    Ok(StmtKind::Import(Import {
+        path: string(&args[0])?,
+        names: Imported::Names(args[1..].iter().map(name).collect::<Result<_, _>>()?),
+    }))
+}
+
+/// Whether a form is a bare name, rather than a literal such as `1`
+fn is_name(form: &Sexp) -> bool {
+    matches!(form, Sexp::Atom(atom, _) if literal(form, atom).is_err())
+}
+
+fn binary_operator(operator: &str) -> Option<BinOp> {
+    BinOp::ALL.into_iter().find(|op| op.to_string() == operator)
+}
+
+fn literal(form: &Sexp, atom: &str) -> Result<Expr, AstError> {
+    match atom {
+        "true" => return Ok(Expr::from_bool(true)),
+        "false" => return Ok(Expr::from_bool(false)),
+        "nil" => return Ok(Expr::from_nil()),
+        _ => {}
+    }
+    if let Ok(n) = atom.parse() {
+        return Ok(Expr::from_int(n));
+    }
+    // numbers are written with a fraction or an exponent, or as `inf` or `NaN`
+    match atom.parse() {
+        Ok(n) if atom.contains(['.', 'e', 'i', 'N']) => Ok(Expr::from_number(n)),
+        _ => Err(form.expected("an expression")),
+    }
+}
+
+fn name(form: &Sexp) -> Result<String, AstError> {
+    match form {
+        Sexp::Atom(name, _) => Ok(name.clone()),
+        _ => Err(form.expected("a name")),
+    }
+}
+
+fn string(form: &Sexp) -> Result<String, AstError> {
+    match form {
+        Sexp::String(str, _) => Ok(str.clone()),
+        _ => Err(form.expected("a string")),
+    }
+}
+
+/// The `n` forms after the head of a list
+fn arguments(form: &Sexp, n: usize) -> Result<&[Sexp], AstError> {
+    arguments_between(form, n, n)
+}
+
+fn arguments_between(form: &Sexp, least: usize, most: usize) -> Result<&[Sexp], AstError> {
+    let Sexp::List(items, _) = form else {
+        return Err(form.expected("a list"));
+    };
+    let args = &items[1..];
+    if (least..=most).contains(&args.len()) {
+        Ok(args)
+    } else {
+        let head = form.head().unwrap_or_default();
+        let what = match (least, most) {
+            (least, most) if least == most => format!("{least} arguments to {head}"),
+            (least, usize::MAX) => format!("at least {least} arguments to {head}"),
+            (least, most) => format!("{least} to {most} arguments to {head}"),
+        };
+        Err(form.expected(&what))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::AstError,
+        parser::LoxParser,
+        syntax::{from_lisp, BinOp, Expr, Target, UnOp},
+    };
+
+    #[test]
+    fn read_expression() {
+        let e1 = Expr::from_unary(UnOp::Neg, Expr::from_int(123));
+        let e2 = Expr::from_grouping(Expr::from_number(45.67));
+        let expected = Expr::from_binary(e1, BinOp::Mul, e2);
+
+        assert_eq!(Ok(expected), Expr::from_lisp("(* (- 123) (group 45.67))"));
+    }
+
+    #[test]
+    fn read_updates_apart_from_comparisons() {
+        let x = || Target::Variable("x".into());
+        let cases = [
+            (
+                "(+= x 1)",
+                Expr::from_update(x(), BinOp::Add, Expr::from_int(1), false),
+            ),
+            (
+                "(post-= x 1)",
+                Expr::from_update(x(), BinOp::Sub, Expr::from_int(1), true),
+            ),
+            (
+                "(<= x 1)",
+                Expr::from_update(x(), BinOp::Lt, Expr::from_int(1), false),
+            ),
+            (
+                "(<= `x` 1)",
+                Expr::from_binary(Expr::from_ident("x".into()), BinOp::Le, Expr::from_int(1)),
+            ),
+        ];
+        for (lisp, expected) in cases {
+            assert_eq!(Ok(expected), Expr::from_lisp(lisp), "{lisp}");
+        }
+    }
+
+    #[test]
+    fn printed_statements_read_back() {
+        let source = "var a = 1; var b; a = b = 2; a += 1; a--; ++a;
+            var l = [1, \"two\\n\\\"quoted\\\"\\t\\u{1F600}\", 3.0, 1e400, nil];
+            l[0] = {\"k\": true, 2: false}; l[0]++; l[1] *= -2;
+            var f = fun (x, y) { return x ~/ y, x % y; };
+            var g = (x) => x ** 2;
+            print f(7, 2) == 1 or !(a < 3 and a >= -1) ? ~a : a << 1 | 2 ^ 3 & 4;
+            print \"{a} and {l[0]}\";
+            { try { throw \"no\"; } catch (e) { print e; } finally { print 1; } }
+            f = fun () { try { return; } finally { print 2; } };
+            import \"a.lox\" as a; from \"b.lox\" import b, c;";
+        let statements = LoxParser::new(source).parse().unwrap();
+        let lisp: String = statements
+            .iter()
+            .map(|stmt| format!("{}\n", stmt.display_lisp()))
+            .collect();
+
+        assert_eq!(Ok(statements), from_lisp(&lisp));
+    }
+
+    #[test]
+    fn invalid_forms() {
+        let cases = [
+            ("(print 1", AstError::UnexpectedEnd),
+            (")", AstError::ExpectedAt(0, "a form".into())),
+            ("(loop 1)", AstError::UnknownForm(0, "loop".into())),
+            (
+                "(print 1 2)",
+                AstError::ExpectedAt(0, "1 arguments to print".into()),
+            ),
+            (
+                "(var)",
+                AstError::ExpectedAt(0, "1 to 2 arguments to var".into()),
+            ),
+            ("(print x)", AstError::ExpectedAt(7, "an expression".into())),
+            ("(var `a`)", AstError::ExpectedAt(5, "a name".into())),
+            (
+                "(= (list) 1)",
+                AstError::ExpectedAt(3, "a name or an index".into()),
+            ),
+            (
+                "(map 1)",
+                AstError::ExpectedAt(0, "a value for every key".into()),
+            ),
+            (
+                "(try (block) (print 1))",
+                AstError::ExpectedAt(13, "a catch or finally clause".into()),
+            ),
+            ("\"\\q\"", AstError::ExpectedAt(1, "an escape".into())),
+        ];
+        for (lisp, expected) in cases {
+            assert_eq!(Err(expected), from_lisp(lisp), "{lisp}");
+        }
+    }
+
+    #[test]
+    fn deep_forms_are_errors() {
+        let depth = 100_000;
+        let lisp = format!(
+            "(print {}nil{})",
+            "(group ".repeat(depth),
+            ")".repeat(depth)
+        );
+
+        assert!(matches!(
+            from_lisp(&lisp),
+            Err(AstError::NestingTooDeepAt(_))
+        ));
+    }
+}
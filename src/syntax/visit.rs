@@ -1,37 +1,103 @@
-use super::{Binary, Expr, Grouping, Literal, Stmt, Unary};
+//! Walking a syntax tree with one method for each kind of node
 
+use std::rc::Rc;
+
+use super::{
+    Assign, Binary, Call, Comma, Conditional, Expr, Function, Grouping, Import, Index, Literal,
+    SetIndex, Stmt, StmtKind, Try, Unary, Update,
+};
+
+/// One method for each kind of [`Expr`], called by [`Expr::accept`]
 pub trait ExprVisitor<R> {
+    /// Visits [`Expr::Assign`]
+    fn visit_assign(&mut self, assign: &Assign) -> R;
+    /// Visits [`Expr::Binary`]
     fn visit_binary(&mut self, binary: &Binary) -> R;
+    /// Visits [`Expr::Call`]
+    fn visit_call(&mut self, call: &Call) -> R;
+    /// Visits [`Expr::Comma`]
+    fn visit_comma(&mut self, comma: &Comma) -> R;
+    /// Visits [`Expr::Conditional`]
+    fn visit_conditional(&mut self, conditional: &Conditional) -> R;
+    /// Visits [`Expr::Function`]
+    fn visit_function(&mut self, function: &Rc<Function>) -> R;
+    /// Visits [`Expr::Grouping`]
     fn visit_group(&mut self, group: &Grouping) -> R;
+    /// Visits [`Expr::Index`]
+    fn visit_index(&mut self, index: &Index) -> R;
+    /// Visits [`Expr::Interpolation`]
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> R;
+    /// Visits [`Expr::List`]
+    fn visit_list(&mut self, elements: &[Expr]) -> R;
+    /// Visits [`Expr::Literal`]
     fn visit_literal(&mut self, lit: &Literal) -> R;
+    /// Visits [`Expr::Map`]
+    fn visit_map(&mut self, entries: &[(Expr, Expr)]) -> R;
+    /// Visits [`Expr::SetIndex`]
+    fn visit_set_index(&mut self, set: &SetIndex) -> R;
+    /// Visits [`Expr::Unary`]
     fn visit_unary(&mut self, unary: &Unary) -> R;
+    /// Visits [`Expr::Update`]
+    fn visit_update(&mut self, update: &Update) -> R;
 }
 
+/// One method for each kind of [`Stmt`], called by [`Stmt::accept`]
 pub trait StmtVisitor<R> {
+    /// Visits [`StmtKind::Expr`]
     fn visit_expr(&mut self, expr: &Expr) -> R;
+    /// Visits [`StmtKind::Print`]
     fn visit_print(&mut self, expr: &Expr) -> R;
-    fn visit_var(&mut self, name: &str, expr: &Expr) -> R;
+    /// Visits [`StmtKind::Var`]
+    fn visit_var(&mut self, name: &str, initializer: Option<&Expr>) -> R;
+    /// Visits [`StmtKind::Block`]
+    fn visit_block(&mut self, statements: &[Stmt]) -> R;
+    /// Visits [`StmtKind::Return`]
+    fn visit_return(&mut self, value: Option<&Expr>) -> R;
+    /// Visits [`StmtKind::Throw`]
+    fn visit_throw(&mut self, value: &Expr) -> R;
+    /// Visits [`StmtKind::Try`]
+    fn visit_try(&mut self, statement: &Try) -> R;
+    /// Visits [`StmtKind::Import`]
+    fn visit_import(&mut self, import: &Import) -> R;
 }
 
 impl Stmt {
+    /// The visitor pattern for this struct, implement the trait
+    /// [`StmtVisitor<R>`] and pass it to this method.
     pub fn accept<R, V: StmtVisitor<R>>(&self, visitor: &mut V) -> R {
-        match self {
-            Stmt::Expr(expr) => visitor.visit_expr(expr),
-            Stmt::Print(expr) => visitor.visit_print(expr),
-            Stmt::Var(name, expr) => visitor.visit_var(name, expr),
+        match &self.kind {
+            StmtKind::Expr(expr) => visitor.visit_expr(expr),
+            StmtKind::Print(expr) => visitor.visit_print(expr),
+            StmtKind::Var(name, initializer) => visitor.visit_var(name, initializer.as_ref()),
+            StmtKind::Block(statements) => visitor.visit_block(statements),
+            StmtKind::Return(value) => visitor.visit_return(value.as_ref()),
+            StmtKind::Throw(value) => visitor.visit_throw(value),
+            StmtKind::Try(statement) => visitor.visit_try(statement),
+            StmtKind::Import(import) => visitor.visit_import(import),
         }
     }
 }
 
 impl Expr {
     /// The visitor pattern for this enum, implement the trait
-    /// [`Visitor<R>`] and pass it to this method.
+    /// [`ExprVisitor<R>`] and pass it to this method.
     pub fn accept<R, V: ExprVisitor<R>>(&self, visitor: &mut V) -> R {
         match self {
+            Expr::Assign(assign) => visitor.visit_assign(assign),
             Expr::Binary(binary) => visitor.visit_binary(binary),
+            Expr::Call(call) => visitor.visit_call(call),
+            Expr::Comma(comma) => visitor.visit_comma(comma),
+            Expr::Conditional(conditional) => visitor.visit_conditional(conditional),
+            Expr::Function(function) => visitor.visit_function(function),
             Expr::Grouping(group) => visitor.visit_group(group),
+            Expr::Index(index) => visitor.visit_index(index),
+            Expr::Interpolation(parts) => visitor.visit_interpolation(parts),
+            Expr::List(elements) => visitor.visit_list(elements),
             Expr::Literal(lit) => visitor.visit_literal(lit),
+            Expr::Map(entries) => visitor.visit_map(entries),
+            Expr::SetIndex(set) => visitor.visit_set_index(set),
             Expr::Unary(unary) => visitor.visit_unary(unary),
+            Expr::Update(update) => visitor.visit_update(update),
         }
     }
 }
@@ -0,0 +1,287 @@
+//! `lox test` runs every `.lox` file under a directory and checks what it
+//! prints against the expectations written in its comments:
+//!
+//! - `// expect: text` the next line the script prints
+//! - `// expect runtime error: message` the script stops with this error
+//! - `// [line N] Error: message` compiling fails on line `N`, and a plain
+//!   `// Error: message` means the line the comment is on
+//!
+//! Each script runs in its own `lox` process, so the exit code and
+//! everything written to stdout and stderr are checked as a user sees them.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use crate::Error;
+
+#[derive(Debug, Default, PartialEq)]
+struct Expectations {
+    output: Vec<String>,
+    runtime_error: Option<String>,
+    /// The line and the text the message should contain
+    compile_errors: Vec<(usize, String)>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut expectations = Self::default();
+        let mut string = None;
+        for (row, line) in source.lines().enumerate() {
+            // the comment can say something before the marker
+            let Some(comment) = comment(line, &mut string) else {
+                continue;
+            };
+            let markers = ["// expect", "// Error", "// [line "];
+            let Some(at) = markers
+                .iter()
+                .filter_map(|marker| comment.find(marker))
+                .min()
+            else {
+                continue;
+            };
+            let comment = &comment[at + "// ".len()..];
+            if let Some(output) = comment.strip_prefix("expect: ") {
+                expectations.output.push(output.into());
+            } else if let Some(message) = comment.strip_prefix("expect runtime error: ") {
+                expectations.runtime_error = Some(message.into());
+            } else if let Some(message) = comment
+                .strip_prefix("Error")
+                .filter(|rest| rest.is_empty() || rest.starts_with(':'))
+            {
+                expectations
+                    .compile_errors
+                    .push((row + 1, error_message(message)));
+            } else if let Some(rest) = comment.strip_prefix("[line ") {
+                let Some((n, message)) = rest.split_once("] Error") else {
+                    continue;
+                };
+                if let Ok(n) = n.parse() {
+                    expectations
+                        .compile_errors
+                        .push((n, error_message(message)));
+                }
+            }
+        }
+        expectations
+    }
+
+    /// Describes every way the output differs from the expectations
+    fn check(&self, status: Option<i32>, stdout: &str, stderr: &str) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        let output: Vec<_> = stdout.lines().collect();
+        if output != self.output {
+            failures.push(format!("  output:\n{}", diff(&self.output, &output)));
+        }
+
+        let errors: Vec<_> = stderr.lines().collect();
+        let expected_status = if !self.compile_errors.is_empty() {
+            for (line, message) in &self.compile_errors {
+                if !errors
+                    .iter()
+                    .any(|error| compile_error_matches(error, *line, message))
+                {
+                    failures.push(format!(
+                        "  expected a compile error on line {line}: {message:?}\n  got {stderr:?}"
+                    ));
+                }
+            }
+            65
+        } else if let Some(message) = &self.runtime_error {
+            if errors.first() != Some(&message.as_str()) {
+                failures.push(format!(
+                    "  expected runtime error {message:?}\n  got {stderr:?}"
+                ));
+            }
+            70
+        } else {
+            if !errors.is_empty() {
+                failures.push(format!("  unexpected error {stderr:?}"));
+            }
+            0
+        };
+        if status != Some(expected_status) {
+            failures.push(format!(
+                "  expected exit code {expected_status}, got {status:?}"
+            ));
+        }
+
+        failures
+    }
+}
+
+/// The message of an error annotation, without the `: ` that follows `Error`
+/// The line from the `//` that starts its comment, skipping any in strings.
+/// `string` is whether a string is open and if so whether it is raw, kept
+/// from one line to the next since strings can span lines.
+fn comment<'a>(line: &'a str, string: &mut Option<bool>) -> Option<&'a str> {
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match (c, *string) {
+            ('"', None) => *string = Some(line[..i].ends_with('r')),
+            ('"', Some(_)) => *string = None,
+            ('\\', Some(false)) => {
+                chars.next();
+            }
+            ('/', None) if chars.peek().is_some_and(|&(_, c)| c == '/') => return Some(&line[i..]),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn error_message(annotation: &str) -> String {
+    annotation
+        .strip_prefix(':')
+        .unwrap_or(annotation)
+        .trim()
+        .into()
+}
+
+/// Compile errors are reported as `[row:col] Kind: message`
+fn compile_error_matches(error: &str, line: usize, message: &str) -> bool {
+    let Some((position, rest)) = error.strip_prefix('[').and_then(|e| e.split_once(']')) else {
+        return false;
+    };
+    let row = position.split(':').next().and_then(|row| row.parse().ok());
+    let actual = rest.split_once(": ").map_or("", |(_, message)| message);
+    row == Some(line) && actual.contains(message)
+}
+
+fn diff(expected: &[String], actual: &[&str]) -> String {
+    let mut diff = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => diff.push_str(&format!("      {e}\n")),
+            (e, a) => {
+                if let Some(e) = e {
+                    diff.push_str(&format!("    - {e}\n"));
+                }
+                if let Some(a) = a {
+                    diff.push_str(&format!("    + {a}\n"));
+                }
+            }
+        }
+    }
+    diff
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn run_file(lox: &Path, file: &Path) -> Result<Vec<String>, Error> {
+    let source = fs::read_to_string(file)?;
+    let expectations = Expectations::parse(&source);
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = Command::new(lox).arg(file).output()?;
+    Ok(expectations.check(
+        status.code(),
+        &String::from_utf8_lossy(&stdout),
+        &String::from_utf8_lossy(&stderr),
+    ))
+}
+
+/// Runs the scripts under `dir` and reports on each of them
+pub fn run<P: AsRef<Path>>(dir: P) -> Result<(), Error> {
+    let mut files = Vec::new();
+    collect(dir.as_ref(), &mut files)?;
+    files.sort();
+
+    let lox = std::env::current_exe()?;
+    let mut failed = 0;
+    for file in &files {
+        let failures = run_file(&lox, file)?;
+        if failures.is_empty() {
+            println!("PASS {}", file.display());
+        } else {
+            failed += 1;
+            println!("FAIL {}", file.display());
+            for failure in failures {
+                println!("{failure}");
+            }
+        }
+    }
+    println!("\n{} passed, {failed} failed", files.len() - failed);
+
+    if failed > 0 {
+        Err(Error::TestsFailed(failed))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Expectations;
+
+    #[test]
+    fn parse_expectations() {
+        let source = r#"
+print 1; // expect: 1
+print "a" + "b"; // expect: ab
+print "// not a marker"; // expect: // not a marker
+print x; // expect runtime error: Undefined variable 'x'
+print 2 3; // Error: expected ';'
+// [line 9] Error
+print "// expect: x"; // Errors are ignored here
+print "a \" // expect: b";
+print r"\" // expect: c";
+print "two
+// expect: d
+lines"; // expect: two
+"#;
+
+        let expected = Expectations {
+            output: vec![
+                "1".into(),
+                "ab".into(),
+                "// not a marker".into(),
+                "c\";".into(),
+                "two".into(),
+            ],
+            runtime_error: Some("Undefined variable 'x'".into()),
+            compile_errors: vec![(6, "expected ';'".into()), (9, "".into())],
+        };
+
+        assert_eq!(expected, Expectations::parse(source));
+    }
+
+    #[test]
+    fn check_output_diff() {
+        let expectations = Expectations::parse("// expect: a\n// expect: b\n// expect: c");
+
+        let failures = expectations.check(Some(0), "a\nx\nc\nd\n", "");
+
+        let expected = "  output:\n      a\n    - b\n    + x\n      c\n    + d\n";
+        assert_eq!([expected], &failures[..]);
+    }
+
+    #[test]
+    fn check_errors() {
+        let compile = Expectations::parse("print 1 2; // Error: expected ';'");
+        let stderr = "[1:9] ParserError: expected ';' after statement\n";
+        assert!(compile.check(Some(65), "", stderr).is_empty());
+        assert_eq!(2, compile.check(Some(70), "", "[2:1] oops\n").len());
+
+        let runtime = Expectations::parse("// expect runtime error: Undefined variable 'x'");
+        assert!(runtime
+            .check(Some(70), "", "Undefined variable 'x'\n")
+            .is_empty());
+        assert_eq!(1, runtime.check(Some(70), "", "Interrupted\n").len());
+    }
+}
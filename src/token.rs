@@ -6,7 +6,10 @@ pub enum Structure {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
     SemiColon,
 }
@@ -18,7 +21,10 @@ impl Display for Structure {
             Structure::RightParen => f.write_char(')'),
             Structure::LeftBrace => f.write_char('{'),
             Structure::RightBrace => f.write_char('}'),
+            Structure::LeftBracket => f.write_char('['),
+            Structure::RightBracket => f.write_char(']'),
             Structure::Comma => f.write_char(','),
+            Structure::Colon => f.write_char(':'),
             Structure::Dot => f.write_char('.'),
             Structure::SemiColon => f.write_char(';'),
         }
@@ -29,12 +35,42 @@ impl Display for Structure {
 pub enum Operator {
     /// "-"
     Minus,
+    /// "-="
+    MinusEqual,
+    /// "--"
+    MinusMinus,
     /// "+"
     Plus,
+    /// "+="
+    PlusEqual,
+    /// "++"
+    PlusPlus,
     /// "/"
     Slash,
+    /// "/="
+    SlashEqual,
     /// "*"
     Star,
+    /// "*="
+    StarEqual,
+    /// "**"
+    StarStar,
+    /// "%"
+    Percent,
+    /// "~/"
+    TildeSlash,
+    /// "&"
+    Ampersand,
+    /// "|"
+    Pipe,
+    /// "^"
+    Caret,
+    /// "~"
+    Tilde,
+    /// "<<"
+    LessLess,
+    /// ">>"
+    GreaterGreater,
     /// "!"
     Bang,
     /// "!="
@@ -43,6 +79,8 @@ pub enum Operator {
     Equal,
     /// "=="
     EqualEqual,
+    /// "=>"
+    Arrow,
     /// ">"
     Greater,
     /// ">="
@@ -51,6 +89,8 @@ pub enum Operator {
     Less,
     /// "<="
     LessEqual,
+    /// "?"
+    Question,
     /// "and"
     And,
     /// "or"
@@ -61,17 +101,34 @@ impl Display for Operator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Operator::Minus => f.write_char('-'),
+            Operator::MinusEqual => f.write_str("-="),
+            Operator::MinusMinus => f.write_str("--"),
             Operator::Plus => f.write_char('+'),
+            Operator::PlusEqual => f.write_str("+="),
+            Operator::PlusPlus => f.write_str("++"),
             Operator::Slash => f.write_char('/'),
+            Operator::SlashEqual => f.write_str("/="),
             Operator::Star => f.write_char('*'),
+            Operator::StarEqual => f.write_str("*="),
+            Operator::StarStar => f.write_str("**"),
+            Operator::Percent => f.write_char('%'),
+            Operator::TildeSlash => f.write_str("~/"),
+            Operator::Ampersand => f.write_char('&'),
+            Operator::Pipe => f.write_char('|'),
+            Operator::Caret => f.write_char('^'),
+            Operator::Tilde => f.write_char('~'),
+            Operator::LessLess => f.write_str("<<"),
+            Operator::GreaterGreater => f.write_str(">>"),
             Operator::Bang => f.write_char('!'),
             Operator::BangEqual => f.write_str("!="),
             Operator::Equal => f.write_char('='),
             Operator::EqualEqual => f.write_str("=="),
+            Operator::Arrow => f.write_str("=>"),
             Operator::Greater => f.write_char('>'),
             Operator::GreaterEqual => f.write_str(">="),
             Operator::Less => f.write_char('<'),
             Operator::LessEqual => f.write_str("<="),
+            Operator::Question => f.write_char('?'),
             Operator::And => f.write_str("and"),
             Operator::Or => f.write_str("or"),
         }
@@ -81,14 +138,19 @@ impl Display for Operator {
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Keyword {
     Class,
+    Catch,
     Else,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Print,
     Return,
     Super,
     This,
+    Throw,
+    Try,
     Var,
     While,
 }
@@ -97,14 +159,19 @@ impl Display for Keyword {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Keyword::Class => f.write_str("class"),
+            Keyword::Catch => f.write_str("catch"),
             Keyword::Else => f.write_str("else"),
+            Keyword::Finally => f.write_str("finally"),
             Keyword::Fun => f.write_str("fun"),
             Keyword::For => f.write_str("for"),
             Keyword::If => f.write_str("if"),
+            Keyword::Import => f.write_str("import"),
             Keyword::Print => f.write_str("print"),
             Keyword::Return => f.write_str("return"),
             Keyword::Super => f.write_str("super"),
             Keyword::This => f.write_str("this"),
+            Keyword::Throw => f.write_str("throw"),
+            Keyword::Try => f.write_str("try"),
             Keyword::Var => f.write_str("var"),
             Keyword::While => f.write_str("while"),
         }
@@ -115,6 +182,9 @@ impl Display for Keyword {
 pub enum TokenKind {
     Identifier(String),
     String(String),
+    /// The part of a string before a `${`
+    Interpolation(String),
+    Int(i64),
     Number(f64),
     Literal(Literal),
     Structure(Structure),
@@ -139,9 +209,12 @@ impl Display for Literal {
     }
 }
 
+/// Where a token starts in the source, counting from one
 #[derive(Debug, Clone)]
 pub struct TokenMeta {
+    /// The line
     pub row: usize,
+    /// The character in the line
     pub col: usize,
 }
 
@@ -1,11 +1,468 @@
-use std::fmt::Display;
+//! The values scripts compute with, and that a host hands to them
 
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    mem,
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    environment::Environment,
+    error::InterpreterError,
+    interpreter::{Console, Interpreter},
+    syntax,
+};
+
+/// A value a script can hold in a variable
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Value {
+    /// An immutable string
     String(String),
+    /// A 64-bit int, arithmetic on them fails rather than overflowing
+    Int(i64),
+    /// A float
     Number(f64),
+    /// `true` or `false`
     Bool(bool),
+    /// `nil`, the value of a variable declared without one
     Nil,
+    /// A list shared by every copy of it
+    List(List),
+    /// A map shared by every copy of it
+    Map(Map),
+    /// A function written in Rust, such as `len`
+    NativeFunction(NativeFunction),
+    /// A function written in Lox
+    Function(Function),
+}
+
+/// A shared, mutable list, copying the value aliases the same elements
+#[derive(Clone, Default)]
+pub struct List(Rc<RefCell<Vec<Value>>>);
+
+impl List {
+    /// A new list of `elements`, not shared with any other
+    pub fn new(elements: Vec<Value>) -> Self {
+        Self(Rc::new(RefCell::new(elements)))
+    }
+
+    /// How many elements the list has
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Whether the list has no elements
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// The element at `index`, which must be an int below the length
+    pub fn get(&self, index: &Value) -> Result<Value, InterpreterError> {
+        let elements = self.0.borrow();
+        let i = Self::position(index, elements.len())?;
+        Ok(elements[i].clone())
+    }
+
+    /// Replaces the element at `index`, which must be an int below the
+    /// length
+    pub fn set(&self, index: &Value, value: Value) -> Result<(), InterpreterError> {
+        let mut elements = self.0.borrow_mut();
+        let i = Self::position(index, elements.len())?;
+        elements[i] = value;
+        Ok(())
+    }
+
+    /// A copy of the elements, which doesn't change with the list
+    pub fn elements(&self) -> Vec<Value> {
+        self.0.borrow().clone()
+    }
+
+    /// Adds `value` to the end
+    pub fn push(&self, value: Value) {
+        self.0.borrow_mut().push(value);
+    }
+
+    /// Removes the last element, failing if there is none
+    pub fn pop(&self) -> Result<Value, InterpreterError> {
+        self.0.borrow_mut().pop().ok_or(InterpreterError::EmptyList)
+    }
+
+    /// Inserts before `index`, which may also be the length to append
+    pub fn insert(&self, index: &Value, value: Value) -> Result<(), InterpreterError> {
+        let mut elements = self.0.borrow_mut();
+        // the end of the list is a position too
+        let i = match Self::position(index, elements.len() + 1) {
+            Err(InterpreterError::IndexOutOfBounds(i, _)) => {
+                return Err(InterpreterError::IndexOutOfBounds(i, elements.len()))
+            }
+            i => i?,
+        };
+        elements.insert(i, value);
+        Ok(())
+    }
+
+    /// Removes the element at `index` and moves the ones after it down
+    pub fn remove(&self, index: &Value) -> Result<Value, InterpreterError> {
+        let mut elements = self.0.borrow_mut();
+        let i = Self::position(index, elements.len())?;
+        Ok(elements.remove(i))
+    }
+
+    /// Checks that `index` is an int below `len`
+    fn position(index: &Value, len: usize) -> Result<usize, InterpreterError> {
+        let Value::Int(n) = *index else {
+            return Err(InterpreterError::InvalidIndex(index.clone()));
+        };
+        match usize::try_from(n) {
+            Err(_) => Err(InterpreterError::NegativeIndex(n)),
+            Ok(i) if i >= len => Err(InterpreterError::IndexOutOfBounds(i, len)),
+            Ok(i) => Ok(i),
+        }
+    }
+
+    fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.0).cast()
+    }
+}
+
+/// Lists are the same when they are the same list, not when they hold the
+/// same elements
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for List {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "List({})", Value::List(self.clone()))
+    }
+}
+
+/// The values that can be map keys
+///
+/// Numbers are compared by value rather than by bits, so a float that is
+/// a whole number is the same key as the int, and `-0.0` is the same key
+/// as `0`. NaN is never equal to itself, but every NaN is the same key so
+/// that it can be looked up again.
+#[derive(Debug, Clone)]
+pub(crate) enum Key {
+    String(String),
+    Int(i64),
+    /// Never a whole number that fits in an int
+    Number(f64),
+    Bool(bool),
+    Nil,
+}
+
+/// The floats that can be converted to an int without saturating
+const INT_RANGE: std::ops::Range<f64> = i64::MIN as f64..-(i64::MIN as f64);
+
+/// The int `n` is exactly equal to, if there is one
+pub(crate) fn exact_int(n: f64) -> Option<i64> {
+    (n.fract() == 0.0 && INT_RANGE.contains(&n)).then_some(n as i64)
+}
+
+/// How `int` orders against `float` without rounding either, `None` when
+/// `float` is NaN
+pub(crate) fn cmp_int_float(int: i64, float: f64) -> Option<Ordering> {
+    if float.is_nan() {
+        None
+    } else if float < INT_RANGE.start {
+        Some(Ordering::Greater)
+    } else if float >= INT_RANGE.end {
+        Some(Ordering::Less)
+    } else {
+        // the whole part fits an int, the fraction breaks a tie
+        match int.cmp(&(float.trunc() as i64)) {
+            Ordering::Equal => 0.0.partial_cmp(&float.fract()),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl Key {
+    fn heap_size(&self) -> usize {
+        match self {
+            Key::String(str) => str.capacity(),
+            _ => 0,
+        }
+    }
+
+    fn number_bits(n: f64) -> u64 {
+        if n.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            n.to_bits()
+        }
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Key::String(left), Key::String(right)) => left == right,
+            (Key::Int(left), Key::Int(right)) => left == right,
+            (Key::Number(left), Key::Number(right)) => {
+                Self::number_bits(*left) == Self::number_bits(*right)
+            }
+            (Key::Bool(left), Key::Bool(right)) => left == right,
+            (Key::Nil, Key::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Key::String(str) => str.hash(state),
+            Key::Int(n) => n.hash(state),
+            Key::Number(n) => Self::number_bits(*n).hash(state),
+            Key::Bool(b) => b.hash(state),
+            Key::Nil => {}
+        }
+    }
+}
+
+impl TryFrom<&Value> for Key {
+    type Error = InterpreterError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(str) => Ok(Key::String(str.clone())),
+            Value::Int(n) => Ok(Key::Int(*n)),
+            Value::Number(n) => Ok(exact_int(*n).map_or(Key::Number(*n), Key::Int)),
+            Value::Bool(b) => Ok(Key::Bool(*b)),
+            Value::Nil => Ok(Key::Nil),
+            value => Err(InterpreterError::InvalidKey(value.clone())),
+        }
+    }
+}
+
+impl From<Key> for Value {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::String(str) => Value::String(str),
+            Key::Int(n) => Value::Int(n),
+            Key::Number(n) => Value::Number(n),
+            Key::Bool(b) => Value::Bool(b),
+            Key::Nil => Value::Nil,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Where each key is in `entries`
+    positions: HashMap<Key, usize>,
+    entries: Vec<(Key, Value)>,
+}
+
+/// A shared, mutable map that remembers the order its keys were inserted in
+#[derive(Clone, Default)]
+pub struct Map(Rc<RefCell<Entries>>);
+
+impl Map {
+    /// How many keys the map has
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    /// Whether the map has no keys
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().entries.is_empty()
+    }
+
+    /// The value of `key`, failing if the map doesn't have it
+    pub fn get(&self, key: &Value) -> Result<Value, InterpreterError> {
+        let map = self.0.borrow();
+        match map.positions.get(&Key::try_from(key)?) {
+            Some(&i) => Ok(map.entries[i].1.clone()),
+            None => Err(InterpreterError::UndefinedKey(key.clone())),
+        }
+    }
+
+    /// Replaces the value of an existing key, or adds the key at the end
+    pub fn set(&self, key: &Value, value: Value) -> Result<(), InterpreterError> {
+        let key = Key::try_from(key)?;
+        let mut map = self.0.borrow_mut();
+        match map.positions.get(&key) {
+            Some(&i) => map.entries[i].1 = value,
+            None => {
+                let i = map.entries.len();
+                map.positions.insert(key.clone(), i);
+                map.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the map has `key`, failing only if it can't be a key
+    pub fn has(&self, key: &Value) -> Result<bool, InterpreterError> {
+        Ok(self.0.borrow().positions.contains_key(&Key::try_from(key)?))
+    }
+
+    /// Removes `key` and returns its value, failing if the map doesn't
+    /// have it
+    pub fn remove(&self, key: &Value) -> Result<Value, InterpreterError> {
+        let mut map = self.0.borrow_mut();
+        let Some(i) = map.positions.remove(&Key::try_from(key)?) else {
+            return Err(InterpreterError::UndefinedKey(key.clone()));
+        };
+        let (_, value) = map.entries.remove(i);
+        for position in map.positions.values_mut() {
+            if *position > i {
+                *position -= 1;
+            }
+        }
+        Ok(value)
+    }
+
+    /// The keys in the order they were first set
+    pub fn keys(&self) -> Vec<Value> {
+        let map = self.0.borrow();
+        map.entries
+            .iter()
+            .map(|(key, _)| key.clone().into())
+            .collect()
+    }
+
+    /// The values in the order their keys were first set
+    pub fn values(&self) -> Vec<Value> {
+        let map = self.0.borrow();
+        map.entries.iter().map(|(_, value)| value.clone()).collect()
+    }
+
+    /// The bytes [`Map::keys`] and [`Map::values`] allocate, since they
+    /// copy strings but share lists and maps
+    pub(crate) fn copied_sizes(&self) -> (usize, usize) {
+        let map = self.0.borrow();
+        let slots = map.entries.len() * mem::size_of::<Value>();
+        let keys = map.entries.iter().map(|(key, _)| match key {
+            Key::String(str) => str.len(),
+            _ => 0,
+        });
+        let values = map.entries.iter().map(|(_, value)| match value {
+            Value::String(str) => str.len(),
+            _ => 0,
+        });
+        (slots + keys.sum::<usize>(), slots + values.sum::<usize>())
+    }
+
+    fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.0).cast()
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for Map {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Map({})", Value::Map(self.clone()))
+    }
+}
+
+/// A function implemented in Rust, such as `len`
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    pub(crate) name: &'static str,
+    pub(crate) arity: Arity,
+    pub(crate) function: Native,
+}
+
+/// The Rust side of a native function
+#[derive(Clone, Copy)]
+pub(crate) enum Native {
+    Pure(fn(&[Value]) -> Result<Value, InterpreterError>),
+    /// Also given the interpreter's console, such as `eprint`
+    Console(fn(&mut Console, &[Value]) -> Result<Value, InterpreterError>),
+    /// Also given the interpreter, to charge what it allocates to the heap
+    /// limit before allocating it, such as `repeat`
+    Interpreter(fn(&mut Interpreter, &[Value]) -> Result<Value, InterpreterError>),
+}
+
+/// How many arguments a native function takes
+#[derive(Clone, Copy)]
+pub(crate) enum Arity {
+    Exactly(usize),
+    /// Such as `format`, which takes any number after the template
+    AtLeast(usize),
+}
+
+impl NativeFunction {
+    /// The global the function is defined as
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Value],
+    ) -> Result<Value, InterpreterError> {
+        match self.arity {
+            Arity::Exactly(arity) if arguments.len() != arity => {
+                return Err(InterpreterError::WrongArity(arity, arguments.len()))
+            }
+            Arity::AtLeast(arity) if arguments.len() < arity => {
+                return Err(InterpreterError::TooFewArguments(arity, arguments.len()))
+            }
+            _ => {}
+        }
+        match self.function {
+            Native::Pure(function) => function(arguments),
+            Native::Console(function) => function(interpreter.console(), arguments),
+            Native::Interpreter(function) => function(interpreter, arguments),
+        }
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+/// A function written in Lox, along with the scope and file it was made in
+#[derive(Clone)]
+pub struct Function {
+    pub(crate) declaration: Rc<syntax::Function>,
+    pub(crate) closure: Rc<RefCell<Environment>>,
+    pub(crate) file: Option<Rc<Path>>,
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.declaration, &other.declaration)
+            && Rc::ptr_eq(&self.closure, &other.closure)
+    }
+}
+
+/// The closure often holds the function itself, so it is left out
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Function({} params)", self.declaration.params.len())
+    }
 }
 
 impl From<&str> for Value {
@@ -20,6 +477,12 @@ impl From<String> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
 impl From<f64> for Value {
     fn from(value: f64) -> Self {
         Self::Number(value)
@@ -32,13 +495,248 @@ impl From<bool> for Value {
     }
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(List::new(value))
+    }
+}
+
+impl Value {
+    /// Roughly how many bytes the value holds on the heap, `seen` are the
+    /// lists, maps and scopes already counted so shared ones count once
+    pub(crate) fn heap_size(&self, seen: &mut HashSet<*const ()>) -> usize {
+        match self {
+            Value::String(str) => str.capacity(),
+            Value::List(list) if !seen.insert(list.as_ptr()) => 0,
+            Value::List(list) => {
+                let elements = list.0.borrow();
+                elements.capacity() * mem::size_of::<Value>()
+                    + elements
+                        .iter()
+                        .map(|element| element.heap_size(seen))
+                        .sum::<usize>()
+            }
+            Value::Map(map) if !seen.insert(map.as_ptr()) => 0,
+            Value::Map(map) => {
+                let entries = map.0.borrow();
+                let keys: usize = entries.entries.iter().map(|(key, _)| key.heap_size()).sum();
+                let values: usize = entries
+                    .entries
+                    .iter()
+                    .map(|(_, value)| value.heap_size(seen))
+                    .sum();
+                entries.entries.capacity() * mem::size_of::<(Key, Value)>()
+                    + entries.positions.capacity() * mem::size_of::<(Key, usize)>()
+                    + 2 * keys
+                    + values
+            }
+            Value::Function(function) => Environment::heap_size(&function.closure, seen),
+            _ => 0,
+        }
+    }
+
+    /// What kind of value this is, as error messages name it
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Int(_) => "int",
+            Value::Number(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::NativeFunction(_) | Value::Function(_) => "function",
+        }
+    }
+
+    /// The value after its type, with strings quoted, for error messages
+    pub(crate) fn described(&self) -> String {
+        match self {
+            Value::String(str) => format!("string {str:?}"),
+            // these already say what they are
+            Value::Nil | Value::NativeFunction(_) | Value::Function(_) => self.to_string(),
+            value => format!("{} {value}", value.type_name()),
+        }
+    }
+
+    /// Writes the value, `outer` are the lists and maps being written
+    /// around it so one that contains itself is written as `[...]` or `{...}`
+    fn write(&self, f: &mut Formatter<'_>, outer: &mut Vec<*const ()>) -> fmt::Result {
         match self {
             Value::String(str) => f.write_str(str),
-            Value::Number(n) => f.write_fmt(format_args!("{n}")),
+            Value::Int(n) => f.write_fmt(format_args!("{n}")),
+            // always with a fraction or exponent, so that it reads as a float
+            Value::Number(n) => f.write_fmt(format_args!("{n:?}")),
             Value::Bool(b) => f.write_fmt(format_args!("{b}")),
             Value::Nil => f.write_str("nil"),
+            Value::List(list) if outer.contains(&list.as_ptr()) => f.write_str("[...]"),
+            Value::List(list) => {
+                outer.push(list.as_ptr());
+                f.write_str("[")?;
+                for (i, element) in list.0.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    element.write_nested(f, outer)?;
+                }
+                outer.pop();
+                f.write_str("]")
+            }
+            Value::Map(map) if outer.contains(&map.as_ptr()) => f.write_str("{...}"),
+            Value::Map(map) => {
+                outer.push(map.as_ptr());
+                f.write_str("{")?;
+                for (i, (key, value)) in map.0.borrow().entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    Value::from(key.clone()).write_nested(f, outer)?;
+                    f.write_str(": ")?;
+                    value.write_nested(f, outer)?;
+                }
+                outer.pop();
+                f.write_str("}")
+            }
+            Value::NativeFunction(function) => {
+                f.write_fmt(format_args!("<native fn {}>", function.name))
+            }
+            Value::Function(_) => f.write_str("<fn anonymous>"),
+        }
+    }
+
+    /// Inside a list or map, strings are quoted
+    fn write_nested(&self, f: &mut Formatter<'_>, outer: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::String(str) => f.write_fmt(format_args!("{str:?}")),
+            value => value.write(f, outer),
         }
     }
 }
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::InterpreterError;
+
+    use super::{Key, List, Map, Value};
+
+    #[test]
+    fn display_lists() {
+        let inner = Value::from(vec![Value::from("a"), Value::Nil]);
+        let list = Value::from(vec![1.into(), 2.5.into(), 3.0.into(), inner]);
+
+        assert_eq!("[1, 2.5, 3.0, [\"a\", nil]]", list.to_string());
+    }
+
+    #[test]
+    fn display_numbers() {
+        assert_eq!("-7", Value::from(-7).to_string());
+        assert_eq!("5.0", Value::from(5.0).to_string());
+        assert_eq!("1e-9", Value::from(1e-9).to_string());
+        assert_eq!("inf", Value::from(f64::INFINITY).to_string());
+    }
+
+    #[test]
+    fn display_list_containing_itself() {
+        let list = List::new(vec![1.into()]);
+        list.push(Value::List(list.clone()));
+
+        assert_eq!("[1, [...]]", Value::List(list).to_string());
+    }
+
+    #[test]
+    fn lists_are_shared() {
+        let list = List::new(vec![1.into(), 2.into()]);
+        let alias = list.clone();
+        alias.set(&0.into(), "one".into()).unwrap();
+
+        assert_eq!(Value::from("one"), list.get(&0.into()).unwrap());
+        assert_eq!(list, alias);
+        assert_ne!(list, List::new(vec!["one".into(), 2.into()]));
+    }
+
+    #[test]
+    fn index_errors() {
+        let list = List::new(vec![1.into(), 2.into()]);
+
+        assert!(matches!(
+            list.get(&(-1).into()),
+            Err(InterpreterError::NegativeIndex(-1))
+        ));
+        assert!(matches!(
+            list.get(&2.into()),
+            Err(InterpreterError::IndexOutOfBounds(2, 2))
+        ));
+        assert!(matches!(
+            list.get(&1.0.into()),
+            Err(InterpreterError::InvalidIndex(_))
+        ));
+        // the length in the message is the list's, not the positions'
+        assert!(matches!(
+            list.insert(&5.into(), 3.into()),
+            Err(InterpreterError::IndexOutOfBounds(5, 2))
+        ));
+        list.insert(&2.into(), 3.into()).unwrap();
+        assert_eq!(3, list.len());
+        assert_eq!(
+            "Invalid index float 1.0",
+            list.get(&1.0.into()).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn maps_keep_insertion_order() {
+        let map = Map::default();
+        map.set(&"b".into(), 1.into()).unwrap();
+        map.set(&"a".into(), 2.into()).unwrap();
+        map.set(&true.into(), Value::Nil).unwrap();
+        map.set(&"b".into(), 3.into()).unwrap();
+        assert_eq!(
+            "{\"b\": 3, \"a\": 2, true: nil}",
+            Value::Map(map.clone()).to_string()
+        );
+
+        assert_eq!(Value::from(3), map.remove(&"b".into()).unwrap());
+        assert_eq!(vec![Value::from("a"), true.into()], map.keys());
+        assert_eq!(Value::from(2), map.get(&"a".into()).unwrap());
+        assert_eq!(Value::Nil, map.get(&true.into()).unwrap());
+    }
+
+    #[test]
+    fn number_keys() {
+        let key = |value: Value| Key::try_from(&value).unwrap();
+        assert_eq!(key(0.into()), key((-0.0).into()));
+        assert_eq!(key(3.into()), key(3.0.into()));
+        assert_eq!(key(f64::NAN.into()), key((-f64::NAN).into()));
+        assert_ne!(key(0.5.into()), key(0.into()));
+        assert_ne!(key(1.into()), key("1".into()));
+        assert_eq!(Key::Number(2f64.powi(63)), key(2f64.powi(63).into()));
+
+        let map = Map::default();
+        map.set(&(-0.0).into(), "zero".into()).unwrap();
+        map.set(&f64::NAN.into(), "nan".into()).unwrap();
+        assert_eq!(Value::from("zero"), map.get(&0.into()).unwrap());
+        assert_eq!(Value::from("nan"), map.get(&(-f64::NAN).into()).unwrap());
+        assert_eq!(Value::from(0), map.keys()[0]);
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let map = Map::default();
+        let list = Value::from(vec![]);
+
+        assert!(matches!(
+            map.set(&list, Value::Nil),
+            Err(InterpreterError::InvalidKey(Value::List(_)))
+        ));
+        assert!(matches!(
+            map.get(&"missing".into()),
+            Err(InterpreterError::UndefinedKey(_))
+        ));
+    }
+}
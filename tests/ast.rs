@@ -0,0 +1,82 @@
+//! `lox ast`, and running a syntax tree another tool wrote as JSON
+
+use std::process::{Command, Output};
+
+use lox::syntax::{from_lisp, Stmt};
+
+use common::TempDir;
+
+mod common;
+
+fn lox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args)
+        .output()
+        .expect("run lox")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// The tree `lox ast` printed, read back from its s-expressions
+fn tree(output: &Output) -> Vec<Stmt> {
+    let lisp = stdout(output);
+    from_lisp(&lisp).unwrap_or_else(|err| panic!("{err}: {lisp}"))
+}
+
+#[test]
+fn ast_is_the_parsed_tree() {
+    let expected = from_lisp(r#"(print "Hello, World!")"#).unwrap();
+    assert_eq!(expected, tree(&lox(&["ast", "examples/hello.lox"])));
+}
+
+#[test]
+fn exported_json_runs_like_the_script() {
+    let dir = TempDir::new("ast");
+    let script = "examples/functions.lox";
+
+    let json = stdout(&lox(&["ast", "--format=json", script]));
+    assert!(json.starts_with("[{\"kind\":"), "{json}");
+    let exported = dir.write("functions.json", &json);
+    let exported = exported.to_str().unwrap();
+
+    assert_eq!(stdout(&lox(&[script])), stdout(&lox(&[exported])));
+    assert_eq!(tree(&lox(&["ast", script])), tree(&lox(&["ast", exported])));
+}
+
+#[test]
+fn dot_is_a_graph() {
+    let dot = stdout(&lox(&["ast", "--format=dot", "examples/hello.lox"]));
+    assert!(dot.starts_with("digraph ast {\n"), "{dot}");
+    assert!(dot.ends_with("}\n"), "{dot}");
+    assert!(dot.contains("n0 -> n1"), "{dot}");
+}
+
+#[test]
+fn invalid_trees_are_data_errors() {
+    let dir = TempDir::new("ast-invalid");
+    let tree = dir.write("invalid.json", r#"[{"kind":"Print"}]"#);
+
+    let output = lox(&[tree.to_str().unwrap()]);
+    assert_eq!(Some(65), output.status.code());
+    assert_eq!(
+        "AstError: Expected a field \"expression\" at [0]\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn unknown_formats_are_usage_errors() {
+    let output = lox(&["ast", "--format=xml", "examples/hello.lox"]);
+    assert_eq!(Some(64), output.status.code());
+    assert_eq!(
+        "Error: Unknown AST format \"xml\", expected lisp, json or dot\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
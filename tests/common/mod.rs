@@ -0,0 +1,61 @@
+//! Helpers shared by the integration tests, each test crate uses only some
+//! of them
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A directory of its own for a test's files, removed when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` keeps tests that run at the same time apart, the process id
+    /// keeps runs that overlap apart
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("lox-{name}-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes `contents` to `file` in the directory, returning its path
+    pub fn write(&self, file: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(file);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // a failure to clean up shouldn't hide why the test failed
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Frames a message for `lox lsp` or `lox dap`, behind the header both
+/// protocols use
+pub fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// The bodies of the framed messages `lox lsp` or `lox dap` wrote
+pub fn unframe(output: Vec<u8>) -> Vec<String> {
+    let mut output = String::from_utf8(output).unwrap();
+    let mut bodies = Vec::new();
+    while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+        let len: usize = header
+            .strip_prefix("Content-Length: ")
+            .and_then(|len| len.parse().ok())
+            .expect("Content-Length header");
+        bodies.push(rest[..len].to_string());
+        output = rest[len..].to_string();
+    }
+    bodies
+}
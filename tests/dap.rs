@@ -0,0 +1,68 @@
+//! Replays recorded transcripts through `lox dap`
+//!
+//! In a transcript `->` lines are sent to the adapter and `<-` lines are
+//! the messages expected back, in order.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use common::{frame, unframe};
+
+mod common;
+
+fn replay(transcript: &str) {
+    let mut sent = String::new();
+    let mut expected = Vec::new();
+    for line in transcript.lines() {
+        if let Some(request) = line.strip_prefix("-> ") {
+            sent.push_str(&frame(request));
+        } else if let Some(message) = line.strip_prefix("<- ") {
+            expected.push(message);
+        }
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg("dap")
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/examples"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("start lox dap");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(sent.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    assert_eq!(expected, unframe(output.stdout));
+}
+
+#[test]
+fn breakpoints_and_stepping() {
+    replay(include_str!("dap/breakpoints.txt"));
+}
+
+#[test]
+fn stop_on_entry() {
+    replay(include_str!("dap/stop_on_entry.txt"));
+}
+
+#[test]
+fn breakpoints_in_imported_modules() {
+    replay(include_str!("dap/modules.txt"));
+}
+
+#[test]
+fn scripts_cannot_read_the_protocol() {
+    replay(include_str!("dap/read_line.txt"));
+}
+
+#[test]
+fn stepping_through_calls() {
+    replay(include_str!("dap/calls.txt"));
+}
@@ -0,0 +1,112 @@
+//! Drives `lox debug` with scripted commands
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use common::TempDir;
+
+mod common;
+
+fn debug(script: &str, commands: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(["debug", script])
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/examples"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("start lox debug");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn breakpoint_finish_and_next() {
+    let output = debug(
+        "scopes.lox",
+        "break scopes.lox:6\ncontinue\nvars\nfinish\nnext\ncontinue\n",
+    );
+
+    let expected = "\
+scopes.lox:1: var a = \"global\";
+(lox) Breakpoint 1 at scopes.lox:6
+(lox) scopes.lox:6: print a; // expect: shadow
+(lox) scope 0
+  a = shadow
+scope 1
+  b = block
+scope 2
+  a = global
+(lox) shadow
+scopes.lox:8: print b; // expect: block
+(lox) block
+scopes.lox:10: print a; // expect: global
+(lox) global
+";
+    assert!(output.status.success());
+    assert_eq!(expected, String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn step_into_blocks_and_quit() {
+    let output = debug("scopes.lox", "step\nstep\nprint a\nprint b\nquit\n");
+
+    let expected = "\
+scopes.lox:1: var a = \"global\";
+(lox) scopes.lox:2: {
+(lox) scopes.lox:3: var b = \"block\";
+(lox) a = global
+(lox) Undefined variable 'b'
+(lox) ";
+    assert!(!output.status.success());
+    assert_eq!(expected, String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn end_of_input_runs_to_completion() {
+    let output = debug("scopes.lox", "break 6\n");
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("(lox) \nshadow\nblock\nglobal\n"));
+}
+
+#[test]
+fn breakpoints_in_imported_modules() {
+    let output = debug(
+        "modules.lox",
+        "break shapes.lox:6\nbreak modules.lox:6\ncontinue\ncontinue\ncontinue\nquit\n",
+    );
+
+    let expected = "\
+modules.lox:1: import \"modules/shapes.lox\" as shapes;
+(lox) Breakpoint 1 at shapes.lox:6
+(lox) Breakpoint 2 at modules.lox:6
+(lox) 4
+modules/shapes.lox:6: return width * height;
+(lox) 12
+modules.lox:6: print area(2, 5); // expect: 10
+(lox) modules/shapes.lox:6: return width * height;
+(lox) ";
+    assert_eq!(expected, String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn scripts_read_the_lines_after_commands() {
+    let dir = TempDir::new("debug");
+    let script = dir.write("read.lox", "print readLine();\nprint readLine();\n");
+
+    let output = debug(script.to_str().unwrap(), "continue\nhello\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("(lox) hello\nnil\n"));
+}
@@ -0,0 +1,94 @@
+//! Uses the library the way a host program would, through its public API
+//! only
+
+use lox::{
+    error::InterpreterError,
+    syntax::{Expr, StmtKind},
+    Lox, Value,
+};
+
+use common::TempDir;
+
+mod common;
+
+#[test]
+fn globals_persist_between_runs() {
+    let mut lox = Lox::new();
+    lox.run("var count = 1;").unwrap();
+    lox.run("count = count + 1;").unwrap();
+    assert_eq!(lox.get("count"), Some(Value::Int(2)));
+}
+
+#[test]
+fn errors_can_be_matched() {
+    let mut lox = Lox::new();
+    let err = lox.run("print 1 +;").unwrap_err();
+    assert!(matches!(err, InterpreterError::ParserError(_)));
+    let err = lox.run("print missing;").unwrap_err();
+    assert!(matches!(err, InterpreterError::UndefinedVariable(name) if name == "missing"));
+}
+
+#[test]
+fn scripts_can_be_parsed_without_running() {
+    let statements = lox::parse("var answer = 42;\nprint answer;").unwrap();
+    assert_eq!(2, statements.len());
+    assert!(matches!(
+        &statements[0].kind,
+        StmtKind::Var(name, Some(Expr::Literal(_))) if name == "answer"
+    ));
+    assert_eq!(2, statements[1].meta.row);
+    assert_eq!("(print `answer`)", statements[1].display_lisp().to_string());
+}
+
+#[test]
+fn version_matches_the_manifest() {
+    assert_eq!(env!("CARGO_PKG_VERSION"), lox::VERSION);
+}
+
+/// Runs a script on a thread with the default stack, which a host gets
+/// unless it asks for more, keeping what it printed and its error
+fn run_on_default_thread(source: String) -> (String, Option<String>) {
+    std::thread::spawn(move || {
+        let captured = lox::run_captured(&source);
+        (
+            captured.stdout,
+            captured.result.err().map(|err| err.to_string()),
+        )
+    })
+    .join()
+    .expect("no stack overflow")
+}
+
+#[test]
+fn runaway_recursion_fails_on_a_default_thread() {
+    let source = "var down = fun (n) { return n == 0 ? 0 : down(n - 1); };\n\
+                  print down(20);\nprint down(100000);";
+    let (stdout, err) = run_on_default_thread(source.into());
+    assert_eq!("0\n", stdout);
+    assert_eq!(Some("Nesting too deep".into()), err);
+}
+
+#[test]
+fn deep_nesting_fails_to_parse_on_a_default_thread() {
+    let source = format!("print {}1{};", "(".repeat(255), ")".repeat(255));
+    let (_, err) = run_on_default_thread(source);
+    assert!(
+        err.as_ref()
+            .is_some_and(|err| err.ends_with("ParserError: nesting too deep")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn a_file_run_before_can_be_imported() {
+    let dir = TempDir::new("embed");
+    let a = dir.write("a.lox", "var name = \"a\";\n");
+    let b = dir.write("b.lox", "from \"a.lox\" import name;\n");
+
+    let mut lox = Lox::new();
+    let results = [lox.run_file(a), lox.run_file(b)];
+    for result in results {
+        assert!(result.is_ok(), "{result:?}");
+    }
+    assert_eq!(lox.get("name"), Some(Value::String("a".into())));
+}
@@ -0,0 +1,41 @@
+//! The behaviour of the language is pinned by the scripts in `examples/`
+
+use std::process::Command;
+
+use common::TempDir;
+
+mod common;
+
+#[test]
+fn examples_meet_their_expectations() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(["test", "examples"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("run lox test");
+
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{report}");
+    assert!(report.contains("PASS examples/hello.lox"));
+}
+
+#[test]
+fn failures_are_reported_with_a_diff() {
+    let dir = TempDir::new("test");
+    dir.write("wrong.lox", "print 1; // expect: 2\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg("test")
+        .arg(dir.path())
+        .output()
+        .expect("run lox test");
+
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(Some(1), output.status.code());
+    assert!(
+        report.contains("wrong.lox\n  output:\n    - 2\n    + 1\n"),
+        "{report}"
+    );
+    assert!(report.ends_with("0 passed, 1 failed\n"));
+    assert_eq!("1 test failed\n", String::from_utf8_lossy(&output.stderr));
+}
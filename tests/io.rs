@@ -0,0 +1,42 @@
+//! The console natives, which `lox test` can't check because it closes
+//! stdin and treats anything on stderr as an error
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use common::TempDir;
+
+mod common;
+
+#[test]
+fn console_natives_use_stdin_and_stderr() {
+    let dir = TempDir::new("io");
+    let script = dir.write(
+        "console.lox",
+        "print readLine();\nprint readLine();\nprint readAll();\neprint(\"oops\");\nprint readLine();\n",
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg(&script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run lox");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"first\r\nsecond\nthe\nrest")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        "first\nsecond\nthe\nrest\nnil\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!("oops\n", String::from_utf8_lossy(&output.stderr));
+}
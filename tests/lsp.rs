@@ -0,0 +1,94 @@
+//! Pipes scripted JSON-RPC sessions through `lox lsp`
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use common::{frame, unframe};
+
+mod common;
+
+/// Runs the session and returns the bodies of the messages sent back
+fn session(messages: &[&str]) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("start lox lsp");
+    let stdin = child.stdin.as_mut().unwrap();
+    for message in messages {
+        stdin.write_all(frame(message).as_bytes()).unwrap();
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    unframe(output.stdout)
+}
+
+const OPEN: &str = r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","languageId":"lox","version":1,"text":"var greet = fun (name) {\n  print name;\n};\nvar x = 1;\nprint x + x;"}}}"#;
+
+#[test]
+fn initialize_and_shutdown() {
+    let responses = session(&[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{}}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ]);
+
+    assert_eq!(2, responses.len());
+    assert!(responses[0].starts_with(r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{"#));
+    assert!(responses[0].contains(r#""hoverProvider":true"#));
+    assert_eq!(r#"{"jsonrpc":"2.0","id":2,"result":null}"#, responses[1]);
+}
+
+#[test]
+fn diagnostics_follow_changes() {
+    let responses = session(&[
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","text":"print 1 +;"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.lox","version":2},"contentChanges":[{"text":"print 1 + 2;"}]}}"#,
+    ]);
+
+    assert_eq!(2, responses.len());
+    assert!(responses[0].contains(r#""method":"textDocument/publishDiagnostics""#));
+    assert!(responses[0]
+        .contains(r#""range":{"start":{"line":0,"character":9},"end":{"line":0,"character":10}}"#));
+    assert!(responses[1].ends_with(r#""diagnostics":[]}}"#));
+}
+
+#[test]
+fn hover_definition_and_references() {
+    let responses = session(&[
+        OPEN,
+        r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///a.lox"},"position":{"line":1,"character":9}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.lox"},"position":{"line":4,"character":10}}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/references","params":{"textDocument":{"uri":"file:///a.lox"},"position":{"line":3,"character":4},"context":{"includeDeclaration":false}}}"#,
+    ]);
+
+    assert_eq!(4, responses.len());
+    assert!(
+        responses[1].contains(r#""value":"(parameter) `name`\n\ndeclared at line 1, column 18""#)
+    );
+    assert_eq!(
+        r#"{"jsonrpc":"2.0","id":2,"result":{"uri":"file:///a.lox","range":{"start":{"line":3,"character":4},"end":{"line":3,"character":5}}}}"#,
+        responses[2]
+    );
+    assert_eq!(
+        r#"{"jsonrpc":"2.0","id":3,"result":[{"uri":"file:///a.lox","range":{"start":{"line":4,"character":6},"end":{"line":4,"character":7}}},{"uri":"file:///a.lox","range":{"start":{"line":4,"character":10},"end":{"line":4,"character":11}}}]}"#,
+        responses[3]
+    );
+}
+
+#[test]
+fn document_symbols() {
+    let responses = session(&[
+        OPEN,
+        r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///a.lox"}}}"#,
+    ]);
+
+    assert_eq!(
+        r#"{"jsonrpc":"2.0","id":1,"result":[{"name":"greet","kind":12,"range":{"start":{"line":0,"character":4},"end":{"line":0,"character":9}},"selectionRange":{"start":{"line":0,"character":4},"end":{"line":0,"character":9}}},{"name":"x","kind":13,"range":{"start":{"line":3,"character":4},"end":{"line":3,"character":5}},"selectionRange":{"start":{"line":3,"character":4},"end":{"line":3,"character":5}}}]}"#,
+        responses[1]
+    );
+}
@@ -0,0 +1,149 @@
+//! Generated input nested far deeper than anyone writes, which has to fail
+//! with a diagnostic rather than overflow the stack
+
+use std::process::{Command, Output};
+
+use common::TempDir;
+
+mod common;
+
+const DEEP: usize = 100_000;
+
+fn run(name: &str, source: &str) -> Output {
+    run_file(&format!("{name}.lox"), source)
+}
+
+fn run_file(file: &str, source: &str) -> Output {
+    let dir = TempDir::new(&format!("nesting-{file}"));
+    let script = dir.write(file, source);
+    Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg(&script)
+        .output()
+        .expect("run lox")
+}
+
+#[test]
+fn deep_expressions_fail_to_parse() {
+    let cases = [
+        (
+            "parens",
+            format!("print {}1{};", "(".repeat(DEEP), ")".repeat(DEEP)),
+        ),
+        ("unary", format!("print {}1;", "- ".repeat(DEEP))),
+        ("assignment", format!("var a; {}1;", "a = ".repeat(DEEP))),
+        (
+            "conditional",
+            format!("print {}1;", "true ? 1 : ".repeat(DEEP)),
+        ),
+        (
+            "then branches",
+            format!("print {}1{};", "true ? ".repeat(DEEP), " : 1".repeat(DEEP)),
+        ),
+        ("power", format!("print 2{};", " ** 1".repeat(DEEP))),
+        (
+            "lists",
+            format!("print {}{};", "[".repeat(DEEP), "]".repeat(DEEP)),
+        ),
+        ("calls", format!("print len{};", "(len".repeat(DEEP))),
+    ];
+    for (name, source) in cases {
+        let output = run(name, &source);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(Some(65), output.status.code(), "{name}: {stderr}");
+        assert!(
+            stderr.starts_with("[1:")
+                && stderr.trim_end().ends_with("ParserError: nesting too deep"),
+            "{name}: {stderr}"
+        );
+    }
+}
+
+#[test]
+fn long_operator_chains_fail_to_parse() {
+    // a left associative chain is parsed by a loop, but nests the tree
+    let cases = [
+        ("sum", format!("print 1{};", " + 1".repeat(DEEP))),
+        ("comma", format!("print 1{};", ", 1".repeat(DEEP))),
+        (
+            "index",
+            format!("var a = [];\nprint a{};", "[0]".repeat(DEEP)),
+        ),
+    ];
+    for (name, source) in cases {
+        let output = run(name, &source);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(Some(65), output.status.code(), "{name}: {stderr}");
+        assert!(
+            stderr.ends_with("ParserError: nesting too deep\n"),
+            "{name}: {stderr}"
+        );
+    }
+}
+
+#[test]
+fn long_flat_chains_run() {
+    // each operator nests the tree, but only as deep as the stack allows
+    let source = format!(
+        "print 1{};\nprint 1{};\nprint \"\"{};\n",
+        " + 1".repeat(299),
+        ", 2".repeat(299),
+        " + \"x\"".repeat(260),
+    );
+    let output = run("flat", &source);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        format!("300\n2\n{}\n", "x".repeat(260)),
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn deep_blocks_fail_to_parse() {
+    let source = format!("{}\nprint 1;\n{}", "{".repeat(DEEP), "}".repeat(DEEP));
+    let output = run("blocks", &source);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(Some(65), output.status.code());
+    // where it stops depends on how much stack each block takes
+    assert!(
+        stderr.starts_with("[1:") && stderr.ends_with("ParserError: nesting too deep\n"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn runaway_recursion_fails_to_run() {
+    let source = "var down = fun (n) {\n    return n == 0 ? 0 : down(n - 1);\n};\nprint down(500);\nprint down(1000000);\n";
+    let output = run("recursion", source);
+    assert_eq!(Some(70), output.status.code());
+    assert_eq!("0\n", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(
+        "Call depth limit of 1000 exceeded\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn deep_json_fails_to_read() {
+    let deep_grouping = format!(
+        "[{{\"kind\":\"Print\",\"expression\":{}{{\"kind\":\"Nil\"}}{}}}]",
+        "{\"kind\":\"Grouping\",\"expression\":".repeat(200_000),
+        "}".repeat(200_000)
+    );
+    let cases = [
+        ("arrays", "[".repeat(300_000)),
+        ("groupings", deep_grouping),
+    ];
+    for (name, source) in cases {
+        let output = run_file(&format!("{name}.json"), &source);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(Some(65), output.status.code(), "{name}: {stderr}");
+        assert!(
+            stderr.starts_with("AstError: Nesting too deep at "),
+            "{name}: {stderr}"
+        );
+    }
+}